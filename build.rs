@@ -0,0 +1,8 @@
+//! Code generation of the gRPC service, only with the grpc feature
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "grpc")]
+    tonic_prost_build::compile_protos("proto/ydcv.proto")?;
+
+    Ok(())
+}
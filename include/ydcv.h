@@ -0,0 +1,26 @@
+/* C API of ydcv-saki, built with `cargo build --release --lib --features ffi` */
+
+#ifndef YDCV_H
+#define YDCV_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Look up `word` (NUL-terminated UTF-8) and return the response as JSON, or
+ * NULL on failure with the reason in ydcv_last_error(). Sentences are
+ * translated instead. Free the result with ydcv_string_free(). */
+char *ydcv_lookup(const char *word);
+
+/* Free a string returned by ydcv_lookup(), doing nothing on NULL */
+void ydcv_string_free(char *s);
+
+/* Why the last lookup on this thread failed, or NULL. Valid until the next
+ * failure on this thread, not to be freed. */
+const char *ydcv_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* YDCV_H */
@@ -0,0 +1,227 @@
+//! Youdao account session and online wordbook sync
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, bail};
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::header::{COOKIE, REFERER};
+use serde::Deserialize;
+
+use crate::db::Db;
+use crate::http::SendRetrying;
+use crate::lang::{Direction, Lang};
+use crate::paths;
+use crate::ydresponse::YdResponse;
+
+const WORDBOOK_LIST: &str = "https://dict.youdao.com/wordbook/webapi/v2/word/list";
+const WORDBOOK_ADD: &str = "https://dict.youdao.com/wordbook/webapi/v2/ajax/add";
+
+/// Number of words fetched per page of the online wordbook
+const PAGE_SIZE: usize = 100;
+
+/// Tag given to words pulled from the online wordbook
+const SYNC_TAG: &str = "youdao";
+
+#[derive(Deserialize)]
+struct ListReply {
+    code: i32,
+    #[serde(default)]
+    msg: String,
+    data: Option<ListData>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListData {
+    total: usize,
+    item_list: Vec<ListItem>,
+}
+
+#[derive(Deserialize)]
+struct ListItem {
+    word: String,
+}
+
+#[derive(Deserialize)]
+struct AddReply {
+    code: i32,
+    #[serde(default)]
+    msg: String,
+}
+
+fn session_path() -> Result<PathBuf> {
+    Ok(paths::data_dir()?.join("youdao-session"))
+}
+
+/// Save the cookie header of a logged-in browser session
+pub fn login(cookie: &str) -> Result<()> {
+    let cookie = cookie.trim();
+    if cookie.is_empty() {
+        bail!("Empty cookie, copy the Cookie header of dict.youdao.com from your browser");
+    }
+
+    let path = session_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&path)?;
+    writeln!(file, "{cookie}")?;
+
+    println!("Saved Youdao session to {}", path.display());
+    Ok(())
+}
+
+pub fn logout() -> Result<()> {
+    let path = session_path()?;
+    if path.is_file() {
+        fs::remove_file(&path)?;
+    }
+
+    Ok(())
+}
+
+fn session() -> Result<String> {
+    let path = session_path()?;
+    fs::read_to_string(&path)
+        .map(|x| x.trim().to_string())
+        .with_context(|| "Not logged in, run `ydcv account login` first")
+}
+
+fn authorized(req: RequestBuilder, cookie: &str) -> RequestBuilder {
+    req.header(COOKIE, cookie)
+        .header(REFERER, "https://dict.youdao.com/")
+}
+
+fn online_words(client: &Client, cookie: &str) -> Result<Vec<String>> {
+    let mut words = vec![];
+
+    loop {
+        let reply: ListReply = authorized(client.get(WORDBOOK_LIST), cookie)
+            .query(&[
+                ("limit", PAGE_SIZE.to_string()),
+                ("offset", words.len().to_string()),
+                ("sort", "time".to_string()),
+            ])
+            .send_retrying()?
+            .error_for_status()?
+            .json()?;
+
+        let Some(data) = reply.data.filter(|_| reply.code == 0) else {
+            bail!(
+                "Failed to list online wordbook ({}): {}, the session may have expired",
+                reply.code,
+                reply.msg
+            );
+        };
+
+        let n = data.item_list.len();
+        words.extend(data.item_list.into_iter().map(|x| x.word));
+        if n == 0 || words.len() >= data.total {
+            break;
+        }
+    }
+
+    Ok(words)
+}
+
+/// Language of the word in the online wordbook, the side of its lookup
+/// other than Chinese
+fn online_lang(resp: &YdResponse) -> Lang {
+    let dir = match resp.lookup() {
+        Some(x) => Direction {
+            from: x.from,
+            to: x.to,
+        },
+        None => Direction::detect(resp.query()),
+    };
+    if dir.from == Lang::Zh {
+        dir.to
+    } else {
+        dir.from
+    }
+}
+
+fn add_online(client: &Client, cookie: &str, word: &str, lang: Lang) -> Result<()> {
+    let reply: AddReply = authorized(client.get(WORDBOOK_ADD), cookie)
+        .query(&[("lan", lang.code()), ("word", word)])
+        .send_retrying()?
+        .error_for_status()?
+        .json()?;
+
+    if reply.code != 0 {
+        bail!(
+            "Failed to add {word} online ({}): {}",
+            reply.code,
+            reply.msg
+        );
+    }
+
+    Ok(())
+}
+
+/// Sync the local wordbook with the online one.
+///
+/// Words missing on either side are added there, removals are not synced.
+pub fn sync(
+    db: &Db,
+    client: &Client,
+    lookup: &dyn Fn(&str) -> Result<YdResponse>,
+    pull: bool,
+    push: bool,
+) -> Result<()> {
+    let cookie = session()?;
+    let online = online_words(client, &cookie)?;
+    let local = db.wordbook()?;
+
+    if pull {
+        let local_words = local
+            .iter()
+            .map(|x| x.word.as_str())
+            .collect::<HashSet<_>>();
+        let mut pulled = 0;
+        for word in online.iter().filter(|x| !local_words.contains(x.as_str())) {
+            let resp = lookup(word)?;
+            if resp.has_result() && db.wordbook_add(&resp)? {
+                db.wordbook_tag(resp.query(), &[SYNC_TAG.to_string()])?;
+                pulled += 1;
+            }
+        }
+        println!("Pulled {pulled} word(s) from Youdao wordbook");
+    }
+
+    if push {
+        let online_words = online.iter().map(|x| x.as_str()).collect::<HashSet<_>>();
+        let mut pushed = 0;
+        for entry in local
+            .iter()
+            .filter(|x| !online_words.contains(x.word.as_str()))
+        {
+            add_online(client, &cookie, &entry.word, online_lang(&entry.snapshot))?;
+            pushed += 1;
+        }
+        println!("Pushed {pushed} word(s) to Youdao wordbook");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fake;
+
+    #[test]
+    fn test_online_lang() {
+        assert_eq!(Lang::En, online_lang(&fake("hello", "你好")));
+        assert_eq!(Lang::En, online_lang(&fake("你好", "hello")));
+        assert_eq!(Lang::Ja, online_lang(&fake("こんにちは", "你好")));
+    }
+}
@@ -0,0 +1,225 @@
+//! systemd socket activation of `ydcv daemon` and `ydcv serve`, so that they
+//! only start on the first query and exit once idle
+
+use std::io::{self, ErrorKind};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::fd::{FromRawFd, OwnedFd};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::process::exit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+
+/// The first socket passed by systemd, see sd_listen_fds(3)
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+
+/// How often a listener is checked for connections while it may exit
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Connections answered at once, the ones over it are closed right away
+const MAX_CONNECTIONS: usize = 64;
+
+/// The socket passed by systemd, if started by socket activation, which must
+/// be called before any thread is started
+#[cfg(unix)]
+pub fn listener<T: From<OwnedFd>>() -> Option<T> {
+    let pid = std::env::var("LISTEN_PID").ok();
+    let fds = std::env::var("LISTEN_FDS").ok();
+    // SAFETY: no other thread reads the environment yet, as documented;
+    // they're unset so that the programs it runs don't take the sockets
+    unsafe {
+        for name in ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+            std::env::remove_var(name);
+        }
+    }
+    if pid?.parse() != Ok(std::process::id()) || !fds?.parse::<i32>().is_ok_and(|x| x >= 1) {
+        return None;
+    }
+
+    // SAFETY: systemd passes the sockets open from LISTEN_FDS_START on, to
+    // this very process as checked with LISTEN_PID, and nothing else owns them
+    Some(T::from(unsafe { OwnedFd::from_raw_fd(LISTEN_FDS_START) }))
+}
+
+#[cfg(not(unix))]
+pub fn listener<T>() -> Option<T> {
+    None
+}
+
+/// Requests being answered, and when the last one was
+struct Activity {
+    busy: usize,
+    last: Instant,
+}
+
+/// Exits the process after `timeout` without requests, from the thread
+/// accepting connections so that none is accepted and then dropped
+pub struct IdleExit {
+    timeout: Duration,
+    activity: Mutex<Activity>,
+}
+
+/// A request being answered, putting off the exit until it's dropped
+pub struct Busy<'a>(&'a IdleExit);
+
+impl Drop for Busy<'_> {
+    fn drop(&mut self) {
+        let mut activity = self.0.activity();
+        activity.busy -= 1;
+        activity.last = Instant::now();
+    }
+}
+
+impl IdleExit {
+    pub fn new(timeout: Duration) -> Self {
+        IdleExit {
+            timeout,
+            activity: Mutex::new(Activity {
+                busy: 0,
+                last: Instant::now(),
+            }),
+        }
+    }
+
+    /// Mark a request as being answered, taken right after accepting it
+    pub fn busy(&self) -> Busy<'_> {
+        self.activity().busy += 1;
+        Busy(self)
+    }
+
+    /// A connection accepted by `accept` of a nonblocking listener, exiting
+    /// instead once idle; the connections left waiting start the process
+    /// again
+    pub fn accept<S>(&self, mut accept: impl FnMut() -> io::Result<S>) -> io::Result<S> {
+        loop {
+            match accept() {
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    let activity = self.activity();
+                    let idle = activity.last.elapsed();
+                    if activity.busy == 0 && idle >= self.timeout {
+                        info!("Exiting after {}s idle", idle.as_secs());
+                        exit(0);
+                    }
+                    drop(activity);
+                    thread::sleep(ACCEPT_INTERVAL);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn activity(&self) -> MutexGuard<'_, Activity> {
+        self.activity.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A listener of TCP or unix sockets
+pub trait Listener: Sync {
+    type Stream: Send;
+
+    /// The next connection, blocking even if the listener isn't
+    fn accept(&self) -> io::Result<Self::Stream>;
+
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+}
+
+impl Listener for TcpListener {
+    type Stream = TcpStream;
+
+    fn accept(&self) -> io::Result<TcpStream> {
+        let (stream, _) = TcpListener::accept(self)?;
+        stream.set_nonblocking(false)?;
+        Ok(stream)
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        TcpListener::set_nonblocking(self, nonblocking)
+    }
+}
+
+#[cfg(unix)]
+impl Listener for UnixListener {
+    type Stream = UnixStream;
+
+    fn accept(&self) -> io::Result<UnixStream> {
+        let (stream, _) = UnixListener::accept(self)?;
+        stream.set_nonblocking(false)?;
+        Ok(stream)
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        UnixListener::set_nonblocking(self, nonblocking)
+    }
+}
+
+/// Answer each connection to `listener` with `answer` on a thread of its
+/// own, forever or until `idle` exits, so that a slow client doesn't hold up
+/// the others
+pub fn serve_each<L: Listener>(
+    listener: &L,
+    idle: Option<&IdleExit>,
+    answer: impl Fn(L::Stream) + Sync,
+) -> io::Result<()> {
+    if idle.is_some() {
+        listener.set_nonblocking(true)?;
+    }
+    let connections = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        loop {
+            let accepted = match idle {
+                Some(idle) => idle.accept(|| listener.accept()),
+                None => listener.accept(),
+            };
+            let stream = match accepted {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("Failed to accept a connection: {e}");
+                    continue;
+                }
+            };
+            if connections.fetch_add(1, Ordering::Relaxed) >= MAX_CONNECTIONS {
+                connections.fetch_sub(1, Ordering::Relaxed);
+                warn!("Closing a connection over {MAX_CONNECTIONS} at once");
+                continue;
+            }
+            let busy = idle.map(IdleExit::busy);
+            let (connections, answer) = (&connections, &answer);
+            scope.spawn(move || {
+                answer(stream);
+                drop(busy);
+                connections.fetch_sub(1, Ordering::Relaxed);
+            });
+        }
+    })
+}
+
+/// systemd user units starting `ydcv <command> <args>` on the first
+/// connection to `listen`, a path or address as of `ListenStream=`
+pub fn systemd_units(exe: &str, command: &str, listen: &str, args: &str) -> String {
+    format!(
+        "# ~/.config/systemd/user/ydcv-{command}.socket
+[Unit]
+Description=Socket of ydcv {command}
+
+[Socket]
+ListenStream={listen}
+
+[Install]
+WantedBy=sockets.target
+
+# ~/.config/systemd/user/ydcv-{command}.service
+[Unit]
+Description=ydcv {command}, started on the first query
+Requires=ydcv-{command}.socket
+
+[Service]
+ExecStart={exe} {command} {args}
+"
+    )
+}
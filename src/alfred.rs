@@ -0,0 +1,118 @@
+//! Script Filter JSON of Alfred and Raycast, so that launcher workflows on
+//! macOS can run `ydcv` without glue scripts
+
+use serde::Serialize;
+
+use crate::ydresponse::YdResponse;
+
+/// What a Script Filter prints for Alfred to list
+#[derive(Serialize)]
+struct ScriptFilter {
+    items: Vec<Item>,
+}
+
+/// A row of the list of Alfred
+#[derive(Serialize)]
+struct Item {
+    title: String,
+    subtitle: String,
+    /// What the workflow gets when the row is chosen
+    arg: String,
+    valid: bool,
+    /// Query to fill in when the row is tabbed to, e.g. a suggested spelling
+    #[serde(skip_serializing_if = "Option::is_none")]
+    autocomplete: Option<String>,
+}
+
+impl Item {
+    /// A row for information only, that can't be chosen
+    fn info(title: String, subtitle: String) -> Self {
+        Item {
+            title,
+            subtitle,
+            arg: String::new(),
+            valid: false,
+            autocomplete: None,
+        }
+    }
+}
+
+/// Script Filter JSON of `resp` with a row per sense, or per suggested
+/// spelling if it has no result
+pub fn script_filter(resp: &YdResponse) -> String {
+    let senses = if !resp.senses().is_empty() {
+        resp.senses()
+            .iter()
+            .map(|x| {
+                let pos = x.pos.as_ref().map(|x| format!("{x} ")).unwrap_or_default();
+                format!("{pos}{}{}", x.labels.join(""), x.meaning)
+            })
+            .collect()
+    } else if !resp.explains().is_empty() {
+        resp.explains().to_vec()
+    } else {
+        resp.translation().to_vec()
+    };
+
+    let subtitle = match resp.phonetic().filter(|x| !x.is_empty()) {
+        Some(phonetic) => format!("{} [{phonetic}]", resp.query()),
+        None => resp.query().to_string(),
+    };
+    let items = if !senses.is_empty() {
+        senses
+            .into_iter()
+            .map(|x| Item {
+                title: x.clone(),
+                subtitle: subtitle.clone(),
+                arg: x,
+                valid: true,
+                autocomplete: None,
+            })
+            .collect()
+    } else if !resp.suggestions().is_empty() {
+        resp.suggestions()
+            .iter()
+            .map(|x| Item {
+                autocomplete: Some(x.clone()),
+                ..Item::info(x.clone(), format!("Did you mean {x}?"))
+            })
+            .collect()
+    } else {
+        vec![Item::info(
+            format!("No result for {}", resp.query()),
+            String::new(),
+        )]
+    };
+
+    to_json(items)
+}
+
+/// Script Filter JSON telling why looking up `word` failed
+pub fn error(word: &str, err: &anyhow::Error) -> String {
+    to_json(vec![Item::info(
+        format!("Failed to look up {word}"),
+        format!("{err:#}"),
+    )])
+}
+
+fn to_json(items: Vec<Item>) -> String {
+    serde_json::to_string(&ScriptFilter { items }).expect("serializing strings can't fail")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fake;
+
+    #[test]
+    fn test_script_filter() {
+        assert_eq!(
+            r#"{"items":[{"title":"你好","subtitle":"hello","arg":"你好","valid":true}]}"#,
+            script_filter(&fake("hello", "你好"))
+        );
+        assert_eq!(
+            r#"{"items":[{"title":"Failed to look up hello","subtitle":"No result from YD","arg":"","valid":false}]}"#,
+            error("hello", &crate::error::YdcvError::NoResult.into())
+        );
+    }
+}
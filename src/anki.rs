@@ -0,0 +1,124 @@
+//! export of the wordbook as an Anki deck
+
+use std::io::Write;
+
+use anyhow::Result;
+use htmlescape::encode_minimal;
+
+use crate::db::{Db, WordbookEntry};
+use crate::ydresponse::Accent;
+
+/// Write all wordbook entries as Front/Back/Phonetic/Example/Tags notes.
+///
+/// The file starts with Anki's header comments, so the separator, the html
+/// setting and the tags column are picked up automatically on import. The
+/// phonetic is the one of `accent` where there is one.
+pub fn export(
+    db: &Db,
+    out: &mut dyn Write,
+    delimiter: u8,
+    html: bool,
+    accent: Accent,
+) -> Result<()> {
+    writeln!(
+        out,
+        "#separator:{}",
+        if delimiter == b'\t' { "Tab" } else { "Comma" }
+    )?;
+    writeln!(out, "#html:{html}")?;
+    writeln!(out, "#columns:Front;Back;Phonetic;Example;Tags")?;
+    writeln!(out, "#tags column:5")?;
+
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .from_writer(out);
+
+    for entry in db.wordbook()? {
+        writer.write_record(note(&entry, html, accent))?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn note(entry: &WordbookEntry, html: bool, accent: Accent) -> [String; 5] {
+    let resp = &entry.snapshot;
+    let escape = |s: &str| {
+        if html {
+            encode_minimal(s)
+        } else {
+            s.to_owned()
+        }
+    };
+
+    let mut back = if resp.explains().is_empty() {
+        escape(&resp.translation().join("; "))
+    } else {
+        resp.explains()
+            .iter()
+            .map(|x| escape(x))
+            .collect::<Vec<_>>()
+            .join(if html { "<br>" } else { "\n" })
+    };
+    if let Some(note) = &entry.note {
+        if html {
+            back.push_str(&format!(
+                r#"<br><span class="note">{}</span>"#,
+                escape(note)
+            ));
+        } else {
+            back.push_str(&format!("\n{note}"));
+        }
+    }
+
+    let phonetic = resp
+        .accent_phonetic(accent)
+        .or_else(|| resp.phonetic())
+        .map(|x| format!("/{x}/"))
+        .unwrap_or_default();
+
+    let example = match (resp.examples().first(), resp.web().first()) {
+        (Some(x), _) => Some((x.sentence.as_str(), x.translation.clone())),
+        (None, Some(x)) => Some((x.key.as_str(), x.value.join("；"))),
+        (None, None) => None,
+    }
+    .map(|(sentence, translation)| {
+        if html {
+            format!("<i>{}</i> {}", escape(sentence), escape(&translation))
+        } else {
+            format!("{sentence} {translation}")
+        }
+    })
+    .unwrap_or_default();
+
+    [
+        escape(&entry.word),
+        back,
+        escape(&phonetic),
+        example,
+        entry.tags.join(" "),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fake;
+
+    #[test]
+    fn test_export_tsv() {
+        let db = Db::open_in_memory().unwrap();
+        db.wordbook_add(&fake("a<b", "甲")).unwrap();
+        db.wordbook_tag("a<b", &["coding".into()]).unwrap();
+
+        let mut out = vec![];
+        export(&db, &mut out, b'\t', true, Accent::Us).unwrap();
+
+        assert_eq!(
+            "#separator:Tab\n#html:true\n#columns:Front;Back;Phonetic;Example;Tags\n\
+             #tags column:5\na&lt;b\t甲\t\t\tcoding\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! pronunciation audio download, caching and playback
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+#[cfg(feature = "play-audio")]
+use std::{fs::File, io::BufReader};
+
+use anyhow::{Context, Result, bail};
+use log::{debug, warn};
+use reqwest::blocking::Client;
+use reqwest::header::REFERER;
+
+use crate::http::SendRetrying;
+use crate::paths;
+use crate::ydresponse::Accent;
+
+/// Known external players in order of auto-detection, with arguments
+/// placed before the file
+const PLAYERS: &[(&str, &[&str])] = &[
+    ("mpv", &["--no-video", "--really-quiet"]),
+    ("ffplay", &["-nodisp", "-autoexit", "-loglevel", "quiet"]),
+    ("mpg123", &["-q"]),
+    ("afplay", &[]),
+    ("cvlc", &["--play-and-exit", "--quiet"]),
+    ("paplay", &[]),
+];
+
+/// Download the MP3 pronunciation of `word`
+pub fn fetch(client: &Client, word: &str, accent: Accent) -> Result<Vec<u8>> {
+    let bytes = client
+        .get(accent.speech_url(word))
+        .header(REFERER, "https://www.youdao.com")
+        .send_retrying()?
+        .error_for_status()?
+        .bytes()?;
+
+    if bytes.is_empty() {
+        bail!("No pronunciation audio for {word}");
+    }
+
+    Ok(bytes.to_vec())
+}
+
+/// Find an executable in `PATH`
+fn which(name: &str) -> Option<PathBuf> {
+    let name = if cfg!(windows) {
+        format!("{name}.exe")
+    } else {
+        name.to_string()
+    };
+
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(&name))
+        .find(|path| path.is_file())
+}
+
+/// Build the command line of `player` for playing `path`.
+///
+/// `player` is a known player name, `auto` (or `None`) for the first known
+/// player found in `PATH`, or a custom command where `{}` stands for the file.
+fn player_command(player: Option<&str>, path: &Path) -> Result<Vec<String>> {
+    let path = path.to_string_lossy().into_owned();
+    let known = |name: &str, args: &[&str]| {
+        let mut cmd = vec![name.to_string()];
+        cmd.extend(args.iter().map(|x| x.to_string()));
+        cmd.push(path.clone());
+        cmd
+    };
+
+    match player.map(|x| x.trim()).filter(|x| !x.is_empty()) {
+        None | Some("auto") => PLAYERS
+            .iter()
+            .find(|(name, _)| which(name).is_some())
+            .map(|(name, args)| known(name, args))
+            .with_context(|| {
+                format!(
+                    "No audio player found, install one of {} or set audio.player in the config",
+                    PLAYERS
+                        .iter()
+                        .map(|(name, _)| *name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }),
+        Some(player) => match PLAYERS.iter().find(|(name, _)| *name == player) {
+            Some((name, args)) => Ok(known(name, args)),
+            None => {
+                let mut cmd = player
+                    .split_whitespace()
+                    .map(|x| x.replace("{}", &path))
+                    .collect::<Vec<_>>();
+                if !player.contains("{}") {
+                    cmd.push(path);
+                }
+                Ok(cmd)
+            }
+        },
+    }
+}
+
+/// Play an audio file in-process on the default output device
+#[cfg(feature = "play-audio")]
+fn play_embedded(path: &Path) -> Result<()> {
+    let mut sink = rodio::DeviceSinkBuilder::open_default_sink()?;
+    sink.log_on_drop(false);
+
+    let player = rodio::play(sink.mixer(), BufReader::new(File::open(path)?))?;
+    player.sleep_until_end();
+
+    Ok(())
+}
+
+/// Play an audio file with an external player, see `player_command`.
+///
+/// With the `play-audio` feature, `auto` (or `None`) plays in-process and
+/// only looks for an external player if that fails.
+pub fn play_file(path: &Path, player: Option<&str>) -> Result<()> {
+    #[cfg(feature = "play-audio")]
+    if matches!(player.map(|x| x.trim()), None | Some("" | "auto")) {
+        match play_embedded(path) {
+            Ok(()) => return Ok(()),
+            Err(e) => debug!("Falling back to an external player: {e}"),
+        }
+    }
+
+    let cmd = player_command(player, path)?;
+    debug!("Playing audio with {cmd:?}");
+
+    let status = Command::new(&cmd[0])
+        .args(&cmd[1..])
+        .stdin(Stdio::null())
+        .status()
+        .with_context(|| format!("Failed to run {}", cmd[0]))?;
+
+    if !status.success() {
+        bail!("{} exited with {status}", cmd[0]);
+    }
+
+    Ok(())
+}
+
+/// File name of a cached pronunciation, with anything but ASCII
+/// alphanumerics and `-` hex-escaped so every word maps to a distinct file
+fn file_name(word: &str) -> String {
+    let mut name = String::new();
+    for b in word.bytes() {
+        if b.is_ascii_alphanumeric() || b == b'-' {
+            name.push(char::from(b));
+        } else {
+            name.push_str(&format!("_{b:02x}"));
+        }
+    }
+    name.push_str(".mp3");
+
+    name
+}
+
+/// Location of the cached pronunciation of `word`
+pub fn cache_path(word: &str, accent: Accent) -> Result<PathBuf> {
+    Ok(paths::cache_dir()?
+        .join("audio")
+        .join(accent.name())
+        .join(file_name(word)))
+}
+
+/// Path to the pronunciation of `word`, downloading it into the cache if
+/// it's not there yet
+pub fn cached_or_fetch(client: &Client, word: &str, accent: Accent) -> Result<PathBuf> {
+    let path = cache_path(word, accent)?;
+    if path.is_file() {
+        return Ok(path);
+    }
+
+    let bytes = fetch(client, word, accent)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, bytes)?;
+
+    Ok(path)
+}
+
+/// Copy the pronunciations of `words` into `dir`, downloading the missing
+/// ones. Failures are reported and skipped. Returns the number of copied files.
+pub fn fetch_all(client: &Client, words: &[String], accent: Accent, dir: &Path) -> Result<usize> {
+    fs::create_dir_all(dir)?;
+
+    let mut copied = 0;
+    for word in words {
+        let path = match cached_or_fetch(client, word, accent) {
+            Ok(path) => path,
+            Err(e) => {
+                warn!("Failed to download pronunciation of {word}: {e}");
+                continue;
+            }
+        };
+        fs::copy(&path, dir.join(file_name(word)))?;
+        copied += 1;
+    }
+
+    Ok(copied)
+}
+
+/// Play the pronunciation of `word`, from the cache when possible.
+///
+/// With the `tts` feature, the word is synthesized locally if the audio
+/// can't be downloaded.
+pub fn speak(client: &Client, word: &str, accent: Accent, player: Option<&str>) -> Result<()> {
+    match cached_or_fetch(client, word, accent) {
+        Ok(path) => play_file(&path, player),
+        #[cfg(feature = "tts")]
+        Err(e) => {
+            debug!("Falling back to local speech synthesis: {e}");
+            crate::tts::speak(word, crate::tts::Voice::English(accent))
+        }
+        #[cfg(not(feature = "tts"))]
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!("hello.mp3", file_name("hello"));
+        assert_eq!("give_20up.mp3", file_name("give up"));
+        assert_eq!("a_2fb.mp3", file_name("a/b"));
+        assert_eq!("_e4_bd_a0.mp3", file_name("你"));
+    }
+
+    #[test]
+    fn test_player_command() {
+        let path = Path::new("/tmp/a.mp3");
+        assert_eq!(
+            vec!["mpv", "--no-video", "--really-quiet", "/tmp/a.mp3"],
+            player_command(Some("mpv"), path).unwrap()
+        );
+        assert_eq!(
+            vec!["play", "-q", "/tmp/a.mp3"],
+            player_command(Some("play -q"), path).unwrap()
+        );
+        assert_eq!(
+            vec!["vlc", "/tmp/a.mp3", "vlc://quit"],
+            player_command(Some("vlc {} vlc://quit"), path).unwrap()
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Compact output for status bars such as Waybar and Polybar
+
+use htmlescape::encode_minimal;
+use serde::Serialize;
+
+use crate::formatters::HtmlFormatter;
+use crate::ydresponse::{ExplainOptions, YdResponse};
+
+/// What a Waybar custom module reads with `"return-type": "json"`
+#[derive(Serialize)]
+struct WaybarModule {
+    text: String,
+    tooltip: String,
+    class: &'static str,
+}
+
+/// `resp` on one line: the word, its phonetic and translation
+pub fn line(resp: &YdResponse) -> String {
+    let translation = match resp.translation() {
+        [] => resp.explains().first().cloned().unwrap_or_default(),
+        translation => translation.join("; "),
+    };
+    one_line(resp, &translation)
+}
+
+/// `resp` on one line with only its first translation, see `--short`
+pub fn short(resp: &YdResponse) -> String {
+    let translation = resp
+        .translation()
+        .first()
+        .or(resp.explains().first())
+        .map(String::as_str)
+        .unwrap_or_default();
+    one_line(resp, translation)
+}
+
+/// The word of `resp`, its phonetic and `translation`
+fn one_line(resp: &YdResponse, translation: &str) -> String {
+    let mut line = resp.query().to_string();
+    if let Some(phonetic) = resp.phonetic().filter(|x| !x.is_empty()) {
+        line.push_str(&format!(" [{phonetic}]"));
+    }
+    if !translation.is_empty() {
+        line.push_str(": ");
+        line.push_str(translation);
+    }
+
+    line.replace('\n', " ")
+}
+
+/// Waybar module JSON of `resp`, with its line as text and the full
+/// explanation in Pango markup as tooltip
+pub fn waybar(resp: &YdResponse, options: &ExplainOptions) -> String {
+    to_json(WaybarModule {
+        text: encode_minimal(&line(resp)),
+        tooltip: resp.explain_with(&HtmlFormatter::new(false), options),
+        class: if resp.has_result() {
+            "ydcv"
+        } else {
+            "no-result"
+        },
+    })
+}
+
+/// Waybar module JSON telling why looking up `word` failed
+pub fn waybar_error(word: &str, err: &anyhow::Error) -> String {
+    to_json(WaybarModule {
+        text: encode_minimal(word),
+        tooltip: encode_minimal(&format!("{err:#}")),
+        class: "error",
+    })
+}
+
+fn to_json(module: WaybarModule) -> String {
+    serde_json::to_string(&module).expect("serializing strings can't fail")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fake;
+
+    #[test]
+    fn test_line() {
+        assert_eq!("hello: 你好", line(&fake("hello", "你好")));
+        assert_eq!("hello: 你好", short(&fake("hello", "你好")));
+        assert_eq!(
+            r#"{"text":"a&lt;b: 你好","tooltip":"<u>a&lt;b</u>\n<span color=\"navy\">  Translation:</span>\n    你好","class":"ydcv"}"#,
+            waybar(&fake("a<b", "你好"), &ExplainOptions::default())
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! A Telegram bot long-polling for messages and answering each with the
+//! explanation of its text, so that the same dictionary works on the phone
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::thread::sleep;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use log::warn;
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+use crate::db::Db;
+use crate::formatters::{MarkdownFormatter, PlainFormatter, Style, escape_markdown};
+use crate::lookup_maybe_cached;
+use crate::ydclient::YdClient;
+use crate::ydresponse::{ExplainOptions, YdResponse};
+
+/// Seconds a getUpdates request waits for messages
+const POLL_TIMEOUT: u64 = 50;
+
+/// How long to wait before polling again after a failure
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Most characters of the text of a message
+const MESSAGE_LIMIT: usize = 4096;
+
+const HELP: &str = "Send a word or a sentence to look it up.";
+
+/// Variable holding the token when no token file is given
+const TOKEN_VAR: &str = "YDCV_TELEGRAM_TOKEN";
+
+/// What every method of the Bot API answers
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    chat: Chat,
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+/// Explanation of `resp` in MarkdownV2, with the `Style::Plain` segments
+/// escaped too
+fn markdown(resp: &YdResponse, options: &ExplainOptions) -> String {
+    let fmt = MarkdownFormatter::new(false);
+    resp.segments(options)
+        .iter()
+        .map(|line| {
+            line.iter()
+                .map(|x| match x.style {
+                    Style::Plain => escape_markdown(&x.text),
+                    _ => x.render(&fmt).into_owned(),
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// What lookups are answered with, and where the bot is
+struct Bot<'a, C> {
+    http: &'a Client,
+    /// Bot API URL with the token, which methods are appended to
+    api: String,
+    client: &'a C,
+    db: &'a Db,
+    explain: &'a ExplainOptions,
+    /// Chats allowed to look up, all if empty
+    allowed_chats: &'a [i64],
+}
+
+impl<C: YdClient> Bot<'_, C> {
+    /// Result of the Bot API method `method`
+    fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        // errors leave out the URL, which has the token in it
+        let resp: ApiResponse<T> = self
+            .http
+            .post(format!("{}/{method}", self.api))
+            .json(&params)
+            .timeout(Duration::from_secs(POLL_TIMEOUT + 10))
+            .send()
+            .and_then(|x| x.json())
+            .map_err(|e| e.without_url())?;
+        match resp.result {
+            Some(result) if resp.ok => Ok(result),
+            _ => bail!(
+                "Telegram {method} failed: {}",
+                resp.description.unwrap_or_default()
+            ),
+        }
+    }
+
+    /// Text and parse mode of the reply to `text`
+    fn reply(&self, text: &str) -> (String, Option<&'static str>) {
+        let word = text.trim();
+        if word.is_empty() || word == "/start" || word == "/help" {
+            return (HELP.to_string(), None);
+        }
+
+        let resp = match lookup_maybe_cached(self.client, Some(self.db), word) {
+            Ok(resp) => resp,
+            Err(e) => return (format!("Failed to look up {word}: {e:#}"), None),
+        };
+        if let Err(e) = self.db.record_lookup(word) {
+            warn!("Failed to record lookup of {word}: {e:#}");
+        }
+
+        let reply = markdown(&resp, self.explain);
+        if reply.chars().count() <= MESSAGE_LIMIT {
+            return (reply, Some("MarkdownV2"));
+        }
+        // cutting markup could leave it unbalanced
+        let plain = resp.explain_with(&PlainFormatter::new(false), self.explain);
+        (plain.chars().take(MESSAGE_LIMIT).collect(), None)
+    }
+
+    fn answer(&self, message: Message) -> Result<()> {
+        let Some(text) = message.text else {
+            return Ok(());
+        };
+        if !self.allowed_chats.is_empty() && !self.allowed_chats.contains(&message.chat.id) {
+            warn!("Ignored a message of chat {}", message.chat.id);
+            return Ok(());
+        }
+
+        let (reply, parse_mode) = self.reply(&text);
+        let mut params = json!({ "chat_id": message.chat.id, "text": reply });
+        if let Some(parse_mode) = parse_mode {
+            params["parse_mode"] = json!(parse_mode);
+        }
+        self.call::<Value>("sendMessage", params)?;
+
+        Ok(())
+    }
+}
+
+/// Token of the bot in the first line of `file`, or in `YDCV_TELEGRAM_TOKEN`,
+/// kept off the command line where other users could see it
+pub fn token(file: Option<&Path>) -> Result<String> {
+    let token = match file {
+        Some(file) => fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?,
+        None => env::var(TOKEN_VAR)
+            .with_context(|| format!("Give --token-file or set {TOKEN_VAR} to the bot token"))?,
+    };
+    match token.lines().next().map(str::trim) {
+        Some(x) if !x.is_empty() => Ok(x.to_string()),
+        _ => bail!("The bot token is empty"),
+    }
+}
+
+/// Answer the messages sent to the bot of `token` until killed
+pub fn run(
+    http: &Client,
+    token: &str,
+    client: &impl YdClient,
+    db: &Db,
+    explain: &ExplainOptions,
+    allowed_chats: &[i64],
+) -> Result<()> {
+    let bot = Bot {
+        http,
+        api: format!("https://api.telegram.org/bot{token}"),
+        client,
+        db,
+        explain,
+        allowed_chats,
+    };
+    let me: Value = bot.call("getMe", json!({}))?;
+    eprintln!(
+        "Answering messages to @{}",
+        me["username"].as_str().unwrap_or("?")
+    );
+    if allowed_chats.is_empty() {
+        eprintln!("Warning: anyone can use the bot, give --allow-chat to only answer your chats");
+    }
+
+    let mut offset = 0;
+    loop {
+        let updates: Vec<Update> = match bot.call(
+            "getUpdates",
+            json!({ "offset": offset, "timeout": POLL_TIMEOUT, "allowed_updates": ["message"] }),
+        ) {
+            Ok(updates) => updates,
+            Err(e) => {
+                warn!("Failed to get messages: {e:#}");
+                sleep(RETRY_DELAY);
+                continue;
+            }
+        };
+        for update in updates {
+            // confirmed by the next getUpdates, so a failed answer isn't
+            // retried forever
+            offset = update.update_id + 1;
+            if let Some(message) = update.message
+                && let Err(e) = bot.answer(message)
+            {
+                warn!("Failed to answer a message: {e:#}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fake;
+
+    #[test]
+    fn test_markdown() {
+        assert_eq!(
+            "__x\\.y__\n*  Translation:*\n    a \\(b\\)",
+            markdown(&fake("x.y", "a (b)"), &ExplainOptions::default())
+        );
+    }
+
+    #[test]
+    fn test_token() {
+        let path = env::temp_dir().join(format!("ydcv-token-{}", std::process::id()));
+        fs::write(&path, "123:abc\n").unwrap();
+        assert_eq!("123:abc", token(Some(&path)).unwrap());
+        fs::write(&path, " \n").unwrap();
+        assert!(token(Some(&path)).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,741 @@
+//! user configuration file
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+use clap::{ColorChoice, ValueEnum};
+use rustyline::DefaultEditor;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+use toml::Table;
+use toml_edit::{Document, DocumentMut, Item, TableLike, Value};
+
+use crate::OutputFormat;
+use crate::keys::ACTIONS;
+use crate::lang::PinyinStyle;
+use crate::paths;
+use crate::phonetic::PhoneticStyle;
+use crate::review::edit_distance;
+use crate::ydresponse::Accent;
+use crate::ydresponse::LABELS;
+
+/// Settings of `config.toml`, every field is optional
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Config {
+    pub audio: AudioConfig,
+    pub display: DisplayConfig,
+    pub lookup: LookupConfig,
+    pub network: NetworkConfig,
+    #[cfg(feature = "notify")]
+    pub notify: NotifyConfig,
+    pub server: ServerConfig,
+    pub storage: StorageConfig,
+    /// Section labels replacing the default ones, like `translation = "释义:"`
+    pub labels: BTreeMap<String, String>,
+    /// Keys of the interactive prompt by action, like `speak = "ctrl-s"`
+    pub keys: BTreeMap<String, String>,
+    /// Profiles as written, which `load` merges into the other keys
+    pub profiles: BTreeMap<String, Config>,
+}
+
+/// Keys of `config.toml`, besides the headers in `network.headers.<name>`
+const KEYS: [&str; 37] = [
+    "audio.player",
+    "audio.accent",
+    "display.romanize",
+    "display.html",
+    "display.color",
+    "display.examples",
+    "display.phrases",
+    "display.collins",
+    "display.en_en",
+    "display.ce_dict",
+    "display.forms",
+    "display.synonyms",
+    "display.pinyin",
+    "display.phonetics",
+    "display.streak",
+    "display.format",
+    "lookup.add",
+    "lookup.speak",
+    "lookup.history",
+    "network.proxy",
+    "network.socks5",
+    "network.timeout",
+    "network.retries",
+    "network.endpoint",
+    "network.ca_cert",
+    "network.insecure",
+    "network.user_agents",
+    "network.headers",
+    "network.cookies",
+    "network.compression",
+    "network.concurrency",
+    "network.pool_idle_timeout",
+    "notify.enabled",
+    "notify.timeout",
+    "server.rate_limit",
+    "server.burst",
+    "storage.database",
+];
+
+/// Keys that can't be set together, the second is reported
+const CONFLICTS: [(&str, &str); 2] = [
+    ("network.proxy", "network.socks5"),
+    ("display.html", "display.format"),
+];
+
+/// Short names of environment variables overriding keys, besides the
+/// `YDCV_<SECTION>_<KEY>` of every key
+const ENV_ALIASES: [(&str, &str); 4] = [
+    ("YDCV_PROXY", "network.proxy"),
+    ("YDCV_BACKEND", "network.endpoint"),
+    ("YDCV_FORMAT", "display.format"),
+    ("YDCV_COLOR", "display.color"),
+];
+
+/// Deserialize a value of a command line option by any of its names there
+fn value_enum<'de, D: Deserializer<'de>, T: ValueEnum>(d: D) -> Result<Option<T>, D::Error> {
+    Option::<String>::deserialize(d)?
+        .map(|x| T::from_str(&x, true).map_err(D::Error::custom))
+        .transpose()
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct AudioConfig {
+    /// Player used for pronunciation: a known player name (`mpv`, `ffplay`,
+    /// `paplay`, ...), `auto`, or a custom command where `{}` is replaced by
+    /// the audio file (appended if missing)
+    pub player: Option<String>,
+    /// Default pronunciation variant, `uk` or `us`
+    pub accent: Option<Accent>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct DisplayConfig {
+    /// Add romaji, pinyin or other romanization under non-Latin results
+    pub romanize: bool,
+    /// HTML-style output like `-H`
+    pub html: bool,
+    /// Colored output, `auto`, `always` or `never`
+    #[serde(deserialize_with = "value_enum")]
+    pub color: Option<ColorChoice>,
+    /// Number of bilingual example sentences to show
+    pub examples: Option<usize>,
+    /// Number of common phrases to show, 0 to hide them
+    pub phrases: Option<usize>,
+    /// Show numbered senses from the Collins dictionary
+    pub collins: bool,
+    /// Show English definitions instead of Chinese explanations
+    pub en_en: bool,
+    /// Show senses from the Chinese-English dictionary
+    pub ce_dict: bool,
+    /// Show inflected forms and the lemma of forms
+    pub forms: bool,
+    /// Show synonyms and antonyms
+    pub synonyms: bool,
+    /// Tone style of pinyin, `marks` or `numbers`
+    #[serde(deserialize_with = "value_enum")]
+    pub pinyin: Option<PinyinStyle>,
+    /// Phonetics style, `raw`, `clean` or `respell`
+    #[serde(deserialize_with = "value_enum")]
+    pub phonetics: Option<PhoneticStyle>,
+    /// Show the daily lookup streak when entering interactive mode
+    pub streak: bool,
+    /// Output for other programs like `--format`
+    #[serde(deserialize_with = "value_enum")]
+    pub format: Option<OutputFormat>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct LookupConfig {
+    /// Add looked-up words to the wordbook
+    pub add: bool,
+    /// Play the pronunciation of looked-up words
+    pub speak: bool,
+    /// Record looked-up words in the history, on by default
+    pub history: Option<bool>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Proxy URL for every request, like `http://127.0.0.1:8080`
+    pub proxy: Option<String>,
+    /// Address of a SOCKS5 proxy, like `127.0.0.1:1080`
+    pub socks5: Option<String>,
+    /// Timeout of a request in seconds
+    pub timeout: Option<u64>,
+    /// Times to retry a request that timed out or got a server error
+    pub retries: Option<u32>,
+    /// Base URL of the dictionary and the sentence translator, like a mirror
+    /// of `https://www.youdao.com`
+    pub endpoint: Option<String>,
+    /// Extra root certificates in PEM or DER, e.g. of a corporate proxy
+    pub ca_cert: Option<PathBuf>,
+    /// Accept invalid TLS certificates
+    pub insecure: bool,
+    /// User-Agents to pick from for each request instead of the built-in ones
+    pub user_agents: Vec<String>,
+    /// Extra headers of every request
+    pub headers: BTreeMap<String, String>,
+    /// Keep cookies in the cache dir between runs, on by default
+    pub cookies: Option<bool>,
+    /// Accept gzip and brotli compressed responses, on by default
+    pub compression: Option<bool>,
+    /// Requests to run at once when looking up many words
+    pub concurrency: Option<usize>,
+    /// Seconds an idle connection is kept for the next lookup, 0 for ever
+    pub pool_idle_timeout: Option<u64>,
+}
+
+#[cfg(feature = "notify")]
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct NotifyConfig {
+    /// Send desktop notifications like `-n`
+    pub enabled: bool,
+    /// Seconds a notification is shown
+    #[cfg(unix)]
+    pub timeout: Option<i32>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// Lookups allowed to each client of `ydcv serve` and `ydcv daemon` a
+    /// minute, 0 for no limit
+    pub rate_limit: Option<u32>,
+    /// Lookups allowed to each client at once
+    pub burst: Option<u32>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Database of the history, the cache and the wordbook
+    pub database: Option<PathBuf>,
+}
+
+impl Config {
+    /// `ydcv/config.toml` in `$XDG_CONFIG_HOME` if set, or else in the config
+    /// dir of the platform
+    pub fn path() -> Result<PathBuf> {
+        Ok(paths::config_dir()?.join("config.toml"))
+    }
+
+    /// Load the config file with the keys of `[profiles.<profile>]` and then
+    /// environment variables overriding it, a missing file gives the default
+    /// config
+    pub fn load(profile: Option<&str>) -> Result<Self> {
+        let path = Self::path()?;
+        let text = if path.is_file() {
+            fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?
+        } else {
+            String::new()
+        };
+        validate(&text).with_context(|| format!("Invalid config {}", path.display()))?;
+
+        resolve(toml::from_str(&text)?, profile, |x| env::var(x).ok())
+            .context("Invalid config in the YDCV_ environment variables")
+    }
+}
+
+/// Put the keys of `over` into `base`, merging the tables in both
+fn merge(base: &mut Table, over: Table) {
+    for (key, value) in over {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(over)) => merge(base, over),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// The file `table` with the keys of `profile` merged in
+fn with_profile(mut table: Table, profile: Option<&str>) -> Result<Table> {
+    let profiles = table.remove("profiles");
+    if let Some(name) = profile {
+        let Some(toml::Value::Table(over)) = profiles.as_ref().and_then(|x| x.get(name)) else {
+            bail!("No profile {name}");
+        };
+        merge(&mut table, over.clone());
+    }
+
+    Ok(table)
+}
+
+/// The first of `CONFLICTS` set in `table`, where `false` counts as unset
+fn conflict(table: &Table) -> Option<(&'static str, &'static str)> {
+    let is_set = |key: &str| {
+        let (section, key) = key.split_once('.').unwrap();
+        table
+            .get(section)
+            .and_then(|x| x.get(key))
+            .is_some_and(|x| x.as_bool() != Some(false))
+    };
+    CONFLICTS.into_iter().find(|(a, b)| is_set(a) && is_set(b))
+}
+
+/// Config of the file `table` with `profile` and then the environment
+/// variables `var` finds overriding it
+fn resolve(
+    table: Table,
+    profile: Option<&str>,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<Config> {
+    let mut table = with_profile(table, profile)?;
+    override_with_env(&mut table, var);
+    if let Some((a, b)) = conflict(&table) {
+        bail!("{b} conflicts with {a}, set only one");
+    }
+    Ok(toml::Value::Table(table).try_into()?)
+}
+
+/// Line of the byte at `offset` of `text`
+fn line(text: &str, offset: usize) -> usize {
+    text[..offset].matches('\n').count() + 1
+}
+
+/// Unknown keys under `prefix` of `table`, with their lines in `text`
+fn unknown_keys(text: &str, prefix: &str, table: &dyn TableLike, errors: &mut Vec<String>) {
+    for (name, item) in table.iter() {
+        let key = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{prefix}.{name}")
+        };
+        if let Err(e) = check_key(&key) {
+            let span = table.get_key_value(name).and_then(|(x, _)| x.span());
+            match span {
+                Some(span) => errors.push(format!("line {}: {e}", line(text, span.start))),
+                None => errors.push(e.to_string()),
+            }
+        } else if let Some(table) = item.as_table_like() {
+            unknown_keys(text, &key, table, errors);
+        }
+    }
+}
+
+/// Line in `text` of the key at `path` of `doc`
+fn key_line(text: &str, doc: &Document<&str>, path: &[&str]) -> Option<usize> {
+    let (last, sections) = path.split_last()?;
+    let table = sections
+        .iter()
+        .try_fold(doc.as_item(), |item, x| item.get(x))?
+        .as_table_like()?;
+    let (key, _) = table.get_key_value(last)?;
+    Some(line(text, key.span()?.start))
+}
+
+/// Check that the config file `text` is valid with each of its profiles,
+/// reporting unknown keys, wrong types and conflicting keys with their lines
+fn validate(text: &str) -> Result<()> {
+    let doc = Document::parse(text)?;
+    let mut errors = vec![];
+    unknown_keys(text, "", doc.as_table(), &mut errors);
+    if !errors.is_empty() {
+        bail!(errors.join("\n"));
+    }
+
+    // errors of toml have the line and the column
+    let config: Config = toml::from_str(text)?;
+    let table: Table = toml::from_str(text)?;
+    let profiles = config.profiles.keys().map(|x| Some(x.as_str()));
+    for profile in [None].into_iter().chain(profiles) {
+        let merged = with_profile(table.clone(), profile)?;
+        if let Some((a, b)) = conflict(&merged) {
+            let (section, key) = b.split_once('.').unwrap();
+            let line = profile
+                .and_then(|x| key_line(text, &doc, &["profiles", x, section, key]))
+                .or_else(|| key_line(text, &doc, &[section, key]));
+            let message = format!("{b} conflicts with {a}, set only one");
+            match line {
+                Some(line) => bail!("line {line}: {message}"),
+                None => bail!(message),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Set the keys of `table` given by the environment variables `var` finds,
+/// each a TOML value or else a string
+fn override_with_env(table: &mut Table, var: impl Fn(&str) -> Option<String>) {
+    let full_names = KEYS
+        .iter()
+        .filter(|x| **x != "network.headers")
+        .map(|x| (format!("YDCV_{}", x.replace('.', "_").to_uppercase()), *x));
+    let names = ENV_ALIASES
+        .iter()
+        .map(|(name, key)| (name.to_string(), *key))
+        .chain(full_names);
+    for (name, key) in names {
+        let Some(text) = var(&name) else {
+            continue;
+        };
+        let value = toml::from_str::<Table>(&format!("value = {text}"))
+            .ok()
+            .and_then(|mut x| x.remove("value"))
+            .unwrap_or(toml::Value::String(text));
+        let (section, key) = key.split_once('.').unwrap();
+        if let toml::Value::Table(section) = table
+            .entry(section)
+            .or_insert_with(|| toml::Value::Table(Table::new()))
+        {
+            section.insert(key.to_string(), value);
+        }
+    }
+}
+
+/// `key` split at dots, if it is a key of `config.toml`
+fn check_key(key: &str) -> Result<Vec<&str>> {
+    let path = key.split('.').collect::<Vec<_>>();
+    if path == ["profiles"] {
+        return Ok(path);
+    }
+    if let ["profiles", name, rest @ ..] = &path[..]
+        && !name.is_empty()
+    {
+        if rest.first() == Some(&"profiles") {
+            bail!("Unknown key {key}, profiles can't have profiles");
+        }
+        if !rest.is_empty() {
+            check_key(&rest.join("."))?;
+        }
+        return Ok(path);
+    }
+    let known = match path[..] {
+        ["network", "headers", name] => !name.is_empty(),
+        ["labels"] => true,
+        ["labels", name] => LABELS.contains(&name),
+        ["keys"] => true,
+        ["keys", name] => ACTIONS.contains(&name),
+        _ => KEYS
+            .iter()
+            .any(|x| *x == key || x.strip_prefix(key).is_some_and(|x| x.starts_with('.'))),
+    };
+    if known {
+        return Ok(path);
+    }
+
+    match KEYS.iter().min_by_key(|x| edit_distance(key, x)) {
+        Some(near) if edit_distance(key, near) <= 3 => {
+            bail!("Unknown key {key}, did you mean {near}?")
+        }
+        _ => bail!("Unknown key {key}, see the README for the keys"),
+    }
+}
+
+/// The config file as written, keeping its comments and layout
+fn document() -> Result<(PathBuf, DocumentMut)> {
+    let path = Config::path()?;
+    let text = if path.is_file() {
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?
+    } else {
+        String::new()
+    };
+    let doc = text
+        .parse()
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok((path, doc))
+}
+
+/// Print the value of `key` in the config file, strings without quotes
+pub fn get(key: &str) -> Result<()> {
+    let path = check_key(key)?;
+    let (_, doc) = document()?;
+    let item = path
+        .iter()
+        .try_fold(doc.as_item(), |item, x| item.get(x))
+        .filter(|x| !x.is_none());
+    match item {
+        Some(Item::Value(Value::String(x))) => println!("{}", x.value()),
+        Some(Item::Value(x)) => {
+            let mut x = x.clone();
+            x.decor_mut().clear();
+            println!("{x}");
+        }
+        Some(item) => print!("{item}"),
+        None => bail!("{key} is not set"),
+    }
+
+    Ok(())
+}
+
+/// Set `key` to `value` in the config file, a TOML value or else a string,
+/// if the config stays valid
+pub fn set(key: &str, value: &str) -> Result<()> {
+    let path = check_key(key)?;
+    let (file, mut doc) = document()?;
+    let value = value
+        .parse::<Value>()
+        .unwrap_or_else(|_| Value::from(value));
+    if path.len() < 2 {
+        bail!("{key} is a section, set one of its keys");
+    }
+    put(&mut doc, &path, Some(value))
+        .with_context(|| format!("Failed to set {key} in {}", file.display()))?;
+
+    let text = doc.to_string();
+    validate(&text).with_context(|| format!("Invalid value of {key}"))?;
+    fs::create_dir_all(file.parent().unwrap())?;
+    fs::write(&file, text).with_context(|| format!("Failed to write {}", file.display()))
+}
+
+/// Set the key at `path` in `doc` to `value`, or remove it if `None`
+fn put(doc: &mut DocumentMut, path: &[&str], value: Option<Value>) -> Result<()> {
+    let (last, sections) = path.split_last().context("Empty key")?;
+    let mut table = doc.as_table_mut();
+    for x in sections {
+        // removing leaves out the sections that aren't there
+        if value.is_none() && !table.contains_key(x) {
+            return Ok(());
+        }
+        table = table
+            .entry(x)
+            .or_insert_with(toml_edit::table)
+            .as_table_mut()
+            .with_context(|| format!("{x} is not a table"))?;
+    }
+    match value {
+        Some(value) => table[*last] = Item::Value(value),
+        None => {
+            table.remove(last);
+        }
+    }
+
+    Ok(())
+}
+
+/// Ask `question` until an answer of `choices` is given, the first one if
+/// the answer is empty
+fn ask(editor: &mut DefaultEditor, question: &str, choices: &[&str]) -> Result<String> {
+    loop {
+        let line = editor.readline(&format!("{question} [{}]: ", choices.join("/")))?;
+        match line.trim().to_lowercase() {
+            x if x.is_empty() => return Ok(choices[0].to_string()),
+            x if choices.contains(&x.as_str()) => return Ok(x),
+            _ => println!("Please answer one of {}", choices.join(", ")),
+        }
+    }
+}
+
+/// Ask a yes or no `question`, `default` if the answer is empty
+fn confirm(editor: &mut DefaultEditor, question: &str, default: bool) -> Result<bool> {
+    let choices = if default { ["y", "n"] } else { ["n", "y"] };
+    Ok(ask(editor, question, &choices)? == "y")
+}
+
+/// Put the keys of the output named `output` in `doc`, `terminal`, `html`
+/// or a `--format`
+fn put_output(doc: &mut DocumentMut, output: &str) -> Result<()> {
+    let html = (output == "html").then(|| Value::from(true));
+    let format = OutputFormat::from_str(output, true)
+        .ok()
+        .map(|_| Value::from(output));
+    put(doc, &["display", "html"], html)?;
+    put(doc, &["display", "format"], format)
+}
+
+/// Write the config file from the answers to a few questions, keeping the
+/// other keys of an existing one
+pub fn init() -> Result<()> {
+    let (file, mut doc) = document()?;
+    let mut editor = DefaultEditor::new()?;
+    if file.is_file() {
+        println!("Updating {}", file.display());
+    }
+
+    let formats = OutputFormat::value_variants()
+        .iter()
+        .filter_map(|x| x.to_possible_value())
+        .map(|x| x.get_name().to_string())
+        .collect::<Vec<_>>();
+    let outputs = ["terminal", "html"]
+        .into_iter()
+        .chain(formats.iter().map(String::as_str))
+        .collect::<Vec<_>>();
+    let output = ask(&mut editor, "Output of lookups", &outputs)?;
+    put_output(&mut doc, &output)?;
+
+    let endpoint =
+        editor.readline("Base URL of the dictionary (empty for https://www.youdao.com): ")?;
+    let endpoint = endpoint.trim();
+    put(
+        &mut doc,
+        &["network", "endpoint"],
+        (!endpoint.is_empty()).then(|| Value::from(endpoint)),
+    )?;
+
+    #[cfg(feature = "notify")]
+    {
+        let notify = confirm(&mut editor, "Show lookups in desktop notifications?", false)?;
+        put(&mut doc, &["notify", "enabled"], Some(Value::from(notify)))?;
+    }
+
+    let history = confirm(&mut editor, "Record looked-up words in the history?", true)?;
+    put(&mut doc, &["lookup", "history"], Some(Value::from(history)))?;
+
+    let text = doc.to_string();
+    validate(&text).with_context(|| format!("Invalid config {}", file.display()))?;
+    fs::create_dir_all(file.parent().unwrap())?;
+    fs::write(&file, text).with_context(|| format!("Failed to write {}", file.display()))?;
+    println!(
+        "Wrote {}, see `ydcv config` to change it later",
+        file.display()
+    );
+
+    Ok(())
+}
+
+/// Open the config file in `$VISUAL` or `$EDITOR`, and check it after
+pub fn edit() -> Result<()> {
+    let path = Config::path()?;
+    fs::create_dir_all(path.parent().unwrap())?;
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    let mut args = editor.split_whitespace();
+    let program = args.next().context("Empty $EDITOR")?;
+    let status = Command::new(program)
+        .args(args)
+        .arg(&path)
+        .status()
+        .with_context(|| format!("Failed to run {editor}"))?;
+    if !status.success() {
+        bail!("{editor} failed with {status}");
+    }
+
+    let text = fs::read_to_string(&path).unwrap_or_default();
+    validate(&text).with_context(|| format!("Invalid config {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config: Config =
+            toml::from_str("[display]\ncolor = \"never\"\npinyin = \"numbers\"\nexamples = 1\n")
+                .unwrap();
+        assert_eq!(Some(ColorChoice::Never), config.display.color);
+        assert_eq!(Some(PinyinStyle::Numbers), config.display.pinyin);
+        assert_eq!(Some(1), config.display.examples);
+        assert_eq!(None, config.display.phonetics);
+        assert!(toml::from_str::<Config>("[display]\ncolor = \"blue\"\n").is_err());
+    }
+
+    #[test]
+    fn test_override_with_env() {
+        let mut table = toml::from_str("[network]\nproxy = \"http://a\"\nretries = 1\n").unwrap();
+        override_with_env(&mut table, |x| match x {
+            "YDCV_PROXY" => Some("http://b".to_string()),
+            "YDCV_NETWORK_RETRIES" => Some("3".to_string()),
+            "YDCV_DISPLAY_COLOR" => Some("never".to_string()),
+            _ => None,
+        });
+        let config: Config = toml::Value::Table(table).try_into().unwrap();
+        assert_eq!(Some("http://b"), config.network.proxy.as_deref());
+        assert_eq!(Some(3), config.network.retries);
+        assert_eq!(Some(ColorChoice::Never), config.display.color);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let table: Table = toml::from_str(
+            "[network]\nendpoint = \"https://a\"\nretries = 1\n\
+             [profiles.work.network]\nendpoint = \"https://b\"\n\
+             [profiles.work.lookup]\nhistory = false\n",
+        )
+        .unwrap();
+        let config = resolve(table.clone(), None, |_| None).unwrap();
+        assert_eq!(Some("https://a"), config.network.endpoint.as_deref());
+        assert_eq!(None, config.lookup.history);
+
+        let config = resolve(table.clone(), Some("work"), |_| None).unwrap();
+        assert_eq!(Some("https://b"), config.network.endpoint.as_deref());
+        assert_eq!(Some(1), config.network.retries);
+        assert_eq!(Some(false), config.lookup.history);
+
+        assert!(resolve(table, Some("home"), |_| None).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(
+            validate("[display]\ncolor = \"never\"\n[profiles.work.network]\nretries = 1\n")
+                .is_ok()
+        );
+        assert_eq!(
+            "line 3: Unknown key display.colr, did you mean display.color?",
+            validate("[display]\nhtml = true\ncolr = \"never\"\n")
+                .unwrap_err()
+                .to_string()
+        );
+        assert!(
+            validate("[display]\nexamples = \"x\"\n")
+                .unwrap_err()
+                .to_string()
+                .contains("line 2")
+        );
+        assert_eq!(
+            "line 4: network.socks5 conflicts with network.proxy, set only one",
+            validate(
+                "[network]\nproxy = \"http://a\"\n[profiles.work.network]\nsocks5 = \"b:1\"\n"
+            )
+            .unwrap_err()
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_check_key() {
+        assert_eq!(
+            vec!["display", "color"],
+            check_key("display.color").unwrap()
+        );
+        assert!(check_key("network.headers.Accept").is_ok());
+        assert!(check_key("network").is_ok());
+        assert!(check_key("profiles.work.display.color").is_ok());
+        assert!(check_key("profiles.work.display.colour").is_err());
+        assert!(check_key("net").is_err());
+        assert_eq!(
+            "Unknown key display.colour, did you mean display.color?",
+            check_key("display.colour").unwrap_err().to_string()
+        );
+        assert!(check_key("foo").is_err());
+    }
+
+    #[test]
+    fn test_put_output() {
+        let mut doc: DocumentMut = "# mine\n[display]\nhtml = true\nexamples = 1\n"
+            .parse()
+            .unwrap();
+        put_output(&mut doc, "line").unwrap();
+        assert_eq!(
+            "# mine\n[display]\nexamples = 1\nformat = \"line\"\n",
+            doc.to_string()
+        );
+        put_output(&mut doc, "html").unwrap();
+        validate(&doc.to_string()).unwrap();
+        put_output(&mut doc, "terminal").unwrap();
+        assert_eq!("# mine\n[display]\nexamples = 1\n", doc.to_string());
+
+        let mut doc = DocumentMut::new();
+        put_output(&mut doc, "terminal").unwrap();
+        assert_eq!("", doc.to_string());
+    }
+}
@@ -0,0 +1,117 @@
+//! cookie jar kept between runs
+
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use anyhow::{Result, anyhow};
+use cookie_store::{CookieStore, RawCookie};
+use log::{debug, warn};
+use reqwest::header::HeaderValue;
+use url::Url;
+
+/// Cookies of every response, saved to a file whenever they change so the
+/// next run continues the same browser-like session
+pub struct CookieJar {
+    path: PathBuf,
+    store: RwLock<CookieStore>,
+}
+
+impl CookieJar {
+    /// Load the jar saved at `path`, starting empty if it can't be read
+    pub fn load(path: PathBuf) -> Self {
+        let store = File::open(&path)
+            .map_err(|e| anyhow!(e))
+            .and_then(|x| {
+                cookie_store::serde::json::load(BufReader::new(x)).map_err(|e| anyhow!(e))
+            })
+            .inspect_err(|e| debug!("No saved cookies at {}: {e}", path.display()))
+            .unwrap_or_default();
+
+        CookieJar {
+            path,
+            store: RwLock::new(store),
+        }
+    }
+
+    fn save(&self, store: &CookieStore) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = File::create(&self.path)?;
+        cookie_store::serde::json::save(store, &mut file).map_err(|e| anyhow!(e))
+    }
+}
+
+impl reqwest::cookie::CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let cookies = cookie_headers
+            .filter_map(|x| x.to_str().ok())
+            .filter_map(|x| RawCookie::parse(x.to_string()).ok())
+            .collect::<Vec<_>>();
+        if cookies.is_empty() {
+            return;
+        }
+
+        let mut store = self.store.write().unwrap();
+        store.store_response_cookies(cookies.into_iter(), url);
+        self.save(&store)
+            .inspect_err(|e| warn!("Failed to save cookies to {}: {e}", self.path.display()))
+            .ok();
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let cookies = self
+            .store
+            .read()
+            .unwrap()
+            .get_request_values(url)
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        (!cookies.is_empty())
+            .then(|| HeaderValue::from_str(&cookies).ok())
+            .flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use reqwest::cookie::CookieStore as _;
+
+    use super::*;
+
+    #[test]
+    fn test_cookie_jar() {
+        let path = env::temp_dir().join("ydcv-test-cookies.json");
+        fs::remove_file(&path).ok();
+        let url = Url::parse("https://www.youdao.com/result").unwrap();
+
+        let jar = CookieJar::load(path.clone());
+        assert!(jar.cookies(&url).is_none());
+        jar.set_cookies(
+            &mut [
+                HeaderValue::from_static("OUTFOX_SEARCH_USER_ID=abc; Max-Age=3600; Path=/"),
+                HeaderValue::from_static("session=1"),
+            ]
+            .iter(),
+            &url,
+        );
+        let cookies = jar.cookies(&url).unwrap();
+        let mut cookies = cookies.to_str().unwrap().split("; ").collect::<Vec<_>>();
+        cookies.sort();
+        assert_eq!(vec!["OUTFOX_SEARCH_USER_ID=abc", "session=1"], cookies);
+
+        // session cookies aren't kept
+        let jar = CookieJar::load(path.clone());
+        assert_eq!(
+            "OUTFOX_SEARCH_USER_ID=abc",
+            jar.cookies(&url).unwrap().to_str().unwrap()
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
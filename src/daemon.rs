@@ -0,0 +1,148 @@
+//! A daemon answering the JSON-RPC requests of `rpc` on a unix socket, and
+//! the client of `ydcv --client` forwarding lookups to it, so that hotkeys
+//! don't pay for starting up and TLS handshakes on every lookup
+
+use std::fs::{create_dir_all, remove_file};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use dirs::runtime_dir;
+use log::warn;
+use serde_json::{Value, json};
+
+use crate::activation::{self, IdleExit};
+use crate::db::Db;
+use crate::paths;
+use crate::ratelimit::RateLimiter;
+use crate::rpc;
+use crate::ydclient::{Query, YdClient};
+use crate::ydresponse::{ExplainOptions, YdResponse};
+
+/// How long a client waits for the daemon, which may have to look up
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// `$XDG_RUNTIME_DIR/ydcv.sock`, or in the cache dir without a runtime dir
+pub fn default_socket() -> Result<PathBuf> {
+    runtime_dir()
+        .or_else(|| paths::cache_dir().ok())
+        .map(|x| x.join("ydcv.sock"))
+        .context("Failed to get runtime dir path")
+}
+
+/// Listen on `path`, taking the place of a socket left by a daemon that
+/// didn't exit cleanly
+pub fn bind(path: &Path) -> Result<UnixListener> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            bail!("A ydcv daemon is already listening on {}", path.display());
+        }
+        remove_file(path)?;
+    } else if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    UnixListener::bind(path).with_context(|| format!("Failed to listen on {}", path.display()))
+}
+
+/// Answer the requests of each connection to `listener` on a thread of its
+/// own with a database from `open_db`, forever or until `idle` exits,
+/// turning lookups away when over `limiter`; the clients are all local, so
+/// they share one bucket
+pub fn serve(
+    client: &(impl YdClient + Sync),
+    open_db: impl Fn() -> Result<Db> + Sync,
+    explain: &ExplainOptions,
+    listener: UnixListener,
+    idle: Option<&IdleExit>,
+    limiter: Option<RateLimiter<()>>,
+) -> Result<()> {
+    activation::serve_each(&listener, idle, |stream| {
+        let db = open_db()
+            .inspect_err(|e| warn!("Failed to open ydcv database: {e:#}"))
+            .ok();
+        if let Err(e) = rpc::serve(
+            client,
+            db.as_ref(),
+            explain,
+            limiter.as_ref(),
+            BufReader::new(&stream),
+            &stream,
+        ) {
+            warn!("Failed to answer a client: {e:#}");
+        }
+    })?;
+    Ok(())
+}
+
+/// Look up `query` with the daemon listening on `socket`, which caches the
+/// lookup, and records it too if `record`
+pub fn lookup(socket: &Path, query: &Query, record: bool) -> Result<YdResponse> {
+    let mut stream = UnixStream::connect(socket)
+        .with_context(|| format!("Failed to connect to ydcv daemon at {}", socket.display()))?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "lookup",
+        "params": {
+            "word": query.text,
+            "from": query.dir.from,
+            "to": query.dir.to,
+            "sentence": query.sentence,
+            "record": record,
+        },
+    });
+    writeln!(stream, "{request}")?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let mut response: Value = serde_json::from_str(&line).context("Bad answer of ydcv daemon")?;
+    if let Some(message) = response["error"]["message"].as_str() {
+        bail!("{message}");
+    }
+
+    Ok(serde_json::from_value(
+        response["result"]["response"].take(),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::lang::Direction;
+    use crate::testing::FakeClient;
+
+    #[test]
+    fn test_lookup() {
+        let socket = std::env::temp_dir().join(format!("ydcv-test-{}.sock", std::process::id()));
+        let listener = bind(&socket).unwrap();
+        thread::spawn(move || {
+            serve(
+                &FakeClient,
+                Db::open_in_memory,
+                &ExplainOptions::default(),
+                listener,
+                None,
+                Some(RateLimiter::new(1, 1)),
+            )
+        });
+
+        let query = Query {
+            text: "hello",
+            dir: Direction::detect("hello"),
+            sentence: false,
+        };
+        let resp = lookup(&socket, &query, true).unwrap();
+        assert_eq!("hello", resp.query());
+        assert_eq!("译", resp.translation()[0]);
+        assert!(lookup(&socket, &query, true).is_err());
+        assert!(bind(&socket).is_err());
+        remove_file(&socket).unwrap();
+    }
+}
@@ -0,0 +1,910 @@
+//! local database for lookup history, cached results, the wordbook and
+//! review schedules
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{create_dir_all, remove_file, rename};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use log::warn;
+use rusqlite::{Connection, OptionalExtension, params};
+
+use crate::lang::Direction;
+use crate::paths;
+use crate::review::Card;
+use crate::ydresponse::YdResponse;
+
+/// Schema migrations, the index of the last applied one is kept in `user_version`
+const MIGRATIONS: &[&str] = &[
+    "
+CREATE TABLE IF NOT EXISTS lookups (
+    id INTEGER PRIMARY KEY,
+    word TEXT NOT NULL,
+    looked_up_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS lookups_word ON lookups (word);
+
+CREATE TABLE IF NOT EXISTS cache (
+    word TEXT PRIMARY KEY,
+    response TEXT NOT NULL,
+    fetched_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS wordbook (
+    word TEXT PRIMARY KEY,
+    snapshot TEXT NOT NULL,
+    added_at INTEGER NOT NULL
+);
+",
+    "
+ALTER TABLE wordbook ADD COLUMN note TEXT;
+
+CREATE TABLE wordbook_tags (
+    word TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (word, tag)
+);
+",
+    "
+CREATE TABLE srs (
+    word TEXT PRIMARY KEY,
+    repetitions INTEGER NOT NULL,
+    interval INTEGER NOT NULL,
+    ease REAL NOT NULL,
+    due TEXT NOT NULL
+);
+",
+    "
+CREATE TABLE plans (
+    name TEXT PRIMARY KEY,
+    per_day INTEGER NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0,
+    started_at INTEGER NOT NULL,
+    last_fed TEXT
+);
+
+CREATE TABLE plan_words (
+    plan TEXT NOT NULL,
+    idx INTEGER NOT NULL,
+    word TEXT NOT NULL,
+    PRIMARY KEY (plan, idx)
+);
+",
+    "
+CREATE VIRTUAL TABLE search USING fts5 (
+    word,
+    definition,
+    note,
+    source UNINDEXED,
+    tokenize = 'trigram'
+);
+
+CREATE TRIGGER wordbook_search_insert AFTER INSERT ON wordbook BEGIN
+    INSERT INTO search (word, definition, note, source)
+    VALUES (new.word, (SELECT group_concat(value, ' ') FROM json_tree(new.snapshot) WHERE type = 'text'), new.note, 'wordbook');
+END;
+
+CREATE TRIGGER wordbook_search_update AFTER UPDATE ON wordbook BEGIN
+    DELETE FROM search WHERE source = 'wordbook' AND word = old.word;
+    INSERT INTO search (word, definition, note, source)
+    VALUES (new.word, (SELECT group_concat(value, ' ') FROM json_tree(new.snapshot) WHERE type = 'text'), new.note, 'wordbook');
+END;
+
+CREATE TRIGGER wordbook_search_delete AFTER DELETE ON wordbook BEGIN
+    DELETE FROM search WHERE source = 'wordbook' AND word = old.word;
+END;
+
+CREATE TRIGGER cache_search_insert AFTER INSERT ON cache BEGIN
+    DELETE FROM search WHERE source = 'cache' AND word = new.word;
+    INSERT INTO search (word, definition, source)
+    VALUES (new.word, (SELECT group_concat(value, ' ') FROM json_tree(new.response) WHERE type = 'text'), 'cache');
+END;
+
+INSERT INTO search (word, definition, note, source)
+SELECT word, (SELECT group_concat(value, ' ') FROM json_tree(snapshot) WHERE type = 'text'), note, 'wordbook'
+FROM wordbook;
+
+INSERT INTO search (word, definition, source)
+SELECT word, (SELECT group_concat(value, ' ') FROM json_tree(response) WHERE type = 'text'), 'cache'
+FROM cache;
+",
+    "
+CREATE TABLE state (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+",
+];
+
+/// A single entry of the lookup history
+pub struct HistoryEntry {
+    pub word: String,
+    pub looked_up_at: DateTime<Local>,
+}
+
+/// A word saved in the wordbook, with the definition at the time it was added
+pub struct WordbookEntry {
+    pub word: String,
+    pub snapshot: YdResponse,
+    pub added_at: DateTime<Local>,
+    pub tags: Vec<String>,
+    pub note: Option<String>,
+}
+
+/// A study plan feeding words from a standard list into the wordbook
+pub struct Plan {
+    pub name: String,
+    pub per_day: u32,
+    /// Number of words of the list already fed into the wordbook
+    pub position: u32,
+    pub total: u32,
+    pub last_fed: Option<NaiveDate>,
+}
+
+/// A wordbook entry or cached result matching a full-text search
+pub struct SearchHit {
+    pub word: String,
+    pub in_wordbook: bool,
+    pub definition: String,
+    pub note: Option<String>,
+}
+
+/// How long to wait for the writes of another process, e.g. the daemon,
+/// before failing as busy
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a cached response is used instead of looking up again
+const CACHE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Wrapper on the sqlite connection holding all local state
+pub struct Db {
+    conn: Connection,
+    /// Whether lookups are recorded, off for `lookup.history = false`
+    history: bool,
+}
+
+impl Db {
+    /// Default location of the database file
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(paths::data_dir()?.join("ydcv.db"))
+    }
+
+    /// Move the database at `from` with its WAL to `to` as a single file,
+    /// failing while another process has it open so that none of its
+    /// writes are left behind
+    pub fn move_file(from: &Path, to: &Path) -> Result<()> {
+        let conn = Connection::open(from)
+            .with_context(|| format!("Failed to open database {}", from.display()))?;
+        conn.busy_timeout(Duration::ZERO)?;
+        // kept until `from` is removed, which WAL connections would share
+        conn.pragma_update(None, "locking_mode", "EXCLUSIVE")?;
+        conn.execute_batch("BEGIN EXCLUSIVE; COMMIT;")
+            .with_context(|| {
+                format!(
+                    "Failed to move {} to {} as it's in use, stop the ydcv daemon and servers first",
+                    from.display(),
+                    to.display()
+                )
+            })?;
+
+        create_dir_all(to.parent().unwrap())?;
+        let part = to.with_extension("db.part");
+        let _ = remove_file(&part);
+        conn.execute(
+            "VACUUM INTO ?1",
+            [part.to_str().context("Non UTF-8 database path")?],
+        )
+        .with_context(|| format!("Failed to copy {} to {}", from.display(), part.display()))?;
+        rename(&part, to)
+            .with_context(|| format!("Failed to move {} to {}", part.display(), to.display()))?;
+        drop(conn);
+
+        for suffix in ["", "-wal", "-shm"] {
+            let mut path = from.as_os_str().to_owned();
+            path.push(suffix);
+            match remove_file(&path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => {
+                    warn!("Failed to remove {}: {e}", Path::new(&path).display());
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn open_default() -> Result<Self> {
+        Self::open(&Self::default_path()?)
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent()
+            && !parent.exists()
+        {
+            create_dir_all(parent)?;
+        }
+
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open database {}", path.display()))?;
+        // the CLI, the daemon and the servers share the database, which WAL
+        // lets them read while one of them writes
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        migrate(&conn)?;
+
+        Ok(Db {
+            conn,
+            history: true,
+        })
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self> {
+        let conn = Connection::open_in_memory()?;
+        migrate(&conn)?;
+
+        Ok(Db {
+            conn,
+            history: true,
+        })
+    }
+
+    /// Leave lookups unrecorded, whichever server or client makes them
+    pub fn without_history(self) -> Self {
+        Db {
+            history: false,
+            ..self
+        }
+    }
+
+    /// Record that `word` has been looked up just now, unless the history is
+    /// off
+    pub fn record_lookup(&self, word: &str) -> Result<()> {
+        if !self.history {
+            return Ok(());
+        }
+        self.conn.execute(
+            "INSERT INTO lookups (word, looked_up_at) VALUES (?1, ?2)",
+            params![word, Local::now().timestamp()],
+        )?;
+
+        Ok(())
+    }
+
+    /// Store the response of a lookup with a result, replacing older
+    /// entries. The cache is keyed by the word alone, so only lookups in the
+    /// detected direction are stored.
+    pub fn cache_response(&self, resp: &YdResponse) -> Result<()> {
+        let detected = resp.lookup().is_none_or(|x| {
+            Direction {
+                from: x.from,
+                to: x.to,
+            } == Direction::detect(resp.query())
+        });
+        if !resp.has_result() || !detected {
+            return Ok(());
+        }
+        self.conn.execute(
+            "INSERT OR REPLACE INTO cache (word, response, fetched_at) VALUES (?1, ?2, ?3)",
+            params![
+                resp.query(),
+                serde_json::to_string(resp)?,
+                Local::now().timestamp()
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Get the cached response of `word` fetched in the last
+    /// `CACHE_MAX_AGE`, if it has a result
+    pub fn cached(&self, word: &str) -> Result<Option<YdResponse>> {
+        let since = Local::now().timestamp() - CACHE_MAX_AGE.as_secs() as i64;
+        Ok(self
+            .cached_since(word, since)?
+            .filter(YdResponse::has_result))
+    }
+
+    /// Get the cached response of `word` however old, e.g. for its
+    /// translation
+    pub fn cached_any_age(&self, word: &str) -> Result<Option<YdResponse>> {
+        self.cached_since(word, i64::MIN)
+    }
+
+    fn cached_since(&self, word: &str, since: i64) -> Result<Option<YdResponse>> {
+        let json: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT response FROM cache WHERE word = ?1 AND fetched_at >= ?2",
+                params![word, since],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        json.map(|x| serde_json::from_str(&x).map_err(Into::into))
+            .transpose()
+    }
+
+    /// All lookups in chronological order
+    pub fn history(&self) -> Result<Vec<HistoryEntry>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT word, looked_up_at FROM lookups ORDER BY looked_up_at, id")?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+
+        let mut entries = vec![];
+        for row in rows {
+            let (word, ts) = row?;
+            entries.push(HistoryEntry {
+                word,
+                looked_up_at: timestamp(ts),
+            });
+        }
+
+        Ok(entries)
+    }
+
+    /// The word looked up last, if any
+    pub fn last_lookup(&self) -> Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT word FROM lookups ORDER BY looked_up_at DESC, id DESC LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(Into::into)
+    }
+
+    /// Words looked up more than `min` times since `since`, most frequent first
+    pub fn frequent_lookups(&self, since: DateTime<Local>, min: u32) -> Result<Vec<(String, u32)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT word, COUNT(*) AS n FROM lookups WHERE looked_up_at >= ?1
+             GROUP BY word HAVING n > ?2 ORDER BY n DESC, word",
+        )?;
+
+        let rows = stmt.query_map(params![since.timestamp(), min], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+
+        rows.collect::<Result<_, _>>().map_err(Into::into)
+    }
+
+    /// Add a word to the wordbook, returns false if it's already there
+    pub fn wordbook_add(&self, resp: &YdResponse) -> Result<bool> {
+        let n = self.conn.execute(
+            "INSERT OR IGNORE INTO wordbook (word, snapshot, added_at) VALUES (?1, ?2, ?3)",
+            params![
+                resp.query(),
+                serde_json::to_string(resp)?,
+                Local::now().timestamp()
+            ],
+        )?;
+
+        Ok(n > 0)
+    }
+
+    /// Remove a word from the wordbook, returns false if it isn't there
+    pub fn wordbook_remove(&self, word: &str) -> Result<bool> {
+        let n = self
+            .conn
+            .execute("DELETE FROM wordbook WHERE word = ?1", params![word])?;
+        self.conn
+            .execute("DELETE FROM wordbook_tags WHERE word = ?1", params![word])?;
+        self.conn
+            .execute("DELETE FROM srs WHERE word = ?1", params![word])?;
+
+        Ok(n > 0)
+    }
+
+    pub fn wordbook_contains(&self, word: &str) -> Result<bool> {
+        self.conn
+            .query_row(
+                "SELECT 1 FROM wordbook WHERE word = ?1",
+                params![word],
+                |_| Ok(()),
+            )
+            .optional()
+            .map(|x| x.is_some())
+            .map_err(Into::into)
+    }
+
+    pub fn wordbook_tag(&self, word: &str, tags: &[String]) -> Result<()> {
+        for tag in tags {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO wordbook_tags (word, tag) VALUES (?1, ?2)",
+                params![word, tag],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn wordbook_untag(&self, word: &str, tags: &[String]) -> Result<()> {
+        for tag in tags {
+            self.conn.execute(
+                "DELETE FROM wordbook_tags WHERE word = ?1 AND tag = ?2",
+                params![word, tag],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace the note of a wordbook entry, `None` clears it
+    pub fn wordbook_set_note(&self, word: &str, note: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE wordbook SET note = ?2 WHERE word = ?1",
+            params![word, note],
+        )?;
+
+        Ok(())
+    }
+
+    /// All wordbook entries, oldest first
+    pub fn wordbook(&self) -> Result<Vec<WordbookEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT w.word, w.snapshot, w.added_at, w.note, group_concat(t.tag, ',')
+             FROM wordbook w LEFT JOIN wordbook_tags t ON w.word = t.word
+             GROUP BY w.word ORDER BY w.added_at, w.word",
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        })?;
+
+        let mut entries = vec![];
+        for row in rows {
+            let (word, snapshot, ts, note, tags) = row?;
+            let mut tags = tags
+                .map(|x| x.split(',').map(|x| x.to_string()).collect::<Vec<_>>())
+                .unwrap_or_default();
+            tags.sort();
+
+            entries.push(WordbookEntry {
+                word,
+                snapshot: serde_json::from_str(&snapshot)?,
+                added_at: timestamp(ts),
+                tags,
+                note,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    /// Review cards of all wordbook entries, soonest due first.
+    ///
+    /// Entries that have never been reviewed get a new card due `today`.
+    pub fn srs_cards(&self, today: NaiveDate) -> Result<Vec<(WordbookEntry, Card)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT word, repetitions, interval, ease, due FROM srs")?;
+
+        let mut cards = stmt
+            .query_map([], |row| {
+                Ok(Card {
+                    word: row.get(0)?,
+                    repetitions: row.get(1)?,
+                    interval: row.get(2)?,
+                    ease: row.get(3)?,
+                    due: row.get::<_, String>(4)?.parse().unwrap_or(today),
+                })
+            })?
+            .map(|x| x.map(|card| (card.word.clone(), card)))
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        let mut result = self
+            .wordbook()?
+            .into_iter()
+            .map(|entry| {
+                let card = cards
+                    .remove(&entry.word)
+                    .unwrap_or_else(|| Card::new(&entry.word, today));
+                (entry, card)
+            })
+            .collect::<Vec<_>>();
+        result.sort_by_key(|(_, card)| card.due);
+
+        Ok(result)
+    }
+
+    pub fn srs_save(&self, card: &Card) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO srs (word, repetitions, interval, ease, due)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                card.word,
+                card.repetitions,
+                card.interval,
+                card.ease,
+                card.due.to_string()
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Search definitions and notes of the wordbook and the cache, wordbook
+    /// entries first, each word at most once
+    pub fn search(&self, text: &str) -> Result<Vec<SearchHit>> {
+        // the trigram tokenizer can only match three characters or more,
+        // shorter queries fall back to a substring scan
+        let (cond, pattern) = if text.chars().count() >= 3 {
+            (
+                "search MATCH ?1",
+                format!("\"{}\"", text.replace('"', "\"\"")),
+            )
+        } else {
+            (
+                r"(definition LIKE ?1 ESCAPE '\' OR note LIKE ?1 ESCAPE '\'
+                  OR word LIKE ?1 ESCAPE '\')",
+                format!(
+                    "%{}%",
+                    text.replace('\\', r"\\")
+                        .replace('%', r"\%")
+                        .replace('_', r"\_")
+                ),
+            )
+        };
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT word, source = 'wordbook', definition, note FROM search
+             WHERE {cond} ORDER BY source = 'wordbook' DESC, rank"
+        ))?;
+
+        let rows = stmt.query_map(params![pattern], |row| {
+            Ok(SearchHit {
+                word: row.get(0)?,
+                in_wordbook: row.get(1)?,
+                definition: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                note: row.get(3)?,
+            })
+        })?;
+
+        let mut seen = HashSet::new();
+        let mut hits = vec![];
+        for hit in rows {
+            let hit = hit?;
+            if seen.insert(hit.word.clone()) {
+                hits.push(hit);
+            }
+        }
+
+        Ok(hits)
+    }
+
+    /// Start a plan with the given word list, or only change the daily
+    /// amount if the plan already exists
+    pub fn plan_start(&self, name: &str, words: &[String], per_day: u32) -> Result<()> {
+        let n = self.conn.execute(
+            "UPDATE plans SET per_day = ?2 WHERE name = ?1",
+            params![name, per_day],
+        )?;
+        if n > 0 {
+            return Ok(());
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO plans (name, per_day, started_at) VALUES (?1, ?2, ?3)",
+            params![name, per_day, Local::now().timestamp()],
+        )?;
+        for (i, word) in (0u32..).zip(words) {
+            tx.execute(
+                "INSERT INTO plan_words (plan, idx, word) VALUES (?1, ?2, ?3)",
+                params![name, i, word],
+            )?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Delete a plan, words already fed stay in the wordbook
+    pub fn plan_stop(&self, name: &str) -> Result<bool> {
+        let n = self
+            .conn
+            .execute("DELETE FROM plans WHERE name = ?1", params![name])?;
+        self.conn
+            .execute("DELETE FROM plan_words WHERE plan = ?1", params![name])?;
+
+        Ok(n > 0)
+    }
+
+    pub fn plans(&self) -> Result<Vec<Plan>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT name, per_day, position, last_fed,
+                (SELECT COUNT(*) FROM plan_words WHERE plan = name)
+             FROM plans ORDER BY started_at",
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok(Plan {
+                name: row.get(0)?,
+                per_day: row.get(1)?,
+                position: row.get(2)?,
+                last_fed: row
+                    .get::<_, Option<String>>(3)?
+                    .and_then(|x| x.parse().ok()),
+                total: row.get(4)?,
+            })
+        })?;
+
+        rows.collect::<Result<_, _>>().map_err(Into::into)
+    }
+
+    /// Up to `n` words of the plan's list starting from `from`
+    pub fn plan_words(&self, name: &str, from: u32, n: u32) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT word FROM plan_words WHERE plan = ?1 AND idx >= ?2 ORDER BY idx LIMIT ?3",
+        )?;
+
+        let rows = stmt.query_map(params![name, from, n], |row| row.get(0))?;
+
+        rows.collect::<Result<_, _>>().map_err(Into::into)
+    }
+
+    /// Record that the plan's list has been fed up to `position` on `day`
+    pub fn plan_advance(&self, name: &str, position: u32, day: NaiveDate) -> Result<()> {
+        self.conn.execute(
+            "UPDATE plans SET position = ?2, last_fed = ?3 WHERE name = ?1",
+            params![name, position, day.to_string()],
+        )?;
+
+        Ok(())
+    }
+
+    /// Number of fed words of the plan that have been reviewed at least once,
+    /// and of those whose review interval reached `mastered_interval` days
+    pub fn plan_progress(&self, name: &str, mastered_interval: u32) -> Result<(u32, u32)> {
+        self.conn
+            .query_row(
+                "SELECT COUNT(s.word), COALESCE(SUM(s.interval >= ?2), 0)
+                 FROM plan_words p JOIN plans ON plans.name = p.plan
+                 LEFT JOIN srs s ON s.word = p.word
+                 WHERE p.plan = ?1 AND p.idx < plans.position",
+                params![name, mastered_interval],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(Into::into)
+    }
+
+    /// The day of the last reminder of due reviews, if any
+    pub fn last_reminded(&self) -> Result<Option<NaiveDate>> {
+        let day: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT value FROM state WHERE key = 'last_reminded'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(day.and_then(|x| x.parse().ok()))
+    }
+
+    /// Record that due reviews were reminded of on `day`
+    pub fn set_last_reminded(&self, day: NaiveDate) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO state (key, value) VALUES ('last_reminded', ?1)",
+            params![day.to_string()],
+        )?;
+
+        Ok(())
+    }
+}
+
+fn migrate(conn: &Connection) -> Result<()> {
+    let version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    for (i, migration) in (1..).zip(MIGRATIONS).skip(version as usize) {
+        conn.execute_batch(migration)?;
+        conn.pragma_update(None, "user_version", i)?;
+    }
+
+    Ok(())
+}
+
+fn timestamp(ts: i64) -> DateTime<Local> {
+    Local
+        .timestamp_opt(ts, 0)
+        .single()
+        .unwrap_or_else(Local::now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::Lang;
+    use crate::testing::fake;
+    use chrono::TimeDelta;
+
+    #[test]
+    fn test_frequent_lookups() {
+        let db = Db::open_in_memory().unwrap();
+        for word in ["hello", "world", "hello", "hello", "world", "foo"] {
+            db.record_lookup(word).unwrap();
+        }
+
+        let since = Local::now() - TimeDelta::days(1);
+        assert_eq!(
+            vec![("hello".to_string(), 3), ("world".to_string(), 2)],
+            db.frequent_lookups(since, 1).unwrap()
+        );
+        assert!(db.frequent_lookups(since, 3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_move_file() {
+        let dir = std::env::temp_dir().join(format!("ydcv-test-move-{}", std::process::id()));
+        let (from, to) = (
+            dir.join("old").join("ydcv.db"),
+            dir.join("new").join("ydcv.db"),
+        );
+        let db = Db::open(&from).unwrap();
+        db.record_lookup("hello").unwrap();
+        // a process still using the database keeps it in place
+        assert!(Db::move_file(&from, &to).is_err());
+        assert!(!to.exists());
+
+        drop(db);
+        Db::move_file(&from, &to).unwrap();
+        assert!(!from.exists());
+        assert_eq!(
+            Some("hello".to_string()),
+            Db::open(&to).unwrap().last_lookup().unwrap()
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_last_lookup() {
+        let db = Db::open_in_memory().unwrap();
+        assert_eq!(None, db.last_lookup().unwrap());
+        for word in ["hello", "world"] {
+            db.record_lookup(word).unwrap();
+        }
+        assert_eq!(Some("world".to_string()), db.last_lookup().unwrap());
+    }
+
+    #[test]
+    fn test_without_history() {
+        let db = Db::open_in_memory().unwrap().without_history();
+        db.record_lookup("hello").unwrap();
+        assert_eq!(None, db.last_lookup().unwrap());
+    }
+
+    #[test]
+    fn test_cache() {
+        let db = Db::open_in_memory().unwrap();
+        db.cache_response(&fake("hello", "你好")).unwrap();
+        assert_eq!(
+            vec!["你好"],
+            db.cached("hello").unwrap().unwrap().translation()
+        );
+
+        let dir = Direction::detect("hakunamatata");
+        let page = r#"<div class="no-data-prompt">没有找到</div>"#;
+        db.cache_response(&YdResponse::from_html(page, "hakunamatata", dir).unwrap())
+            .unwrap();
+        assert!(db.cached("hakunamatata").unwrap().is_none());
+
+        let dir = Direction::resolve("你好", None, Some(Lang::Ja)).unwrap();
+        db.cache_response(&YdResponse::from_translation(
+            "你好",
+            vec!["やあ".into()],
+            dir,
+        ))
+        .unwrap();
+        assert!(db.cached("你好").unwrap().is_none());
+
+        db.conn
+            .execute("UPDATE cache SET fetched_at = 0 WHERE word = 'hello'", [])
+            .unwrap();
+        assert!(db.cached("hello").unwrap().is_none());
+        assert!(db.cached_any_age("hello").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_wordbook() {
+        let db = Db::open_in_memory().unwrap();
+        assert!(db.wordbook_add(&fake("hello", "你好")).unwrap());
+        assert!(!db.wordbook_add(&fake("hello", "喂")).unwrap());
+        assert!(db.wordbook_add(&fake("world", "世界")).unwrap());
+
+        let words = db.wordbook().unwrap();
+        assert_eq!(2, words.len());
+        assert_eq!(["你好"], words[0].snapshot.translation());
+
+        assert!(db.wordbook_remove("hello").unwrap());
+        assert!(!db.wordbook_remove("hello").unwrap());
+        assert_eq!(1, db.wordbook().unwrap().len());
+    }
+
+    #[test]
+    fn test_wordbook_tags() {
+        let db = Db::open_in_memory().unwrap();
+        db.wordbook_add(&fake("hello", "你好")).unwrap();
+        db.wordbook_tag("hello", &["novel".into(), "coding".into()])
+            .unwrap();
+        db.wordbook_untag("hello", &["novel".into()]).unwrap();
+        db.wordbook_set_note("hello", Some("greeting")).unwrap();
+
+        let words = db.wordbook().unwrap();
+        assert_eq!(vec!["coding"], words[0].tags);
+        assert_eq!(Some("greeting"), words[0].note.as_deref());
+    }
+
+    #[test]
+    fn test_srs_cards() {
+        let db = Db::open_in_memory().unwrap();
+        let today = Local::now().date_naive();
+        db.wordbook_add(&fake("hello", "你好")).unwrap();
+        db.wordbook_add(&fake("world", "世界")).unwrap();
+
+        let mut card = Card::new("hello", today);
+        card.grade(5, today);
+        db.srs_save(&card).unwrap();
+
+        let cards = db.srs_cards(today).unwrap();
+        assert_eq!(
+            vec![("world", true), ("hello", false)],
+            cards
+                .iter()
+                .map(|(entry, card)| (entry.word.as_str(), card.is_due(today)))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_last_reminded() {
+        let db = Db::open_in_memory().unwrap();
+        assert_eq!(None, db.last_reminded().unwrap());
+        let day = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        db.set_last_reminded(day).unwrap();
+        db.set_last_reminded(day.succ_opt().unwrap()).unwrap();
+        assert_eq!(day.succ_opt(), db.last_reminded().unwrap());
+    }
+
+    #[test]
+    fn test_search() {
+        let db = Db::open_in_memory().unwrap();
+        db.cache_response(&fake("lukewarm", "微温的")).unwrap();
+        db.cache_response(&fake("tepid", "温热的")).unwrap();
+        db.wordbook_add(&fake("tepid", "温热的")).unwrap();
+        db.wordbook_set_note("tepid", Some("like bath water"))
+            .unwrap();
+
+        let words = |text| {
+            db.search(text)
+                .unwrap()
+                .into_iter()
+                .map(|x| (x.word, x.in_wordbook))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![("tepid".to_string(), true), ("lukewarm".to_string(), false)],
+            words("温")
+        );
+        assert_eq!(vec![("tepid".to_string(), true)], words("bath"));
+        assert_eq!(vec![("lukewarm".to_string(), false)], words("微温的"));
+
+        // wildcards of LIKE are matched as they are
+        assert!(words("%").is_empty() && words("_").is_empty());
+        db.cache_response(&fake("100%", "百分之百")).unwrap();
+        assert_eq!(vec![("100%".to_string(), false)], words("%"));
+    }
+}
@@ -0,0 +1,293 @@
+//! D-Bus service of a long-running `ydcv`, so that desktop widgets and
+//! scripts can look up without starting a process each time
+
+use std::collections::HashMap;
+use std::future;
+use std::sync::Arc;
+#[cfg(feature = "clipboard")]
+use std::sync::Mutex;
+use std::thread;
+
+use anyhow::Result;
+use log::warn;
+use tokio::runtime;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::Value;
+use zbus::{connection, fdo, interface};
+
+use crate::lookups::Lookups;
+use crate::open_in_browser;
+use crate::ydresponse::YdResponse;
+
+/// Well-known name of the service on the session bus
+pub const NAME: &str = "org.ydcv.Ydcv";
+
+/// Object path of the service
+pub const PATH: &str = "/org/ydcv/Ydcv";
+
+/// Object path of the GNOME Shell search provider, as in
+/// `data/org.ydcv.Ydcv.search-provider.ini`
+pub const SEARCH_PROVIDER_PATH: &str = "/org/ydcv/Ydcv/SearchProvider";
+
+/// Object path of the KRunner runner, as in `data/plasma-runner-ydcv.desktop`
+pub const RUNNER_PATH: &str = "/org/ydcv/Ydcv/Runner";
+
+/// Id of the KRunner action copying the translation
+const COPY_ACTION: &str = "copy";
+
+/// Id, text, icon, type, relevance and properties of a KRunner match
+type RunnerMatch = (
+    String,
+    String,
+    String,
+    i32,
+    f64,
+    HashMap<String, Value<'static>>,
+);
+
+/// The `org.ydcv.Ydcv1` interface
+struct Service(Arc<Lookups>);
+
+#[interface(name = "org.ydcv.Ydcv1")]
+impl Service {
+    /// Plain and HTML explanations of `word`
+    async fn lookup(
+        &self,
+        word: &str,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<(String, String)> {
+        let word = word.trim();
+        let (plain, html) = self
+            .0
+            .explain(word)
+            .await
+            .map_err(|e| fdo::Error::Failed(format!("{e:#}")))?;
+        Self::result_ready(&emitter, word, &plain, &html).await?;
+
+        Ok((plain, html))
+    }
+
+    /// Sent after every lookup, for widgets following the latest one
+    #[zbus(signal)]
+    async fn result_ready(
+        emitter: &SignalEmitter<'_>,
+        word: &str,
+        plain: &str,
+        html: &str,
+    ) -> zbus::Result<()>;
+}
+
+/// GNOME Shell search provider, showing the translation of what's typed in
+/// the overview
+struct SearchProvider(Arc<Lookups>);
+
+impl SearchProvider {
+    /// The query of `terms` as the only result if it has one, not recorded
+    /// as the overview searches on every key
+    async fn results(&self, terms: &[String]) -> Vec<String> {
+        let query = terms.join(" ");
+        match self.0.lookup_unrecorded(&query).await {
+            Ok(resp) if resp.has_result() => vec![query],
+            Ok(_) => vec![],
+            Err(e) => {
+                warn!("Failed to look up {query}: {e:#}");
+                vec![]
+            }
+        }
+    }
+}
+
+#[interface(name = "org.gnome.Shell.SearchProvider2")]
+impl SearchProvider {
+    async fn get_initial_result_set(&self, terms: Vec<String>) -> Vec<String> {
+        self.results(&terms).await
+    }
+
+    async fn get_subsearch_result_set(
+        &self,
+        _previous_results: Vec<String>,
+        terms: Vec<String>,
+    ) -> Vec<String> {
+        self.results(&terms).await
+    }
+
+    /// Names and summaries of the results, looked up again
+    async fn get_result_metas(
+        &self,
+        identifiers: Vec<String>,
+    ) -> Vec<HashMap<String, Value<'static>>> {
+        let mut metas = vec![];
+        for id in identifiers {
+            let Ok(resp) = self.0.lookup_unrecorded(&id).await else {
+                continue;
+            };
+            metas.push(HashMap::from([
+                ("description".to_string(), Value::from(summary(&resp))),
+                ("name".to_string(), Value::from(id.clone())),
+                ("id".to_string(), Value::from(id)),
+            ]));
+        }
+
+        metas
+    }
+
+    /// Open the full result on YD in the browser, recording the lookup as
+    /// it's the one chosen
+    fn activate_result(&self, identifier: &str, _terms: Vec<String>, _timestamp: u32) {
+        self.0.record(identifier);
+        open_result(identifier.to_string());
+    }
+
+    fn launch_search(&self, terms: Vec<String>, _timestamp: u32) {
+        open_result(terms.join(" "));
+    }
+}
+
+/// KRunner runner, showing the translation of what's typed in KRunner with an
+/// action to copy it
+struct Runner {
+    lookups: Arc<Lookups>,
+    /// Kept open once copied to, as what's copied is gone on X11 once the
+    /// clipboard is closed
+    #[cfg(feature = "clipboard")]
+    clipboard: Mutex<Option<arboard::Clipboard>>,
+}
+
+impl Runner {
+    fn new(lookups: Arc<Lookups>) -> Self {
+        Runner {
+            lookups,
+            #[cfg(feature = "clipboard")]
+            clipboard: Mutex::new(None),
+        }
+    }
+
+    /// Copy `text` to the clipboard, opening it the first time
+    #[cfg(feature = "clipboard")]
+    fn copy(&self, text: String) -> Result<(), arboard::Error> {
+        let mut clipboard = self.clipboard.lock().unwrap_or_else(|e| e.into_inner());
+        let clipboard = match &mut *clipboard {
+            Some(clipboard) => clipboard,
+            None => clipboard.insert(arboard::Clipboard::new()?),
+        };
+        clipboard.set_text(text)
+    }
+}
+
+#[interface(name = "org.kde.krunner1")]
+impl Runner {
+    fn actions(&self) -> Vec<(String, String, String)> {
+        if cfg!(feature = "clipboard") {
+            vec![(
+                COPY_ACTION.to_string(),
+                "Copy translation".to_string(),
+                "edit-copy".to_string(),
+            )]
+        } else {
+            vec![]
+        }
+    }
+
+    /// The query as the only match if it has a result, with its summary
+    #[zbus(name = "Match")]
+    async fn find_matches(&self, query: &str) -> Vec<RunnerMatch> {
+        let query = query.trim();
+        // KRunner asks on every key, so single letters aren't worth a request
+        if query.chars().count() < 2 {
+            return vec![];
+        }
+
+        // recorded only once run, as KRunner asks on every key
+        match self.lookups.lookup_unrecorded(query).await {
+            Ok(resp) if resp.has_result() => vec![(
+                query.to_string(),
+                summary(&resp),
+                "accessories-dictionary".to_string(),
+                // QueryMatch::ExactMatch
+                100,
+                1.0,
+                HashMap::from([("subtext".to_string(), Value::from(query.to_string()))]),
+            )],
+            Ok(_) => vec![],
+            Err(e) => {
+                warn!("Failed to look up {query}: {e:#}");
+                vec![]
+            }
+        }
+    }
+
+    /// Copy the translation of `match_id`, or open its full result on YD
+    async fn run(&self, match_id: &str, action_id: &str) {
+        self.lookups.record(match_id);
+        #[cfg(feature = "clipboard")]
+        if action_id == COPY_ACTION {
+            match self.lookups.lookup_unrecorded(match_id).await {
+                Ok(resp) => {
+                    let translation = match resp.translation() {
+                        [] => summary(&resp),
+                        translation => translation.join("; "),
+                    };
+                    self.copy(translation)
+                        .inspect_err(|e| warn!("Failed to copy the translation of {match_id}: {e}"))
+                        .ok();
+                }
+                Err(e) => warn!("Failed to look up {match_id}: {e:#}"),
+            }
+            return;
+        }
+        #[cfg(not(feature = "clipboard"))]
+        let _ = action_id;
+
+        open_result(match_id.to_string());
+    }
+}
+
+/// Open the full result of `word` on YD in the browser, without holding up
+/// the bus until the browser opens
+fn open_result(word: String) {
+    thread::spawn(move || open_in_browser(&word));
+}
+
+/// One line of phonetic and explanations of `resp`, for search results
+fn summary(resp: &YdResponse) -> String {
+    let explains = match resp.explains() {
+        [] => resp.translation().join("; "),
+        explains => explains.join("; "),
+    };
+    match resp.phonetic().filter(|x| !x.is_empty()) {
+        Some(phonetic) => format!("[{phonetic}] {explains}"),
+        None => explains,
+    }
+}
+
+/// Serve `lookups` on the session bus until killed
+pub fn serve(lookups: Lookups) -> Result<()> {
+    let lookups = Arc::new(lookups);
+    runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(async {
+            let _connection = connection::Builder::session()?
+                .name(NAME)?
+                .serve_at(PATH, Service(lookups.clone()))?
+                .serve_at(SEARCH_PROVIDER_PATH, SearchProvider(lookups.clone()))?
+                .serve_at(RUNNER_PATH, Runner::new(lookups))?
+                .build()
+                .await?;
+            println!("Serving lookups as {NAME} on the session bus");
+            future::pending::<()>().await;
+
+            Ok(())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fake;
+
+    #[test]
+    fn test_summary() {
+        assert_eq!("你好", summary(&fake("hello", "你好")));
+    }
+}
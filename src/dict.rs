@@ -0,0 +1,358 @@
+//! A DICT protocol (RFC 2229) server, so that dict clients such as `dict`,
+//! Emacs dictionary.el and GoldenDict can look up words in `ydcv` and its
+//! cache
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use anyhow::Result;
+use log::warn;
+
+use crate::activation;
+use crate::db::Db;
+use crate::formatters::PlainFormatter;
+use crate::lookup_maybe_cached;
+use crate::ydclient::YdClient;
+use crate::ydresponse::ExplainOptions;
+
+/// The only database, what `*` and `!` stand for too
+const DATABASE: &str = "ydcv";
+const DATABASE_DESCRIPTION: &str = "Youdao dictionary via ydcv";
+
+/// Longest command line, as of RFC 2229
+const MAX_LINE: usize = 1024;
+
+/// How long a client may wait between commands
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Strategies of MATCH, with what they match
+const STRATEGIES: [(&str, &str); 2] = [
+    ("exact", "Match headwords exactly"),
+    ("lev", "Match headwords and their suggested spellings"),
+];
+
+/// Words of a command line, unquoting "quoted" and 'quoted' words and
+/// backslash escapes
+fn split_command(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut word = String::new();
+        let mut quote = None;
+        while let Some(c) = chars.next() {
+            match (c, quote) {
+                ('\\', _) => word.extend(chars.next()),
+                ('"' | '\'', None) => quote = Some(c),
+                (c, Some(q)) if c == q => quote = None,
+                (c, None) if c.is_whitespace() => break,
+                (c, _) => word.push(c),
+            }
+        }
+        words.push(word);
+    }
+
+    words
+}
+
+/// Write `text` as a text response, lines starting with a dot doubled and
+/// ended by a line of a single dot
+fn write_text(out: &mut impl Write, text: &str) -> Result<()> {
+    for line in text.lines() {
+        if line.starts_with('.') {
+            write!(out, ".")?;
+        }
+        write!(out, "{line}\r\n")?;
+    }
+    write!(out, ".\r\n")?;
+
+    Ok(())
+}
+
+/// Write the status line of a lookup failing with `e`, on a single line as
+/// any status
+fn write_unavailable(out: &mut impl Write, e: &anyhow::Error) -> Result<()> {
+    let message = format!("{e:#}");
+    let message = message.split_whitespace().collect::<Vec<_>>().join(" ");
+    write!(out, "420 server temporarily unavailable: {message}\r\n")?;
+
+    Ok(())
+}
+
+/// What commands are answered with
+struct Server<'a, C> {
+    client: &'a C,
+    db: Option<&'a Db>,
+    explain: &'a ExplainOptions,
+}
+
+impl<C: YdClient> Server<'_, C> {
+    /// Answer the command `line` on `out`, false once the client quits
+    fn respond(&self, line: &str, out: &mut impl Write) -> Result<bool> {
+        let words = split_command(line);
+        let args = words.iter().map(String::as_str).collect::<Vec<_>>();
+        let command = args.first().map(|x| x.to_ascii_uppercase());
+        match (command.as_deref(), args.get(1..).unwrap_or_default()) {
+            (Some("DEFINE"), [database, word]) => self.define(database, word, out)?,
+            (Some("MATCH"), [database, strategy, word]) => {
+                self.find_matches(database, strategy, word, out)?
+            }
+            (Some("SHOW"), [what, ..]) => match what.to_ascii_uppercase().as_str() {
+                "DB" | "DATABASES" => {
+                    write!(out, "110 1 databases present\r\n")?;
+                    write_text(out, &format!("{DATABASE} \"{DATABASE_DESCRIPTION}\""))?;
+                    write!(out, "250 ok\r\n")?;
+                }
+                "STRAT" | "STRATEGIES" => {
+                    write!(out, "111 {} strategies present\r\n", STRATEGIES.len())?;
+                    let strategies = STRATEGIES
+                        .iter()
+                        .map(|(name, description)| format!("{name} \"{description}\""))
+                        .collect::<Vec<_>>();
+                    write_text(out, &strategies.join("\n"))?;
+                    write!(out, "250 ok\r\n")?;
+                }
+                "INFO" => {
+                    write!(out, "112 database information follows\r\n")?;
+                    write_text(out, DATABASE_DESCRIPTION)?;
+                    write!(out, "250 ok\r\n")?;
+                }
+                "SERVER" => {
+                    write!(out, "114 server information follows\r\n")?;
+                    write_text(out, &format!("ydcv {}", env!("CARGO_PKG_VERSION")))?;
+                    write!(out, "250 ok\r\n")?;
+                }
+                _ => write!(out, "501 syntax error, illegal parameters\r\n")?,
+            },
+            (Some("CLIENT" | "OPTION"), _) => write!(out, "250 ok\r\n")?,
+            (Some("STATUS"), _) => write!(out, "210 up\r\n")?,
+            (Some("HELP"), _) => {
+                write!(out, "113 help text follows\r\n")?;
+                write_text(
+                    out,
+                    "DEFINE database word\nMATCH database strategy word\n\
+                     SHOW DB\nSHOW STRAT\nSHOW INFO database\nSHOW SERVER\n\
+                     CLIENT info\nSTATUS\nHELP\nQUIT",
+                )?;
+                write!(out, "250 ok\r\n")?;
+            }
+            (Some("QUIT"), _) => {
+                write!(out, "221 bye\r\n")?;
+                return Ok(false);
+            }
+            (Some("DEFINE" | "MATCH"), _) => {
+                write!(out, "501 syntax error, illegal parameters\r\n")?
+            }
+            (None, _) => write!(out, "500 syntax error, command not recognized\r\n")?,
+            (Some(_), _) => write!(out, "502 command not implemented\r\n")?,
+        }
+        out.flush()?;
+
+        Ok(true)
+    }
+
+    fn define(&self, database: &str, word: &str, out: &mut impl Write) -> Result<()> {
+        if !is_database(database) {
+            write!(out, "550 invalid database, use \"SHOW DB\" for list\r\n")?;
+            return Ok(());
+        }
+        let resp = match lookup_maybe_cached(self.client, self.db, word) {
+            Ok(resp) if resp.has_result() => resp,
+            Ok(_) => {
+                write!(out, "552 no match\r\n")?;
+                return Ok(());
+            }
+            Err(e) => return write_unavailable(out, &e),
+        };
+        if let Some(db) = self.db
+            && let Err(e) = db.record_lookup(word)
+        {
+            warn!("Failed to record lookup of {word}: {e:#}");
+        }
+
+        write!(out, "150 1 definitions retrieved\r\n")?;
+        write!(
+            out,
+            "151 \"{}\" {DATABASE} \"{DATABASE_DESCRIPTION}\"\r\n",
+            resp.query()
+        )?;
+        write_text(
+            out,
+            &resp.explain_with(&PlainFormatter::new(false), self.explain),
+        )?;
+        write!(out, "250 ok\r\n")?;
+
+        Ok(())
+    }
+
+    fn find_matches(
+        &self,
+        database: &str,
+        strategy: &str,
+        word: &str,
+        out: &mut impl Write,
+    ) -> Result<()> {
+        if !is_database(database) {
+            write!(out, "550 invalid database, use \"SHOW DB\" for list\r\n")?;
+            return Ok(());
+        }
+        // the default strategy is the server's best
+        let strategy = if strategy == "." { "lev" } else { strategy };
+        if !STRATEGIES.iter().any(|(name, _)| *name == strategy) {
+            write!(out, "551 invalid strategy, use \"SHOW STRAT\" for list\r\n")?;
+            return Ok(());
+        }
+        let resp = match lookup_maybe_cached(self.client, self.db, word) {
+            Ok(resp) => resp,
+            Err(e) => return write_unavailable(out, &e),
+        };
+
+        let mut matches = Vec::new();
+        if resp.has_result() {
+            matches.push(resp.query());
+        }
+        if strategy == "lev" {
+            matches.extend(resp.suggestions().iter().map(String::as_str));
+        }
+        if matches.is_empty() {
+            write!(out, "552 no match\r\n")?;
+            return Ok(());
+        }
+
+        write!(out, "152 {} matches found\r\n", matches.len())?;
+        let lines = matches
+            .iter()
+            .map(|x| format!("{DATABASE} \"{x}\""))
+            .collect::<Vec<_>>();
+        write_text(out, &lines.join("\n"))?;
+        write!(out, "250 ok\r\n")?;
+
+        Ok(())
+    }
+
+    /// Greet the client on `stream` and answer its commands until it quits,
+    /// goes quiet or sends too long a line
+    fn serve_client(&self, stream: &TcpStream) -> Result<()> {
+        stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+        stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+        let mut out = stream;
+        write!(
+            out,
+            "220 ydcv {} <> <{}.{}@ydcv>\r\n",
+            env!("CARGO_PKG_VERSION"),
+            std::process::id(),
+            stream.peer_addr()?.port()
+        )?;
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.by_ref().take(MAX_LINE as u64).read_line(&mut line)?;
+            if read == 0 {
+                break;
+            }
+            if !line.ends_with('\n') && read == MAX_LINE {
+                write!(out, "500 line too long\r\n")?;
+                break;
+            }
+            if !self.respond(&line, &mut out)? {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn is_database(database: &str) -> bool {
+    matches!(database, "*" | "!") || database == DATABASE
+}
+
+/// Answer each client of `listener` on a thread of its own with a database
+/// from `open_db`, forever
+pub fn serve(
+    client: &(impl YdClient + Sync),
+    open_db: impl Fn() -> Result<Db> + Sync,
+    explain: &ExplainOptions,
+    listener: TcpListener,
+) -> Result<()> {
+    eprintln!("Listening on dict://{}", listener.local_addr()?);
+    activation::serve_each(&listener, None, |stream| {
+        let db = open_db()
+            .inspect_err(|e| warn!("Failed to open ydcv database: {e:#}"))
+            .ok();
+        let server = Server {
+            client,
+            db: db.as_ref(),
+            explain,
+        };
+        if let Err(e) = server.serve_client(&stream) {
+            warn!("Failed to answer a client: {e:#}");
+        }
+    })?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FakeClient;
+
+    #[test]
+    fn test_split_command() {
+        assert_eq!(
+            ["DEFINE", "*", "ice cream"],
+            split_command("DEFINE * \"ice cream\"\r\n").as_slice()
+        );
+        assert_eq!(["a'b", "c d"], split_command(r"a\'b 'c d'").as_slice());
+    }
+
+    #[test]
+    fn test_write_unavailable() {
+        let mut out = Vec::new();
+        let e = anyhow::anyhow!("Bad page:\n<html>\r\n</html>").context("Failed to look up");
+        write_unavailable(&mut out, &e).unwrap();
+        assert_eq!(
+            "420 server temporarily unavailable: Failed to look up: Bad page: <html> </html>\r\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_respond() {
+        let db = Db::open_in_memory().unwrap();
+        let server = Server {
+            client: &FakeClient,
+            db: Some(&db),
+            explain: &ExplainOptions::default(),
+        };
+        let respond = |line: &str| {
+            let mut out = Vec::new();
+            let more = server.respond(line, &mut out).unwrap();
+            (String::from_utf8(out).unwrap(), more)
+        };
+
+        let (define, _) = respond("define ydcv \"ice cream\"");
+        assert!(define.starts_with(
+            "150 1 definitions retrieved\r\n151 \"ice cream\" ydcv \"Youdao dictionary via ydcv\"\r\n"
+        ));
+        assert!(define.contains("译"));
+        assert!(define.ends_with("\r\n.\r\n250 ok\r\n"));
+        assert_eq!(Some("ice cream".to_string()), db.last_lookup().unwrap());
+
+        assert_eq!(
+            "152 1 matches found\r\nydcv \"hello\"\r\n.\r\n250 ok\r\n",
+            respond("MATCH * exact hello").0
+        );
+        assert!(respond("DEFINE wordnet hello").0.starts_with("550 "));
+        assert!(respond("MATCH * soundex hello").0.starts_with("551 "));
+        assert!(respond("DEFINE hello").0.starts_with("501 "));
+        assert_eq!(("221 bye\r\n".to_string(), false), respond("QUIT"));
+    }
+}
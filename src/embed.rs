@@ -0,0 +1,27 @@
+//! Lookups for the foreign language bindings, on a client of their own
+
+use std::sync::LazyLock;
+
+use anyhow::{Result, anyhow};
+
+use crate::http::{self, HttpOptions};
+use crate::lang::{self, Direction};
+use crate::ydclient::{BlockingYdClient, YdClient};
+use crate::ydresponse::YdResponse;
+
+/// Client shared by every lookup, with the default network settings
+static CLIENT: LazyLock<Result<BlockingYdClient>> = LazyLock::new(|| {
+    Ok(BlockingYdClient::new(http::build_async_client(
+        &HttpOptions::default(),
+    )?)?)
+});
+
+/// Look up `word`, translating it if it's a sentence
+pub fn lookup(word: &str) -> Result<YdResponse> {
+    let client = CLIENT.as_ref().map_err(|e| anyhow!("{e:#}"))?;
+    Ok(if lang::is_sentence(word) {
+        client.translate(word, Direction::detect(word))?
+    } else {
+        client.lookup_word(word)?
+    })
+}
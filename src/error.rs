@@ -0,0 +1,80 @@
+//! errors of lookups, for callers to tell the kinds of failure apart
+
+use std::{fmt, io};
+
+use crate::lang::Lang;
+
+/// Why a lookup failed
+#[derive(Debug)]
+pub enum YdcvError {
+    /// The request couldn't be sent or got an error status
+    #[cfg(not(target_arch = "wasm32"))]
+    Network(reqwest::Error),
+    /// YD refused the request for coming too often
+    RateLimited,
+    /// YD answered without any result, e.g. a translation with an error code
+    NoResult,
+    /// The answer has nothing looked for, e.g. after a change of page layout
+    ParseFailed {
+        /// CSS selector or JSON field that wasn't found
+        selector: &'static str,
+    },
+    /// YD wants a captcha solved before answering
+    Captcha,
+    /// Neither language is Chinese, which YD's dictionaries always need
+    UnsupportedDirection { from: Lang, to: Lang },
+    /// Local I/O failed, e.g. saving a page for `--dump-html`
+    Io(io::Error),
+}
+
+/// Result of the lookup layers
+pub type Result<T, E = YdcvError> = std::result::Result<T, E>;
+
+impl fmt::Display for YdcvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            YdcvError::Network(_) => write!(f, "Request to YD failed"),
+            YdcvError::RateLimited => write!(f, "Rate limited by YD, try again later"),
+            YdcvError::NoResult => write!(f, "No result from YD"),
+            YdcvError::ParseFailed { selector } => {
+                write!(f, "Failed to parse the answer of YD, missing {selector}")
+            }
+            YdcvError::Captcha => write!(f, "YD asks for a captcha, solve it in a browser"),
+            YdcvError::UnsupportedDirection { from, to } => write!(
+                f,
+                "Can't look up from {} to {}, one side must be zh",
+                from.code(),
+                to.code()
+            ),
+            YdcvError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for YdcvError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            YdcvError::Network(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<reqwest::Error> for YdcvError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS) {
+            YdcvError::RateLimited
+        } else {
+            YdcvError::Network(e)
+        }
+    }
+}
+
+impl From<io::Error> for YdcvError {
+    fn from(e: io::Error) -> Self {
+        YdcvError::Io(e)
+    }
+}
@@ -0,0 +1,125 @@
+//! C API of lookups, declared in `include/ydcv.h`
+//!
+//! Every string returned is owned by the caller and freed with
+//! `ydcv_string_free`, except for `ydcv_last_error`.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use anyhow::{Result, anyhow};
+
+use crate::embed;
+
+thread_local! {
+    /// Message of the last failure on this thread, see `ydcv_last_error`
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(e: anyhow::Error) {
+    let message = CString::new(format!("{e:#}").replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|x| *x.borrow_mut() = Some(message));
+}
+
+/// The result of `f`, or a failure if it panics, as panics must not unwind
+/// into C
+fn catch_panic<T>(f: impl FnOnce() -> Result<T>) -> Result<T> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown cause");
+        Err(anyhow!("ydcv panicked: {message}"))
+    })
+}
+
+/// The response to `word` as JSON
+fn lookup(word: &CStr) -> Result<CString> {
+    let resp = embed::lookup(word.to_str()?)?;
+    Ok(CString::new(serde_json::to_string(&resp)?)?)
+}
+
+/// Look up `word` and return the response as JSON, or NULL on failure with
+/// the reason in `ydcv_last_error`
+///
+/// # Safety
+///
+/// `word` must be NULL or a NUL-terminated UTF-8 string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ydcv_lookup(word: *const c_char) -> *mut c_char {
+    let json = catch_panic(|| {
+        if word.is_null() {
+            return Err(anyhow!("word is NULL"));
+        }
+        // SAFETY: non-null and NUL-terminated as promised by the caller
+        lookup(unsafe { CStr::from_ptr(word) })
+    });
+    match json {
+        Ok(json) => json.into_raw(),
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free a string returned by `ydcv_lookup`, doing nothing on NULL
+///
+/// # Safety
+///
+/// `s` must be NULL or a string returned by `ydcv_lookup`, freed only once.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ydcv_string_free(s: *mut c_char) {
+    let freed = catch_panic(|| {
+        if !s.is_null() {
+            // SAFETY: allocated by `CString::into_raw` as promised by the
+            // caller
+            drop(unsafe { CString::from_raw(s) });
+        }
+        Ok(())
+    });
+    if let Err(e) = freed {
+        set_last_error(e);
+    }
+}
+
+/// Why the last lookup on this thread failed, or NULL. The string stays
+/// valid until the next failure on this thread and must not be freed.
+#[unsafe(no_mangle)]
+pub extern "C" fn ydcv_last_error() -> *const c_char {
+    catch_panic(|| Ok(LAST_ERROR.with(|x| x.borrow().as_ref().map_or(ptr::null(), |x| x.as_ptr()))))
+        .unwrap_or(ptr::null())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_null_word() {
+        assert!(unsafe { ydcv_lookup(ptr::null()) }.is_null());
+        let error = unsafe { CStr::from_ptr(ydcv_last_error()) };
+        assert_eq!("word is NULL", error.to_str().unwrap());
+        unsafe { ydcv_string_free(ptr::null_mut()) };
+    }
+
+    #[test]
+    fn test_catch_panic() {
+        let result = catch_panic::<()>(|| panic!("bad page"));
+        assert_eq!("ydcv panicked: bad page", result.unwrap_err().to_string());
+        let result = catch_panic::<()>(|| panic!("{} bad pages", 2));
+        assert_eq!(
+            "ydcv panicked: 2 bad pages",
+            result.unwrap_err().to_string()
+        );
+        assert_eq!(1, catch_panic(|| Ok(1)).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_word() {
+        assert!(unsafe { ydcv_lookup(c"\xff".as_ptr()) }.is_null());
+        assert!(!ydcv_last_error().is_null());
+    }
+}
@@ -7,6 +7,7 @@ use notify_rust::Notification;
 use winrt_notification::{Duration, Toast};
 
 use htmlescape::encode_minimal;
+use std::borrow::Cow;
 
 macro_rules! def {
     ($($n:ident),*) => { $(
@@ -26,6 +27,54 @@ pub trait Formatter {
     fn print(&mut self, word: &str, body: &str);
 }
 
+/// How a `Segment` is styled, one of the `Formatter` methods or none
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// Written as is, e.g. indentation and separators
+    Plain,
+    Default,
+    Red,
+    Yellow,
+    Purple,
+    Cyan,
+    Underline,
+}
+
+impl Style {
+    /// A segment of `text` in this style
+    pub fn of<'a>(self, text: impl Into<Cow<'a, str>>) -> Segment<'a> {
+        Segment {
+            style: self,
+            text: text.into(),
+        }
+    }
+}
+
+/// Piece of an explanation in a single style, see `YdResponse::segments`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment<'a> {
+    pub style: Style,
+    pub text: Cow<'a, str>,
+}
+
+/// Segments of a line of an explanation
+pub type Line<'a> = Vec<Segment<'a>>;
+
+impl Segment<'_> {
+    /// The text styled by `fmt`
+    pub fn render(&self, fmt: &dyn Formatter) -> Cow<'_, str> {
+        match self.style {
+            Style::Plain => Cow::Borrowed(&self.text),
+            Style::Default => fmt.default(&self.text).into(),
+            Style::Red => fmt.red(&self.text).into(),
+            Style::Yellow => fmt.yellow(&self.text).into(),
+            Style::Purple => fmt.purple(&self.text).into(),
+            Style::Cyan => fmt.cyan(&self.text).into(),
+            Style::Underline => fmt.underline(&self.text).into(),
+        }
+    }
+}
+
 /// Plain text formatter
 pub struct PlainFormatter;
 
@@ -196,17 +245,150 @@ impl Formatter for HtmlFormatter {
     }
 }
 
+/// Stylesheet of the documents of `GoldenDictFormatter`
+const GOLDENDICT_STYLE: &str = ".ydcv { white-space: pre-wrap; font-family: sans-serif; }
+.red { color: #c0392b; }
+.yellow { color: #b8860b; }
+.purple { color: #8e44ad; }
+.cyan { color: #16808a; font-weight: bold; }";
+
+/// Formatter of whole HTML documents, as read by the "programs" sources of
+/// GoldenDict
+pub struct GoldenDictFormatter;
+
+macro_rules! css {
+    ($($n:ident),*) => { $(
+        fn $n (&self, s: &str) -> String {
+            format!(r#"<span class="{}">{}</span>"#, stringify!($n), encode_minimal(s))
+        }
+    )* }
+}
+
+impl GoldenDictFormatter {
+    pub fn new(_: bool) -> GoldenDictFormatter {
+        GoldenDictFormatter {}
+    }
+
+    /// Document of the explanation `body` of `word`, also the HTML pages of
+    /// `ydcv serve`
+    pub fn document(word: &str, body: &str) -> String {
+        format!(
+            "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{}</title>
+<style>
+{GOLDENDICT_STYLE}
+</style>
+</head>
+<body>
+<div class=\"ydcv\">{body}</div>
+</body>
+</html>",
+            encode_minimal(word)
+        )
+    }
+}
+
+impl Formatter for GoldenDictFormatter {
+    css!(red, yellow, purple, cyan);
+
+    fn underline(&self, s: &str) -> String {
+        format!("<b>{}</b>", encode_minimal(s))
+    }
+    fn default(&self, s: &str) -> String {
+        encode_minimal(s)
+    }
+
+    fn print(&mut self, word: &str, body: &str) {
+        println!("{}", Self::document(word, body));
+    }
+}
+
+/// `s` with the characters special to MarkdownV2 escaped
+pub fn escape_markdown(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "_*[]()~`>#+-=|{}.!\\".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Formatter of the MarkdownV2 of Telegram messages, escaping what it styles
+/// but not `Style::Plain` segments, see `escape_markdown`
+pub struct MarkdownFormatter;
+
+impl MarkdownFormatter {
+    pub fn new(_: bool) -> MarkdownFormatter {
+        MarkdownFormatter {}
+    }
+}
+
+macro_rules! markdown {
+    ($( $n:ident = $x:expr ),*) => { $(
+        fn $n (&self, s: &str) -> String {
+            format!("{0}{1}{0}", $x, escape_markdown(s))
+        }
+    )* }
+}
+
+impl Formatter for MarkdownFormatter {
+    markdown!(
+        red = "*",
+        yellow = "_",
+        purple = "_",
+        cyan = "*",
+        underline = "__"
+    );
+    fn default(&self, s: &str) -> String {
+        escape_markdown(s)
+    }
+
+    fn print(&mut self, _: &str, body: &str) {
+        println!("{body}");
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::formatters::HtmlFormatter;
+    use crate::YD_CLIENT;
+    use crate::formatters::{
+        GoldenDictFormatter, HtmlFormatter, MarkdownFormatter, escape_markdown,
+    };
     use crate::ydclient::*;
-    use reqwest::blocking::Client;
+    use crate::ydresponse::YdResponse;
+
+    #[test]
+    fn test_goldendict() {
+        let body = YdResponse::fake("a<b", "你好").explain(&GoldenDictFormatter::new(false));
+        assert_eq!(
+            "<b>a&lt;b</b>\n<span class=\"cyan\">  Translation:</span>\n    你好",
+            body
+        );
+        let document = GoldenDictFormatter::document("a<b", &body);
+        assert!(document.starts_with("<!DOCTYPE html>"));
+        assert!(document.contains("<title>a&lt;b</title>"));
+        assert!(document.contains(&format!("<div class=\"ydcv\">{body}</div>")));
+    }
+
+    #[test]
+    fn test_markdown() {
+        assert_eq!(r"1\.5 \(a\_b\)", escape_markdown("1.5 (a_b)"));
+        assert_eq!(
+            "__a\\-b__\n*  Translation:*\n    你好",
+            YdResponse::fake("a-b", "你好").explain(&MarkdownFormatter::new(false))
+        );
+    }
 
     #[test]
     fn test_explain_html_1() {
         let result = format!(
             "\n{}\n",
-            Client::new()
+            YD_CLIENT
                 .lookup_word("hakunamatata")
                 .unwrap()
                 .explain(&HtmlFormatter::new(false))
@@ -223,7 +405,7 @@ mod tests {
     fn test_explain_html_2() {
         let result = format!(
             "\n{}\n",
-            Client::new()
+            YD_CLIENT
                 .lookup_word("comment")
                 .unwrap()
                 .explain(&HtmlFormatter::new(false))
@@ -251,7 +433,7 @@ mod tests {
     fn test_explain_html_3() {
         let result = format!(
             "\n{}\n",
-            Client::new()
+            YD_CLIENT
                 .lookup_word("暂时")
                 .unwrap()
                 .explain(&HtmlFormatter::new(false))
@@ -0,0 +1,94 @@
+//! gRPC service with the Lookup, Suggest and AddToWordbook RPCs of
+//! `proto/ydcv.proto`, for embedding `ydcv` into self-hosted
+//! language-learning stacks
+
+use std::net::SocketAddr;
+
+use anyhow::Result;
+use tokio::runtime;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+use crate::lookups::Lookups;
+use crate::ydresponse::YdResponse;
+
+mod proto {
+    tonic::include_proto!("ydcv.v1");
+}
+
+use proto::ydcv_server::{Ydcv, YdcvServer};
+use proto::{AddToWordbookReply, LookupReply, LookupRequest, SuggestReply};
+
+struct Service(Lookups);
+
+impl Service {
+    /// Look up the word of `request`
+    async fn lookup_request(&self, request: Request<LookupRequest>) -> Result<YdResponse, Status> {
+        let word = request.into_inner().word;
+        if word.trim().is_empty() {
+            return Err(Status::invalid_argument("Missing word"));
+        }
+        self.0
+            .lookup(word.trim())
+            .await
+            .map_err(|e| Status::unavailable(format!("{e:#}")))
+    }
+}
+
+#[tonic::async_trait]
+impl Ydcv for Service {
+    async fn lookup(
+        &self,
+        request: Request<LookupRequest>,
+    ) -> Result<Response<LookupReply>, Status> {
+        let resp = self.lookup_request(request).await?;
+        let (text, html) = self.0.explain_response(&resp);
+        Ok(Response::new(LookupReply {
+            query: resp.query().to_string(),
+            text,
+            html,
+            json: serde_json::to_string(&resp).map_err(|e| Status::internal(e.to_string()))?,
+        }))
+    }
+
+    async fn suggest(
+        &self,
+        request: Request<LookupRequest>,
+    ) -> Result<Response<SuggestReply>, Status> {
+        let resp = self.lookup_request(request).await?;
+        Ok(Response::new(SuggestReply {
+            suggestions: resp.suggestions().to_vec(),
+        }))
+    }
+
+    async fn add_to_wordbook(
+        &self,
+        request: Request<LookupRequest>,
+    ) -> Result<Response<AddToWordbookReply>, Status> {
+        let resp = self.lookup_request(request).await?;
+        if !resp.has_result() {
+            return Err(Status::not_found(format!("No result for {}", resp.query())));
+        }
+        let added = self
+            .0
+            .wordbook_add(&resp)
+            .map_err(|e| Status::failed_precondition(format!("{e:#}")))?;
+        Ok(Response::new(AddToWordbookReply { added }))
+    }
+}
+
+/// Serve `lookups` on `listen` until killed
+pub fn serve(lookups: Lookups, listen: SocketAddr) -> Result<()> {
+    runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(async {
+            eprintln!("Listening on {listen}");
+            Server::builder()
+                .add_service(YdcvServer::new(Service(lookups)))
+                .serve(listen)
+                .await?;
+
+            Ok(())
+        })
+}
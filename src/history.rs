@@ -0,0 +1,80 @@
+//! export of the lookup history
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::db::Db;
+
+/// Output format of `ydcv history export`
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Serialize)]
+struct ExportRecord {
+    word: String,
+    timestamp: String,
+    translation: String,
+}
+
+/// Write the whole lookup history, with translations taken from the cache
+pub fn export(db: &Db, format: ExportFormat, out: &mut dyn Write) -> Result<()> {
+    let mut records = vec![];
+    for entry in db.history()? {
+        let translation = db
+            .cached_any_age(&entry.word)?
+            .map(|x| x.translation().join("; "))
+            .unwrap_or_default();
+
+        records.push(ExportRecord {
+            word: entry.word,
+            timestamp: entry.looked_up_at.to_rfc3339(),
+            translation,
+        });
+    }
+
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for record in &records {
+                writer.serialize(record)?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, &records)?;
+            writeln!(out)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fake;
+
+    #[test]
+    fn test_export_csv() {
+        let db = Db::open_in_memory().unwrap();
+        let resp = fake("hello", "你好");
+        db.record_lookup("hello").unwrap();
+        db.cache_response(&resp).unwrap();
+        db.record_lookup("nothing").unwrap();
+
+        let mut out = vec![];
+        export(&db, ExportFormat::Csv, &mut out).unwrap();
+        let lines = String::from_utf8(out).unwrap();
+        let lines = lines.lines().collect::<Vec<_>>();
+
+        assert_eq!("word,timestamp,translation", lines[0]);
+        assert!(lines[1].starts_with("hello,") && lines[1].ends_with(",你好"));
+        assert!(lines[2].starts_with("nothing,") && lines[2].ends_with(','));
+    }
+}
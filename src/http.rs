@@ -0,0 +1,461 @@
+//! http client shared by every request
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::sleep;
+use std::time::Duration;
+
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use log::{debug, warn};
+use reqwest::Proxy;
+use reqwest::blocking::{Client, ClientBuilder, RequestBuilder, Response};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+
+use crate::cookies::CookieJar;
+
+/// Browser User-Agents, one of which is sent with each request
+const USER_AGENTS: [&str; 4] = [
+    "Mozilla/5.0 (X11; AOSC OS; Linux x86_64; rv:109.0) Gecko/20100101 Firefox/117.0",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Safari/605.1.15",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0",
+];
+
+/// User-Agents configured instead of `USER_AGENTS`, set by `build_client`
+static CUSTOM_AGENTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Retries of `send_retrying`, set by `build_client`
+static RETRIES: AtomicU32 = AtomicU32::new(0);
+
+/// Interval of TCP keep-alive probes, so that idle connections kept in the
+/// pool between lookups aren't silently dropped by NAT
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+
+/// Timeout of a whole request if not set, as reqwest's blocking client has
+/// but not its async one
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Delay before the first retry, doubled for each following one
+const BACKOFF: Duration = Duration::from_millis(500);
+
+/// Longest delay before a retry
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Most retries of a request, however many are configured
+const MAX_RETRIES: u32 = 10;
+
+/// Settings of the shared client from the command line and config file
+#[derive(Default)]
+pub struct HttpOptions {
+    /// URL of the proxy for every request, like `http://host:port` or
+    /// `socks5h://host:port`
+    pub proxy: Option<String>,
+    /// Timeout of a whole request, `DEFAULT_TIMEOUT` if not set
+    pub timeout: Option<Duration>,
+    /// Times to retry a request that timed out or got a server error
+    pub retries: u32,
+    /// Extra root certificates in PEM or DER, e.g. of a corporate proxy
+    pub ca_cert: Option<PathBuf>,
+    /// Accept invalid TLS certificates
+    pub insecure: bool,
+    /// User-Agents to pick from for each request, the built-in ones if empty
+    pub user_agents: Vec<String>,
+    /// Extra headers of every request, a `User-Agent` here disables rotation
+    pub headers: Vec<(String, String)>,
+    /// File to keep cookies in between runs, cookies are dropped if not set
+    pub cookie_jar: Option<PathBuf>,
+    /// Ask for uncompressed responses, e.g. to read them in a packet capture
+    pub no_compression: bool,
+    /// How long an idle connection is kept for the next request, reqwest's
+    /// default of 90s if not set, or forever if zero
+    pub pool_idle_timeout: Option<Duration>,
+}
+
+/// Proxy URL of a SOCKS5 proxy at `addr`, resolving host names through the
+/// proxy unless `addr` already has a scheme
+pub fn socks5_url(addr: &str) -> String {
+    if addr.contains("://") {
+        addr.to_string()
+    } else {
+        format!("socks5h://{addr}")
+    }
+}
+
+/// Parse a header given as `Name: value`
+pub fn parse_header(header: &str) -> Result<(String, String)> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Expected `Name: value`, got {header}"))?;
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
+/// Cookies shared by the blocking and the async client
+static COOKIE_JAR: OnceLock<Arc<CookieJar>> = OnceLock::new();
+
+/// Build the blocking client used for every request but lookups
+pub fn build_client(options: &HttpOptions) -> Result<Client> {
+    // the blocking client has a timeout of its own
+    ClientBuilder::from(async_builder(options)?)
+        .timeout(options.timeout.unwrap_or(DEFAULT_TIMEOUT))
+        .build()
+        .context("Failed to create http client")
+}
+
+/// Build the async client of lookups
+pub fn build_async_client(options: &HttpOptions) -> Result<reqwest::Client> {
+    async_builder(options)?
+        .build()
+        .context("Failed to create http client")
+}
+
+/// Builder with every option applied, on the TLS backend chosen by cargo
+/// features (rustls if both are enabled)
+fn async_builder(options: &HttpOptions) -> Result<reqwest::ClientBuilder> {
+    let mut headers = HeaderMap::new();
+    let mut agents = options.user_agents.clone();
+    for (name, value) in &options.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .with_context(|| format!("Invalid header name {name}"))?;
+        if name == USER_AGENT {
+            agents = vec![value.clone()];
+        }
+        let value = HeaderValue::from_str(value)
+            .with_context(|| format!("Invalid value of header {name}"))?;
+        headers.insert(name, value);
+    }
+    *CUSTOM_AGENTS.lock().unwrap() = agents;
+
+    // gzip and brotli responses are accepted and decompressed by default
+    let mut builder = reqwest::ClientBuilder::new()
+        .default_headers(headers)
+        .gzip(!options.no_compression)
+        .brotli(!options.no_compression)
+        .tcp_keepalive(TCP_KEEPALIVE)
+        .timeout(options.timeout.unwrap_or(DEFAULT_TIMEOUT));
+    if let Some(timeout) = options.pool_idle_timeout {
+        builder = builder.pool_idle_timeout((!timeout.is_zero()).then_some(timeout));
+    }
+    if let Some(path) = &options.cookie_jar {
+        let jar = COOKIE_JAR.get_or_init(|| Arc::new(CookieJar::load(path.clone())));
+        builder = builder.cookie_provider(jar.clone());
+    }
+    RETRIES.store(options.retries.min(MAX_RETRIES), Ordering::Relaxed);
+    if let Some(proxy) = &options.proxy {
+        builder =
+            builder.proxy(Proxy::all(proxy).with_context(|| format!("Invalid proxy {proxy}"))?);
+    }
+
+    #[cfg(any(feature = "native-tls", feature = "rustls"))]
+    {
+        if let Some(path) = &options.ca_cert {
+            builder = builder.tls_certs_merge(load_certs(path)?);
+        }
+        if options.insecure {
+            warn!("TLS certificate verification is disabled");
+            builder = builder.tls_danger_accept_invalid_certs(true);
+        }
+    }
+
+    #[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+    if options.ca_cert.is_some() || options.insecure {
+        warn!("TLS options are ignored without a TLS backend");
+    }
+
+    #[cfg(feature = "rustls")]
+    let builder = {
+        // fails only if a provider is already installed
+        rustls::crypto::ring::default_provider()
+            .install_default()
+            .ok();
+        builder.tls_backend_rustls()
+    };
+    #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+    let builder = builder.tls_backend_native();
+
+    // without a proxy, reqwest will use HTTPS_PROXY and NO_PROXY env
+    Ok(builder)
+}
+
+/// Certificates of a PEM bundle or a DER file
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+fn load_certs(path: &Path) -> Result<Vec<reqwest::Certificate>> {
+    let bytes =
+        fs::read(path).with_context(|| format!("Failed to read certificate {}", path.display()))?;
+    if bytes.windows(10).any(|x| x == b"-----BEGIN") {
+        reqwest::Certificate::from_pem_bundle(&bytes)
+    } else {
+        reqwest::Certificate::from_der(&bytes).map(|x| vec![x])
+    }
+    .with_context(|| format!("Invalid certificate {}", path.display()))
+}
+
+/// A User-Agent for the next request, picked at random
+fn user_agent() -> String {
+    let agents = CUSTOM_AGENTS.lock().unwrap();
+    match fastrand::choice(agents.iter()) {
+        Some(agent) => agent.clone(),
+        None => fastrand::choice(USER_AGENTS).unwrap().to_string(),
+    }
+}
+
+/// Blocking requests sent with a rotated User-Agent and retries
+pub trait SendRetrying {
+    /// Send the request like `send` with a rotated User-Agent, retrying with
+    /// exponential backoff on timeouts, connection errors and server errors
+    fn send_retrying(self) -> reqwest::Result<Response>;
+}
+
+impl SendRetrying for RequestBuilder {
+    fn send_retrying(self) -> reqwest::Result<Response> {
+        send_with_retries(self.header(USER_AGENT, user_agent()), retries())
+    }
+}
+
+/// Async requests sent like `SendRetrying`
+pub trait SendRetryingAsync {
+    /// Async counterpart of `SendRetrying::send_retrying`
+    fn send_retrying(self) -> impl Future<Output = reqwest::Result<reqwest::Response>>;
+}
+
+impl SendRetryingAsync for reqwest::RequestBuilder {
+    async fn send_retrying(self) -> reqwest::Result<reqwest::Response> {
+        let request = self.header(USER_AGENT, user_agent());
+        for attempt in 0..retries() {
+            let Some(retry) = request.try_clone() else {
+                break;
+            };
+            match retry.send().await {
+                Ok(resp) if resp.status().is_server_error() => {
+                    debug!("Retrying after {}", resp.status())
+                }
+                Err(e) if e.is_timeout() || e.is_connect() => debug!("Retrying after {e}"),
+                result => return result,
+            }
+            tokio::time::sleep(backoff(attempt)).await;
+        }
+
+        request.send().await
+    }
+}
+
+/// Times to retry a request, as set by `build_client`
+fn retries() -> u32 {
+    RETRIES.load(Ordering::Relaxed)
+}
+
+/// Delay before the retry after the failed attempt `attempt`, counted from 0
+fn backoff(attempt: u32) -> Duration {
+    BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF)
+}
+
+fn send_with_retries(request: RequestBuilder, retries: u32) -> reqwest::Result<Response> {
+    for attempt in 0..retries {
+        // requests with a streaming body can't be retried
+        let Some(retry) = request.try_clone() else {
+            break;
+        };
+        match retry.send() {
+            Ok(resp) if resp.status().is_server_error() => {
+                debug!("Retrying after {}", resp.status())
+            }
+            Err(e) if e.is_timeout() || e.is_connect() => debug!("Retrying after {e}"),
+            result => return result,
+        }
+        sleep(backoff(attempt));
+    }
+
+    request.send()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    const TEST_CA: &str = "-----BEGIN CERTIFICATE-----\n\
+MIIBhDCCASugAwIBAgIUMIlxlfBHe9/pPlBGP6dfsQucAJEwCgYIKoZIzj0EAwIw\n\
+FzEVMBMGA1UEAwwMeWRjdiB0ZXN0IENBMCAXDTI2MTAxNjA0MzMwMFoYDzIxMjYw\n\
+OTIyMDQzMzAwWjAXMRUwEwYDVQQDDAx5ZGN2IHRlc3QgQ0EwWTATBgcqhkjOPQIB\n\
+BggqhkjOPQMBBwNCAAQlhluDdZA5v8Yx9v/ld9g1LJhC/t+TOQ19pzk4HkBquNyS\n\
+LZkJDTXZn6bB92oYLrxoQHaAaD3e+CyYyDfC2uELo1MwUTAdBgNVHQ4EFgQU50l3\n\
+YK8GgInZQXgGYBRA0E6FabowHwYDVR0jBBgwFoAU50l3YK8GgInZQXgGYBRA0E6F\n\
+abowDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNHADBEAiAdfyWcKYG5BvZ+\n\
+XOFbf4gnpp8kGNtaYsbKPV5heRibiAIgLCgRfX1qp3bIbd4mpEGU4voWQxUbaWQZ\n\
+ghM8i23n3mI=\n\
+-----END CERTIFICATE-----\n";
+
+    #[test]
+    fn test_proxy() {
+        assert_eq!("socks5h://127.0.0.1:1080", socks5_url("127.0.0.1:1080"));
+        assert_eq!(
+            "socks5://localhost:1080",
+            socks5_url("socks5://localhost:1080")
+        );
+
+        let options = HttpOptions {
+            proxy: Some(socks5_url("127.0.0.1:1080")),
+            ..Default::default()
+        };
+        assert!(build_client(&options).is_ok());
+        let options = HttpOptions {
+            proxy: Some("not a url".to_string()),
+            ..Default::default()
+        };
+        assert!(build_client(&options).is_err());
+    }
+
+    #[test]
+    fn test_headers() {
+        assert_eq!(
+            ("Accept-Language".to_string(), "zh-CN,zh".to_string()),
+            parse_header("Accept-Language: zh-CN,zh").unwrap()
+        );
+        assert!(parse_header("no colon").is_err());
+
+        let options = HttpOptions {
+            headers: vec![("Bad Name".to_string(), "x".to_string())],
+            ..Default::default()
+        };
+        assert!(build_client(&options).is_err());
+    }
+
+    #[test]
+    fn test_ca_cert() {
+        let path = env::temp_dir().join("ydcv-test-ca.pem");
+        fs::write(&path, TEST_CA).unwrap();
+        assert_eq!(1, load_certs(&path).unwrap().len());
+        let options = HttpOptions {
+            ca_cert: Some(path.clone()),
+            insecure: true,
+            ..Default::default()
+        };
+        assert!(build_client(&options).is_ok());
+
+        fs::write(
+            &path,
+            "-----BEGIN CERTIFICATE-----\n!!!\n-----END CERTIFICATE-----\n",
+        )
+        .unwrap();
+        assert!(load_certs(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_compression() {
+        const HELLO_GZIP: [u8; 25] = [
+            31, 139, 8, 0, 0, 0, 0, 0, 2, 3, 203, 72, 205, 201, 201, 7, 0, 134, 166, 16, 54, 5, 0,
+            0, 0,
+        ];
+
+        for no_compression in [false, true] {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let server = thread::spawn(move || {
+                let (mut stream, _) = listener.accept().unwrap();
+                let head = BufReader::new(&stream)
+                    .lines()
+                    .map_while(Result::ok)
+                    .take_while(|x| !x.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+                    .to_lowercase();
+                let gzip = head.contains("accept-encoding: ") && head.contains("gzip");
+                if gzip {
+                    write!(stream, "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", HELLO_GZIP.len()).unwrap();
+                    stream.write_all(&HELLO_GZIP).unwrap();
+                } else {
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello"
+                    )
+                    .unwrap();
+                }
+                gzip
+            });
+
+            let client = build_client(&HttpOptions {
+                no_compression,
+                ..Default::default()
+            })
+            .unwrap();
+            assert_eq!("hello", client.get(&url).send().unwrap().text().unwrap());
+            assert_eq!(!no_compression, server.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_send_retrying() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            for status in ["503 Service Unavailable", "200 OK"] {
+                let (mut stream, _) = listener.accept().unwrap();
+                // skip the request head
+                BufReader::new(&stream)
+                    .lines()
+                    .map_while(Result::ok)
+                    .take_while(|x| !x.is_empty())
+                    .for_each(drop);
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+            }
+        });
+
+        let client = build_client(&HttpOptions::default()).unwrap();
+        let resp = send_with_retries(client.get(&url), 1).unwrap();
+        assert_eq!(200, resp.status().as_u16());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(Duration::from_millis(500), backoff(0));
+        assert_eq!(Duration::from_secs(2), backoff(2));
+        assert_eq!(MAX_BACKOFF, backoff(40));
+    }
+
+    #[test]
+    fn test_keep_alive() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        // answers both requests on the first connection only
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            for _ in 0..2 {
+                (&mut reader)
+                    .lines()
+                    .map_while(Result::ok)
+                    .take_while(|x| !x.is_empty())
+                    .for_each(drop);
+                (&stream)
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                    .unwrap();
+            }
+        });
+
+        let client = build_client(&HttpOptions {
+            timeout: Some(Duration::from_secs(5)),
+            pool_idle_timeout: Some(Duration::ZERO),
+            ..Default::default()
+        })
+        .unwrap();
+        for _ in 0..2 {
+            assert_eq!("ok", client.get(&url).send().unwrap().text().unwrap());
+        }
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+//! Key bindings of the interactive prompt, acting on the last result as set
+//! in `[keys]` of the config
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result, bail};
+use rustyline::history::FileHistory;
+use rustyline::{
+    Cmd, ConditionalEventHandler, Editor, Event, EventContext, EventHandler, KeyCode, KeyEvent,
+    Modifiers, RepeatCount,
+};
+
+/// What a key does to the last result
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Speak,
+    AddToWordbook,
+    OpenBrowser,
+    Redisplay,
+    LookUpSuggestion,
+}
+
+/// Names of the actions in `[keys]`
+pub const ACTIONS: [&str; 5] = [
+    "speak",
+    "add_to_wordbook",
+    "open_browser",
+    "redisplay",
+    "look_up_suggestion",
+];
+
+/// Key looking up the first spelling suggestion unless set in `[keys]`
+const SUGGESTION_KEY: &str = "ctrl-y";
+
+impl Action {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "speak" => Some(Action::Speak),
+            "add_to_wordbook" => Some(Action::AddToWordbook),
+            "open_browser" => Some(Action::OpenBrowser),
+            "redisplay" => Some(Action::Redisplay),
+            "look_up_suggestion" => Some(Action::LookUpSuggestion),
+            _ => None,
+        }
+    }
+}
+
+/// Key written like `ctrl-s`, `alt-a` or `f2`
+fn parse_key(key: &str) -> Result<KeyEvent> {
+    let lower = key.to_lowercase();
+    let (mods, name) = match lower.rsplit_once('-') {
+        Some((mods, name)) if !name.is_empty() => (mods, name),
+        _ => ("", lower.as_str()),
+    };
+    let mut modifiers = Modifiers::NONE;
+    for x in mods.split('-').filter(|x| !x.is_empty()) {
+        modifiers |= match x {
+            "ctrl" | "c" => Modifiers::CTRL,
+            "alt" | "meta" | "m" => Modifiers::ALT,
+            "shift" | "s" => Modifiers::SHIFT,
+            _ => bail!("Unknown modifier {x} in key {key}"),
+        };
+    }
+
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(KeyEvent::new(c, modifiers)),
+        _ => match name.strip_prefix('f').and_then(|x| x.parse().ok()) {
+            Some(n @ 1..=24) => Ok(KeyEvent(KeyCode::F(n), modifiers)),
+            _ => {
+                bail!("Unknown key {key}, use a character or f1 to f24 with ctrl-, alt- or shift-")
+            }
+        },
+    }
+}
+
+/// Accepts the empty line, leaving the action for the prompt to run
+struct Handler {
+    action: Action,
+    pending: Arc<Mutex<Option<Action>>>,
+}
+
+impl ConditionalEventHandler for Handler {
+    fn handle(&self, _: &Event, _: RepeatCount, _: bool, ctx: &EventContext) -> Option<Cmd> {
+        // the key keeps its editing command while typing a word
+        if !ctx.line().is_empty() {
+            return None;
+        }
+        *self.pending.lock().unwrap() = Some(self.action);
+        Some(Cmd::AcceptLine)
+    }
+}
+
+/// Key of the `look_up_suggestion` action in `keys`
+pub fn suggestion_key(keys: &BTreeMap<String, String>) -> &str {
+    keys.get("look_up_suggestion")
+        .map_or(SUGGESTION_KEY, |x| x.as_str())
+}
+
+/// Bind the keys of `keys`, by action name, in `editor`, giving where the
+/// action of a pressed key is left
+pub fn bind(
+    editor: &mut Editor<(), FileHistory>,
+    keys: &BTreeMap<String, String>,
+) -> Result<Arc<Mutex<Option<Action>>>> {
+    let pending = Arc::new(Mutex::new(None));
+    let suggestion = ("look_up_suggestion", suggestion_key(keys));
+    for (name, key) in keys
+        .iter()
+        .map(|(x, y)| (x.as_str(), y.as_str()))
+        .filter(|x| x.0 != suggestion.0)
+        .chain([suggestion])
+    {
+        let action = Action::from_name(name)
+            .with_context(|| format!("Unknown action {name}, one of {}", ACTIONS.join(", ")))?;
+        let handler = Handler {
+            action,
+            pending: pending.clone(),
+        };
+        editor.bind_sequence(
+            parse_key(key)?,
+            EventHandler::Conditional(Box::new(handler)),
+        );
+    }
+
+    Ok(pending)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(KeyEvent::ctrl('s'), parse_key("Ctrl-S").unwrap());
+        assert_eq!(KeyEvent::alt('a'), parse_key("alt-a").unwrap());
+        assert_eq!(
+            KeyEvent(KeyCode::F(2), Modifiers::NONE),
+            parse_key("f2").unwrap()
+        );
+        assert!(parse_key("hyper-x").is_err());
+        assert!(parse_key("f99").is_err());
+    }
+
+    #[test]
+    fn test_suggestion_key() {
+        let mut keys = BTreeMap::new();
+        assert_eq!("ctrl-y", suggestion_key(&keys));
+        keys.insert("look_up_suggestion".to_string(), "f3".to_string());
+        assert_eq!("f3", suggestion_key(&keys));
+    }
+}
@@ -1,3 +1,456 @@
+use crate::error::{Result, YdcvError};
+use clap::ValueEnum;
+use pinyin::ToPinyin;
+use serde::{Deserialize, Serialize};
+
+/// Language of a query or its translation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    Zh,
+    En,
+    Ja,
+    Ko,
+    Fr,
+    De,
+    Es,
+    Ru,
+}
+
+impl Lang {
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::Zh => "zh",
+            Lang::En => "en",
+            Lang::Ja => "ja",
+            Lang::Ko => "ko",
+            Lang::Fr => "fr",
+            Lang::De => "de",
+            Lang::Es => "es",
+            Lang::Ru => "ru",
+        }
+    }
+
+    /// Language of a code like `ja`, case-insensitive
+    pub fn from_code(code: &str) -> Option<Lang> {
+        Lang::from_str(code, true).ok()
+    }
+
+    /// Guess the language of `word`, see `detect_lang`
+    pub fn detect(word: &str) -> Lang {
+        detect_lang(word).lang
+    }
+}
+
+/// Writing system of a letter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Script {
+    Han,
+    Kana,
+    Hangul,
+    Cyrillic,
+    Latin,
+}
+
+impl Script {
+    pub fn of(ch: char) -> Option<Script> {
+        match ch as u32 {
+            0x4e00..=0x9fff
+            | 0x3400..=0x4dbf
+            | 0x20000..=0x2ceaf
+            | 0xf900..=0xfaff
+            | 0x2f800..=0x2fa1f => Some(Script::Han),
+            0x3040..=0x30ff | 0x31f0..=0x31ff | 0xff66..=0xff9f => Some(Script::Kana),
+            0xac00..=0xd7af | 0x1100..=0x11ff | 0x3130..=0x318f => Some(Script::Hangul),
+            0x0400..=0x04ff => Some(Script::Cyrillic),
+            _ if ch.is_alphabetic() && (ch.is_ascii() || ('\u{c0}'..='\u{24f}').contains(&ch)) => {
+                Some(Script::Latin)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Result of `detect_lang`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Detection {
+    pub lang: Lang,
+    /// From 0 to 1, the share of letters supporting the guess, lowered for
+    /// guesses between languages of the same script
+    pub confidence: f32,
+}
+
+/// Guess the language of `text` from the scripts of its letters.
+///
+/// Any kana means Japanese since kanji alone can't be told apart from
+/// Chinese, and any hangul means Korean. Latin text is English unless it has
+/// letters specific to French, German or Spanish.
+pub fn detect_lang(text: &str) -> Detection {
+    let scripts = text.chars().filter_map(Script::of).collect::<Vec<_>>();
+    let count = |script| scripts.iter().filter(|x| **x == script).count();
+    let share = |n: usize| n as f32 / scripts.len() as f32;
+
+    if scripts.is_empty() {
+        return Detection {
+            lang: Lang::En,
+            confidence: 0.0,
+        };
+    }
+
+    let kana = count(Script::Kana);
+    if kana > 0 {
+        return Detection {
+            lang: Lang::Ja,
+            confidence: share(kana + count(Script::Han)),
+        };
+    }
+
+    let hangul = count(Script::Hangul);
+    if hangul > 0 {
+        return Detection {
+            lang: Lang::Ko,
+            confidence: share(hangul + count(Script::Han)),
+        };
+    }
+
+    let (han, cyrillic, latin) = (
+        count(Script::Han),
+        count(Script::Cyrillic),
+        count(Script::Latin),
+    );
+    if han >= cyrillic && han >= latin {
+        Detection {
+            lang: Lang::Zh,
+            confidence: share(han),
+        }
+    } else if cyrillic >= latin {
+        Detection {
+            lang: Lang::Ru,
+            confidence: share(cyrillic),
+        }
+    } else {
+        let has = |letters: &str| {
+            text.chars()
+                .any(|x| letters.contains(x.to_ascii_lowercase()))
+        };
+        let lang = if has("ñ¿¡") {
+            Lang::Es
+        } else if has("äöüß") {
+            Lang::De
+        } else if has("çœèêëàâîïôûù") {
+            Lang::Fr
+        } else {
+            Lang::En
+        };
+        Detection {
+            lang,
+            confidence: share(latin) * if lang == Lang::En { 0.8 } else { 0.6 },
+        }
+    }
+}
+
+/// Split a language prefix like `ja:` off a query
+pub fn split_prefix(query: &str) -> (Option<Lang>, &str) {
+    match query.split_once(':') {
+        Some((code, rest)) if !rest.trim().is_empty() => match Lang::from_code(code.trim()) {
+            Some(lang) => (Some(lang), rest.trim()),
+            None => (None, query),
+        },
+        _ => (None, query),
+    }
+}
+
+/// Whether `text` reads as a sentence to translate rather than a word or
+/// short phrase to look up: more than four words, punctuation between words,
+/// or more than eight CJK characters
+pub fn is_sentence(text: &str) -> bool {
+    let text = text
+        .trim()
+        .trim_end_matches(['.', '!', '?', '。', '！', '？']);
+    let tokens = text.split_whitespace().collect::<Vec<_>>();
+    let words = tokens.len();
+    let cjk = text
+        .chars()
+        .filter(|x| {
+            matches!(
+                Script::of(*x),
+                Some(Script::Han | Script::Kana | Script::Hangul)
+            )
+        })
+        .count();
+    // Latin punctuation only counts ending a word followed by another, so
+    // that `a.m.`, `U.S.` or `10:30` are still words, while CJK text has no
+    // spaces to go by
+    let punctuated = text.chars().any(|x| "，。；：！？、".contains(x))
+        || tokens.iter().rev().skip(1).any(|x| {
+            x.ends_with([',', ';', ':', '!', '?', '"'])
+                || x.ends_with('.') && x.matches('.').count() == 1
+        });
+
+    words > 4 || cjk > 8 || punctuated
+}
+
+/// Source and target language of a lookup, one of which is Chinese
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Direction {
+    pub from: Lang,
+    pub to: Lang,
+}
+
+impl Direction {
+    /// Direction between `from` and `to`, guessing the missing ones from
+    /// `word`. Chinese words are translated to English by default and other
+    /// words to Chinese.
+    pub fn resolve(word: &str, from: Option<Lang>, to: Option<Lang>) -> Result<Self> {
+        let from = from.unwrap_or_else(|| match to {
+            Some(Lang::Zh) | None => Lang::detect(word),
+            Some(_) => Lang::Zh,
+        });
+        let to = to.unwrap_or(if from == Lang::Zh { Lang::En } else { Lang::Zh });
+
+        if from == to || (from != Lang::Zh && to != Lang::Zh) {
+            return Err(YdcvError::UnsupportedDirection { from, to });
+        }
+
+        Ok(Direction { from, to })
+    }
+
+    /// Direction guessed from `word` alone
+    pub fn detect(word: &str) -> Self {
+        let from = Lang::detect(word);
+        let to = if from == Lang::Zh { Lang::En } else { Lang::Zh };
+        Direction { from, to }
+    }
+
+    /// Direction between Chinese and `lang` for `word`, from Chinese if
+    /// `word` is Chinese
+    pub fn pair(word: &str, lang: Lang) -> Self {
+        match lang {
+            Lang::Zh => Direction::detect(word),
+            _ if Lang::detect(word) == Lang::Zh => Direction {
+                from: Lang::Zh,
+                to: lang,
+            },
+            _ => Direction {
+                from: lang,
+                to: Lang::Zh,
+            },
+        }
+    }
+
+    /// The foreign side, sent as YD's `lang` parameter
+    pub fn dict(self) -> Lang {
+        if self.from == Lang::Zh {
+            self.to
+        } else {
+            self.from
+        }
+    }
+}
+
+/// Traditional Chinese variant to show results in
+#[cfg(feature = "traditional")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ZhVariant {
+    /// Traditional characters
+    Hant,
+    /// Traditional characters and phrasing of Taiwan
+    Tw,
+    /// Traditional characters and phrasing of Hong Kong
+    Hk,
+}
+
+/// Whether `text` is confidently written in traditional Chinese, text that's
+/// the same in both scripts isn't
+#[cfg(feature = "traditional")]
+pub fn is_traditional(text: &str) -> bool {
+    is_chinese(text) && zhconv::is_hans_confidence(text) < 0.5
+}
+
+#[cfg(feature = "traditional")]
+pub fn to_simplified(text: &str) -> String {
+    zhconv::zhconv(text, zhconv::Variant::ZhHans)
+}
+
+#[cfg(feature = "traditional")]
+pub fn to_traditional(text: &str, variant: ZhVariant) -> String {
+    let variant = match variant {
+        ZhVariant::Hant => zhconv::Variant::ZhHant,
+        ZhVariant::Tw => zhconv::Variant::ZhTW,
+        ZhVariant::Hk => zhconv::Variant::ZhHK,
+    };
+    zhconv::zhconv(text, variant)
+}
+
+/// How tones are written in pinyin
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PinyinStyle {
+    /// Tone marks, e.g. nǐ hǎo
+    Marks,
+    /// Tone numbers, e.g. ni3 hao3
+    Numbers,
+}
+
+/// Pinyin of every Chinese character in `text`, separated by spaces
+pub fn pinyin(text: &str, style: PinyinStyle) -> String {
+    text.to_pinyin()
+        .flatten()
+        .map(|x| match style {
+            PinyinStyle::Marks => x.with_tone(),
+            PinyinStyle::Numbers => x.with_tone_num_end(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Romaji of hiragana from ぁ to ゖ, katakana are mapped onto them
+const ROMAJI: [&str; 86] = [
+    "a", "a", "i", "i", "u", "u", "e", "e", "o", "o", // ぁ..お
+    "ka", "ga", "ki", "gi", "ku", "gu", "ke", "ge", "ko", "go", // か..ご
+    "sa", "za", "shi", "ji", "su", "zu", "se", "ze", "so", "zo", // さ..ぞ
+    "ta", "da", "chi", "ji", "", "tsu", "zu", "te", "de", "to", "do", // た..ど
+    "na", "ni", "nu", "ne", "no", // な..の
+    "ha", "ba", "pa", "hi", "bi", "pi", "fu", "bu", "pu", "he", "be", "pe", "ho", "bo",
+    "po", // は..ぽ
+    "ma", "mi", "mu", "me", "mo", // ま..も
+    "ya", "ya", "yu", "yu", "yo", "yo", // ゃ..よ
+    "ra", "ri", "ru", "re", "ro", // ら..ろ
+    "wa", "wa", "i", "e", "o", "n", "vu", "ka", "ke", // ゎ..ゖ
+];
+
+const HANGUL_INITIALS: [&str; 19] = [
+    "g", "kk", "n", "d", "tt", "r", "m", "b", "pp", "s", "ss", "", "j", "jj", "ch", "k", "t", "p",
+    "h",
+];
+const HANGUL_VOWELS: [&str; 21] = [
+    "a", "ae", "ya", "yae", "eo", "e", "yeo", "ye", "o", "wa", "wae", "oe", "yo", "u", "wo", "we",
+    "wi", "yu", "eu", "ui", "i",
+];
+const HANGUL_FINALS: [&str; 28] = [
+    "", "k", "k", "k", "n", "n", "n", "t", "l", "k", "m", "l", "l", "l", "p", "l", "m", "p", "p",
+    "t", "t", "ng", "t", "t", "k", "t", "p", "t",
+];
+
+/// Russian letters from а to я
+const CYRILLIC: [&str; 32] = [
+    "a", "b", "v", "g", "d", "e", "zh", "z", "i", "y", "k", "l", "m", "n", "o", "p", "r", "s", "t",
+    "u", "f", "kh", "ts", "ch", "sh", "shch", "", "y", "", "e", "yu", "ya",
+];
+
+/// Latin transcription of `text`: Hepburn romaji for kana, Revised
+/// Romanization for hangul, pinyin for Chinese characters and a plain
+/// transliteration for Russian. Kanji in Japanese text are kept since their
+/// reading is unknown. Returns `None` if there's nothing to transcribe.
+pub fn romanize(text: &str, style: PinyinStyle) -> Option<String> {
+    if !text.chars().any(|ch| {
+        matches!(
+            Script::of(ch),
+            Some(Script::Han | Script::Kana | Script::Hangul | Script::Cyrillic)
+        )
+    }) {
+        return None;
+    }
+
+    let japanese = is_japanese(text);
+    let mut out = String::new();
+    // a small っ doubles the next consonant
+    let mut geminate = false;
+    // pinyin syllables are separated from the following letters
+    let mut spaced = false;
+
+    for ch in text.chars() {
+        let code = ch as u32;
+        let kana = match code {
+            0x3041..=0x3096 => Some(code),
+            0x30a1..=0x30f6 => Some(code - 0x60),
+            _ => None,
+        };
+
+        if let Some(kana) = kana {
+            let romaji = ROMAJI[(kana - 0x3041) as usize];
+            match kana {
+                // っ
+                0x3063 => geminate = true,
+                // ゃ, ゅ, ょ after a syllable ending with i, like きゃ
+                0x3083 | 0x3085 | 0x3087 if out.len() > 1 && out.ends_with('i') => {
+                    out.pop();
+                    if !(out.ends_with("sh") || out.ends_with("ch") || out.ends_with('j')) {
+                        out.push('y');
+                    }
+                    out.push_str(&romaji[1..]);
+                }
+                // small vowels after a syllable, like ファ
+                0x3041 | 0x3043 | 0x3045 | 0x3047 | 0x3049
+                    if out.ends_with(|x: char| "aiueo".contains(x)) && out.len() > 1 =>
+                {
+                    out.pop();
+                    out.push_str(romaji);
+                }
+                _ => {
+                    if geminate {
+                        match romaji.chars().next() {
+                            Some('c') => out.push('t'),
+                            Some(x) if !"aiueon".contains(x) => out.push(x),
+                            _ => {}
+                        }
+                        geminate = false;
+                    }
+                    out.push_str(romaji);
+                }
+            }
+            spaced = false;
+            continue;
+        }
+
+        match Script::of(ch) {
+            // ー lengthens the previous vowel
+            _ if ch == 'ー' => {
+                if let Some(vowel) = out.chars().last().filter(|x| "aiueo".contains(*x)) {
+                    out.push(vowel);
+                }
+            }
+            Some(Script::Hangul) if (0xac00..=0xd7a3).contains(&code) => {
+                let syllable = (code - 0xac00) as usize;
+                out.push_str(HANGUL_INITIALS[syllable / 588]);
+                out.push_str(HANGUL_VOWELS[syllable % 588 / 28]);
+                out.push_str(HANGUL_FINALS[syllable % 28]);
+            }
+            Some(Script::Cyrillic) => {
+                let lower = ch.to_lowercase().next().unwrap_or(ch);
+                let latin = match lower as u32 {
+                    0x430..=0x44f => CYRILLIC[(lower as u32 - 0x430) as usize],
+                    0x451 => "yo",
+                    _ => "",
+                };
+                if ch.is_uppercase() {
+                    let mut chars = latin.chars();
+                    out.extend(chars.next().map(|x| x.to_ascii_uppercase()));
+                    out.push_str(chars.as_str());
+                } else {
+                    out.push_str(latin);
+                }
+            }
+            Some(Script::Han) if !japanese => {
+                if out.ends_with(char::is_alphanumeric) {
+                    out.push(' ');
+                }
+                out.push_str(&pinyin(&ch.to_string(), style));
+                spaced = true;
+                continue;
+            }
+            _ => {
+                if spaced && ch.is_alphanumeric() {
+                    out.push(' ');
+                }
+                out.push(ch);
+            }
+        }
+        spaced = false;
+    }
+
+    Some(out)
+}
+
 pub fn is_chinese(s: &str) -> bool {
     for ch in s.chars() {
         if is_chinese_char(ch) {
@@ -8,6 +461,18 @@ pub fn is_chinese(s: &str) -> bool {
     false
 }
 
+/// Whether `s` has any kana
+pub fn is_japanese(s: &str) -> bool {
+    s.chars().any(|ch| {
+        matches!(ch as u32,
+            0x3040..=0x309f // Hiragana
+            | 0x30a0..=0x30ff // Katakana
+            | 0x31f0..=0x31ff // Katakana Phonetic Extensions
+            | 0xff66..=0xff9f // Halfwidth Katakana
+        )
+    })
+}
+
 #[inline]
 fn is_chinese_char(ch: char) -> bool {
     match ch as u32 {
@@ -46,3 +511,116 @@ fn is_chinese_char(ch: char) -> bool {
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Lang::Ja, Lang::detect("ありがとう"));
+        assert_eq!(Lang::Ja, Lang::detect("勉強する"));
+        assert_eq!(Lang::Zh, Lang::detect("勉强"));
+        assert_eq!(Lang::En, Lang::detect("hello"));
+    }
+
+    #[test]
+    fn test_detect_lang() {
+        assert_eq!(Lang::Ko, detect_lang("안녕하세요").lang);
+        assert_eq!(Lang::Ru, detect_lang("привет").lang);
+        assert_eq!(Lang::Fr, detect_lang("garçon").lang);
+        assert_eq!(Lang::De, detect_lang("Straße").lang);
+        assert_eq!(Lang::Es, detect_lang("mañana").lang);
+
+        let zh = detect_lang("你好 ok");
+        assert_eq!(Lang::Zh, zh.lang);
+        assert_eq!(0.5, zh.confidence);
+        assert_eq!(1.0, detect_lang("你好").confidence);
+        assert_eq!(0.0, detect_lang("123").confidence);
+    }
+
+    #[test]
+    fn test_split_prefix() {
+        assert_eq!(
+            (Some(Lang::Ja), "こんにちは"),
+            split_prefix("ja:こんにちは")
+        );
+        assert_eq!((Some(Lang::Fr), "bonjour"), split_prefix("FR: bonjour"));
+        assert_eq!((None, "re:invent"), split_prefix("re:invent"));
+        assert_eq!((None, "ja:"), split_prefix("ja:"));
+
+        let dir = Direction::pair("你好", Lang::Fr);
+        assert_eq!((Lang::Zh, Lang::Fr), (dir.from, dir.to));
+    }
+
+    #[test]
+    fn test_is_sentence() {
+        assert!(!is_sentence("hello"));
+        assert!(!is_sentence("give up"));
+        assert!(!is_sentence("state-of-the-art"));
+        assert!(!is_sentence("Hello!"));
+        assert!(is_sentence("How are you doing today?"));
+        assert!(is_sentence("Yes, please"));
+        assert!(is_sentence("Thanks. See you"));
+        for word in ["e.g.", "a.m.", "U.S.", "U.S. dollar", "10:30", "std::io"] {
+            assert!(!is_sentence(word), "{word}");
+        }
+        assert!(!is_sentence("一心一意"));
+        assert!(is_sentence("今天天气怎么样，适合出门吗"));
+    }
+
+    #[test]
+    fn test_resolve_direction() {
+        let resolve = |word, from, to| {
+            Direction::resolve(word, from, to)
+                .ok()
+                .map(|x| (x.from, x.to))
+        };
+
+        assert_eq!(Some((Lang::En, Lang::Zh)), resolve("hello", None, None));
+        assert_eq!(Some((Lang::Zh, Lang::En)), resolve("你好", None, None));
+        assert_eq!(
+            Some((Lang::Zh, Lang::Fr)),
+            resolve("你好", None, Some(Lang::Fr))
+        );
+        assert_eq!(
+            Some((Lang::Fr, Lang::Zh)),
+            resolve("bonjour", Some(Lang::Fr), None)
+        );
+        assert_eq!(None, resolve("hello", Some(Lang::En), Some(Lang::Ja)));
+        assert_eq!(Lang::En, Direction::detect("你好").dict());
+    }
+
+    #[cfg(feature = "traditional")]
+    #[test]
+    fn test_traditional() {
+        assert!(is_traditional("學習"));
+        assert!(!is_traditional("学习"));
+        assert!(!is_traditional("你好"));
+        assert!(!is_traditional("hello"));
+        assert_eq!("学习", to_simplified("學習"));
+        assert_eq!("學習 study", to_traditional("学习 study", ZhVariant::Hant));
+    }
+
+    #[test]
+    fn test_romanize() {
+        let romanize = |x| romanize(x, PinyinStyle::Marks);
+        assert_eq!(Some("konnichiha".into()), romanize("こんにちは"));
+        assert_eq!(Some("kyouto".into()), romanize("きょうと"));
+        assert_eq!(Some("kitte".into()), romanize("きって"));
+        assert_eq!(Some("matcha".into()), romanize("まっちゃ"));
+        assert_eq!(Some("koohii".into()), romanize("コーヒー"));
+        assert_eq!(Some("faito".into()), romanize("ファイト"));
+        assert_eq!(Some("日本go".into()), romanize("日本ご"));
+        assert_eq!(Some("annyeonghaseyo".into()), romanize("안녕하세요"));
+        assert_eq!(Some("Moskva".into()), romanize("Москва"));
+        assert_eq!(Some("int. wèi；hā luó".into()), romanize("int. 喂；哈罗"));
+        assert_eq!(None, romanize("hello"));
+    }
+
+    #[test]
+    fn test_pinyin() {
+        assert_eq!("nǐ hǎo shì jiè", pinyin("你好，世界", PinyinStyle::Marks));
+        assert_eq!("ping2 guo3", pinyin("苹果 apple", PinyinStyle::Numbers));
+    }
+}
@@ -0,0 +1,59 @@
+//! Lookups on YouDao and explanations of the results, the core of `ydcv`
+//! for other programs such as editor plugins, bots or GUIs
+//!
+//! Look up a word and print its explanation:
+//!
+//! ```no_run
+//! use ydcv_saki::formatters::PlainFormatter;
+//! use ydcv_saki::http::{self, HttpOptions};
+//! use ydcv_saki::ydclient::{BlockingYdClient, YdClient};
+//!
+//! let client = BlockingYdClient::new(http::build_async_client(&HttpOptions::default())?)?;
+//! let resp = client.lookup_word("hello")?;
+//! println!("{}", resp.explain(&PlainFormatter::new(false)));
+//! # anyhow::Ok(())
+//! ```
+//!
+//! Async programs such as GUIs and servers can look up on a `reqwest::Client`
+//! directly, without a thread to block:
+//!
+//! ```no_run
+//! use ydcv_saki::http::{self, HttpOptions};
+//! use ydcv_saki::ydclient::AsyncYdClient;
+//!
+//! async fn translation(word: &str) -> anyhow::Result<Vec<String>> {
+//!     let client = http::build_async_client(&HttpOptions::default())?;
+//!     Ok(client.lookup_word_async(word).await?.translation().to_vec())
+//! }
+//! ```
+//!
+//! On wasm32 only the parser and formatters are built, without any
+//! networking: fetch the result page yourself and parse it with
+//! `ydresponse::YdResponse::from_html`.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod cookies;
+#[cfg(all(any(feature = "ffi", feature = "python"), not(target_arch = "wasm32")))]
+mod embed;
+pub mod error;
+#[cfg(all(feature = "ffi", not(target_arch = "wasm32")))]
+pub mod ffi;
+pub mod formatters;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod http;
+pub mod lang;
+pub mod phonetic;
+#[cfg(all(feature = "python", not(target_arch = "wasm32")))]
+mod python;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ydclient;
+pub mod ydresponse;
+
+/// Client for the lookups of tests, built like the one of `ydcv`
+#[cfg(test)]
+static YD_CLIENT: std::sync::LazyLock<ydclient::BlockingYdClient> =
+    std::sync::LazyLock::new(|| {
+        http::build_async_client(&http::HttpOptions::default())
+            .and_then(|x| Ok(ydclient::BlockingYdClient::new(x)?))
+            .expect("Failed to create http client")
+    });
@@ -0,0 +1,104 @@
+//! Async lookups of the long-running services, the D-Bus and gRPC ones,
+//! cached in the ydcv database
+
+use std::sync::Mutex;
+
+use anyhow::Result;
+use log::warn;
+
+use crate::db::Db;
+use crate::formatters::{HtmlFormatter, PlainFormatter};
+use crate::lang::{self, Direction};
+use crate::ydclient::{self, AsyncYdClient};
+use crate::ydresponse::{ExplainOptions, YdResponse};
+
+/// Lookups of a service, shared by all its interfaces and cached in the
+/// ydcv database
+pub struct Lookups {
+    client: reqwest::Client,
+    db: Option<Mutex<Db>>,
+    explain: ExplainOptions,
+}
+
+impl Lookups {
+    pub fn new(client: reqwest::Client, db: Option<Db>, explain: ExplainOptions) -> Self {
+        Lookups {
+            client,
+            db: db.map(Mutex::new),
+            explain,
+        }
+    }
+
+    /// Look up `word`, translating it if it's a sentence, from the cache if
+    /// it was looked up before, and record it in the history
+    pub async fn lookup(&self, word: &str) -> Result<YdResponse> {
+        let resp = self.fetch(word, true).await?;
+        self.record(word);
+        Ok(resp)
+    }
+
+    /// Like `lookup` without recording or caching, for what's looked up
+    /// while typed, so every prefix doesn't end up in the database
+    pub async fn lookup_unrecorded(&self, word: &str) -> Result<YdResponse> {
+        self.fetch(word, false).await
+    }
+
+    /// Look up `word` from the cache or the network, caching what's fetched
+    /// if `cache`
+    async fn fetch(&self, word: &str, cache: bool) -> Result<YdResponse> {
+        if lang::is_sentence(word) {
+            return Ok(self.client.translate(word, Direction::detect(word)).await?);
+        }
+
+        let cached = self.with_db(|db| db.cached(word)).flatten();
+        if let Some(resp) = cached {
+            ydclient::report_cache_hit(word);
+            return Ok(resp);
+        }
+
+        let resp = self.client.lookup_word_async(word).await?;
+        if cache {
+            self.with_db(|db| db.cache_response(&resp));
+        }
+        Ok(resp)
+    }
+
+    /// Record that `word` has been looked up
+    pub fn record(&self, word: &str) {
+        self.with_db(|db| db.record_lookup(word));
+    }
+
+    /// Plain and HTML explanations of `resp`
+    pub fn explain_response(&self, resp: &YdResponse) -> (String, String) {
+        (
+            resp.explain_with(&PlainFormatter::new(false), &self.explain),
+            resp.explain_with(&HtmlFormatter::new(false), &self.explain),
+        )
+    }
+
+    /// Plain and HTML explanations of `word`
+    pub async fn explain(&self, word: &str) -> Result<(String, String)> {
+        let resp = self.lookup(word).await?;
+        Ok(self.explain_response(&resp))
+    }
+
+    /// Add `resp` to the wordbook, returning whether it wasn't there yet
+    #[cfg(feature = "grpc")]
+    pub fn wordbook_add(&self, resp: &YdResponse) -> Result<bool> {
+        let db = self
+            .db
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Wordbook is unavailable without the ydcv database"))?
+            .lock()
+            .unwrap();
+        db.wordbook_add(resp)
+    }
+
+    /// Run `f` on the database if there's one, only warning on failure
+    fn with_db<T>(&self, f: impl FnOnce(&Db) -> Result<T>) -> Option<T> {
+        let db = self.db.as_ref()?.lock().unwrap();
+        f(&db)
+            .inspect_err(|e| warn!("Failed to use the ydcv database: {e:#}"))
+            .ok()
+    }
+}
@@ -0,0 +1,226 @@
+//! A minimal language server explaining the word under the cursor on hover
+//! in prose files, such as markdown, plain text and org
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use anyhow::{Context, Result};
+use log::warn;
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::db::Db;
+use crate::formatters::PlainFormatter;
+use crate::lookup_maybe_cached;
+use crate::rpc::{METHOD_NOT_FOUND, Request, Response, RpcError};
+use crate::ydclient::YdClient;
+use crate::ydresponse::ExplainOptions;
+
+/// Language ids of the documents to explain words of
+const PROSE_LANGUAGES: [&str; 4] = ["markdown", "plaintext", "text", "org"];
+
+/// `TextDocumentSyncKind.Full`: every change sends the whole document
+const SYNC_FULL: i32 = 1;
+
+/// What hovers are answered with, and the documents open in the editor by URI
+struct Server<'a, C> {
+    client: &'a C,
+    db: Option<&'a Db>,
+    explain: &'a ExplainOptions,
+    documents: HashMap<String, String>,
+}
+
+impl<C: YdClient> Server<'_, C> {
+    /// Result of `method`, dropped for notifications
+    fn handle(&mut self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        let uri = || params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match method {
+            "initialize" => {
+                return Ok(json!({
+                    "capabilities": { "hoverProvider": true, "textDocumentSync": SYNC_FULL },
+                    "serverInfo": { "name": "ydcv", "version": env!("CARGO_PKG_VERSION") },
+                }));
+            }
+            "textDocument/hover" => return Ok(self.hover(uri(), &params["position"])),
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                let language = document["languageId"].as_str().unwrap_or_default();
+                if PROSE_LANGUAGES.contains(&language) {
+                    let text = document["text"].as_str().unwrap_or_default();
+                    self.documents.insert(uri().to_string(), text.to_string());
+                }
+            }
+            "textDocument/didChange" => {
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|x| x.last())
+                    .and_then(|x| x["text"].as_str());
+                if let (Some(document), Some(text)) = (self.documents.get_mut(uri()), text) {
+                    *document = text.to_string();
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri());
+            }
+            "initialized" | "shutdown" => {}
+            _ => {
+                return Err(RpcError {
+                    code: METHOD_NOT_FOUND,
+                    message: format!("No method {method}"),
+                });
+            }
+        }
+
+        Ok(Value::Null)
+    }
+
+    /// Explanation of the word at `position` of the document `uri`, or null
+    fn hover(&self, uri: &str, position: &Value) -> Value {
+        let word = self.documents.get(uri).and_then(|text| {
+            let line = position["line"].as_u64()? as usize;
+            let character = position["character"].as_u64()? as usize;
+            word_at(text, line, character)
+        });
+        let Some(word) = word else {
+            return Value::Null;
+        };
+
+        match lookup_maybe_cached(self.client, self.db, word) {
+            Ok(resp) if resp.has_result() => json!({
+                "contents": {
+                    "kind": "plaintext",
+                    "value": resp.explain_with(&PlainFormatter::new(false), self.explain),
+                }
+            }),
+            Ok(_) => Value::Null,
+            Err(e) => {
+                warn!("Failed to look up {word}: {e:#}");
+                Value::Null
+            }
+        }
+    }
+}
+
+/// The word at `character`, counted in UTF-16 code units like LSP does, of
+/// the line `line` of `text`
+fn word_at(text: &str, line: usize, character: usize) -> Option<&str> {
+    let is_word = |c: char| c.is_alphabetic() || c == '\'' || c == '-';
+    let line = text.lines().nth(line)?;
+
+    let mut units = 0;
+    let (offset, c) = line.char_indices().find(|(_, c)| {
+        units += c.len_utf16();
+        units > character
+    })?;
+    if !is_word(c) {
+        return None;
+    }
+
+    let start = line[..offset]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word(*c))
+        .last()
+        .map_or(offset, |(i, _)| i);
+    let end = line[offset..]
+        .find(|c| !is_word(c))
+        .map_or(line.len(), |i| offset + i);
+    let word = line[start..end].trim_matches(['\'', '-']);
+
+    (!word.is_empty()).then_some(word)
+}
+
+/// Content of the next message of `input`, None at its end
+fn read_message(input: &mut impl BufRead) -> Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            length = Some(value.trim().parse::<usize>()?);
+        }
+    }
+
+    let mut content = vec![0; length.context("Missing Content-Length header")?];
+    input.read_exact(&mut content)?;
+    Ok(Some(String::from_utf8(content)?))
+}
+
+fn write_message(output: &mut impl Write, message: &impl Serialize) -> Result<()> {
+    let content = serde_json::to_string(message)?;
+    write!(output, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    output.flush()?;
+
+    Ok(())
+}
+
+/// Serve the editor talking on `input` and `output` until it exits
+pub fn serve(
+    client: &impl YdClient,
+    db: Option<&Db>,
+    explain: &ExplainOptions,
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> Result<()> {
+    let mut server = Server {
+        client,
+        db,
+        explain,
+        documents: HashMap::new(),
+    };
+    while let Some(content) = read_message(&mut input)? {
+        let request = match serde_json::from_str::<Request>(&content) {
+            Ok(request) => request,
+            Err(e) => {
+                warn!("Failed to parse a message of the editor: {e}");
+                continue;
+            }
+        };
+        if request.method == "exit" {
+            break;
+        }
+
+        let result = server.handle(&request.method, &request.params);
+        if let Some(id) = request.id {
+            write_message(&mut output, &Response::new(id, result))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_at() {
+        let text = "# Title\nThe quick brown fox's tail\n“naïve” 你好 x";
+        assert_eq!(Some("quick"), word_at(text, 1, 4));
+        assert_eq!(Some("quick"), word_at(text, 1, 8));
+        assert_eq!(None, word_at(text, 1, 9));
+        assert_eq!(Some("fox's"), word_at(text, 1, 17));
+        assert_eq!(Some("naïve"), word_at(text, 2, 1));
+        assert_eq!(Some("你好"), word_at(text, 2, 9));
+        assert_eq!(None, word_at(text, 2, 100));
+        assert_eq!(None, word_at(text, 5, 0));
+    }
+
+    #[test]
+    fn test_read_message() {
+        let mut input =
+            "Content-Length: 2\r\nContent-Type: x\r\n\r\n{}Content-Length: 4\r\n\r\nnull"
+                .as_bytes();
+        assert_eq!(Some("{}".to_string()), read_message(&mut input).unwrap());
+        assert_eq!(Some("null".to_string()), read_message(&mut input).unwrap());
+        assert_eq!(None, read_message(&mut input).unwrap());
+    }
+}
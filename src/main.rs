@@ -1,116 +1,1871 @@
 //! main module of ydcv-rs
 
-use std::fs::{self, create_dir_all};
-use std::io::{IsTerminal, Write, stdout};
-use std::sync::LazyLock;
+use std::borrow::Cow;
+use std::fs::{self, File, create_dir_all};
+use std::hash::Hash;
+use std::io::{IsTerminal, Write, stdin, stdout};
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::sync::{LazyLock, OnceLock};
+use std::time::Duration;
 
 use anyhow::{Context, Result};
-use clap::{ColorChoice, CommandFactory, Parser};
+use chrono::{Local, TimeDelta};
+use clap::{ColorChoice, CommandFactory, Parser, Subcommand, ValueEnum};
 use clap_complete::CompleteEnv;
-use dirs::cache_dir;
 use log::warn;
-use reqwest::blocking::{Client, ClientBuilder};
+use reqwest::blocking::Client;
 use rustyline::Editor;
 use rustyline::config::Builder;
 use rustyline::history::FileHistory;
 
-mod formatters;
-mod lang;
-mod ydclient;
-mod ydresponse;
+mod account;
+mod activation;
+mod alfred;
+mod anki;
+mod audio;
+mod bar;
+mod bot;
+mod config;
+#[cfg(unix)]
+mod daemon;
+mod db;
+#[cfg(all(unix, feature = "dbus"))]
+mod dbus;
+mod dict;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod history;
+mod keys;
+#[cfg(any(all(unix, feature = "dbus"), feature = "grpc"))]
+mod lookups;
+mod lsp;
+mod metrics;
+mod paths;
+mod plan;
+mod ratelimit;
+mod remind;
+mod review;
+mod rpc;
+mod server;
+mod stats;
+#[cfg(test)]
+mod testing;
+mod tmux;
+#[cfg(feature = "tts")]
+mod tts;
+mod wordbook;
 
+use ydcv_saki::{error, formatters, http, lang, phonetic, ydclient, ydresponse};
+
+use crate::activation::IdleExit;
+use crate::config::Config;
+use crate::db::Db;
+use crate::error::YdcvError;
 #[cfg(windows)]
 #[cfg(feature = "notify")]
 use crate::formatters::WinFormatter;
-use crate::formatters::{AnsiFormatter, Formatter, HtmlFormatter, PlainFormatter};
-use crate::ydclient::YdClient;
+use crate::formatters::{
+    AnsiFormatter, Formatter, GoldenDictFormatter, HtmlFormatter, PlainFormatter,
+};
+use crate::history::ExportFormat;
+use crate::http::HttpOptions;
+#[cfg(feature = "traditional")]
+use crate::lang::ZhVariant;
+use crate::lang::{Direction, Lang, PinyinStyle};
+use crate::phonetic::PhoneticStyle;
+use crate::ratelimit::RateLimiter;
+use crate::review::QuizMode;
+use crate::stats::Stats;
+use crate::ydclient::{BlockingYdClient, Query, YdClient};
+use crate::ydresponse::{Accent, ExplainOptions, YdResponse};
+
+/// Network settings of `CLIENT` and `YD_CLIENT`, set once the command line is parsed
+static HTTP_OPTIONS: OnceLock<HttpOptions> = OnceLock::new();
 
 static CLIENT: LazyLock<Client> = LazyLock::new(|| {
-    #[cfg(feature = "rustls")]
-    rustls::crypto::ring::default_provider()
-        .install_default()
-        .expect("Failed to install rustls crypto provider");
-
-    // reqwest will use HTTPS_PROXY env automatically
-    ClientBuilder::new()
-        .build()
+    http::build_client(HTTP_OPTIONS.get_or_init(HttpOptions::default))
+        .expect("Failed to create http client")
+});
+
+/// Client for lookups, blocking on the async core
+static YD_CLIENT: LazyLock<BlockingYdClient> = LazyLock::new(|| {
+    http::build_async_client(HTTP_OPTIONS.get_or_init(HttpOptions::default))
+        .and_then(|x| Ok(BlockingYdClient::new(x)?))
         .expect("Failed to create http client")
 });
 
-fn lookup_explain(client: &Client, word: &str, fmt: &mut dyn Formatter, raw: bool) -> Result<()> {
-    if raw {
-        println!("{}", serde_json::to_string(&client.lookup_word(word)?)?);
+/// Profile of the config chosen by `--profile`, set before `CONFIG` is used
+static PROFILE: OnceLock<Option<String>> = OnceLock::new();
+
+static CONFIG: LazyLock<Config> = LazyLock::new(|| {
+    Config::load(PROFILE.get().and_then(|x| x.as_deref()))
+        .inspect_err(|e| warn!("Failed to load config: {e:#}"))
+        .unwrap_or_default()
+});
+
+/// Output for other programs instead of an explanation, see `--format`
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Script Filter JSON of Alfred and Raycast, with an item per sense
+    Alfred,
+    /// JSON of a Waybar custom module, with the full explanation as tooltip
+    Waybar,
+    /// A single line for status bars such as Polybar
+    Line,
+    /// The word, its phonetic and first translation on a line, see `--short`
+    Short,
+}
+
+impl OutputFormat {
+    /// `resp` in this format
+    fn result(self, resp: &YdResponse, options: &ExplainOptions) -> String {
+        match self {
+            OutputFormat::Alfred => alfred::script_filter(resp),
+            OutputFormat::Waybar => bar::waybar(resp, options),
+            OutputFormat::Line => bar::line(resp),
+            OutputFormat::Short => bar::short(resp),
+        }
+    }
+
+    /// Why looking up `word` failed, in this format
+    fn error(self, word: &str, err: &anyhow::Error) -> String {
+        match self {
+            OutputFormat::Alfred => alfred::error(word, err),
+            OutputFormat::Waybar => bar::waybar_error(word, err),
+            OutputFormat::Line | OutputFormat::Short => format!("{word}: {err:#}"),
+        }
+    }
+
+    /// Whether a status bar shows it, e.g. of the last looked-up word when
+    /// no word is given
+    fn is_bar(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Waybar | OutputFormat::Line | OutputFormat::Short
+        )
+    }
+}
+
+/// What to do with every looked-up word
+#[derive(Clone)]
+struct LookupOptions {
+    raw: bool,
+    format: Option<OutputFormat>,
+    add: bool,
+    speak: bool,
+    #[cfg(feature = "tts")]
+    speak_source: bool,
+    #[cfg(feature = "tts")]
+    speak_target: bool,
+    accent: Accent,
+    /// Record looked-up words in the history
+    history: bool,
+    /// Look up again rather than taking results from the cache or the daemon
+    refresh: bool,
+    /// Whether to translate the words as sentences, detected if not given
+    sentence: Option<bool>,
+    /// Languages to look up from and to, detected from each word if not given
+    from: Option<Lang>,
+    to: Option<Lang>,
+    explain: ExplainOptions,
+    /// Only show words in the word list of this exam
+    filter_tag: Option<String>,
+    /// Look up traditional Chinese words in simplified characters
+    #[cfg(feature = "traditional")]
+    simplify: bool,
+    /// Show results in traditional Chinese
+    #[cfg(feature = "traditional")]
+    traditional: Option<ZhVariant>,
+}
+
+/// The text to look up for `word`, in simplified characters if asked to
+fn lookup_text<'a>(word: &'a str, opts: &LookupOptions) -> Cow<'a, str> {
+    #[cfg(feature = "traditional")]
+    if opts.simplify && lang::is_traditional(word) {
+        return Cow::Owned(lang::to_simplified(word));
+    }
+    #[cfg(not(feature = "traditional"))]
+    let _ = opts;
+
+    Cow::Borrowed(word)
+}
+
+/// How to look up `word`, in the languages of `opts` or detected ones
+fn lookup_query<'a>(word: &'a str, opts: &LookupOptions) -> Result<Query<'a>> {
+    Ok(Query {
+        text: word,
+        dir: Direction::resolve(word, opts.from, opts.to)?,
+        sentence: opts.sentence.unwrap_or_else(|| lang::is_sentence(word)),
+    })
+}
+
+fn lookup_explain(
+    client: &impl YdClient,
+    db: Option<&Db>,
+    word: &str,
+    fmt: &mut dyn Formatter,
+    opts: &LookupOptions,
+) -> Result<Option<YdResponse>> {
+    let word = lookup_text(word, opts);
+    let result = lookup_query(&word, opts).and_then(|query| {
+        if let Some(resp) = shared_lookup(db, &query, opts) {
+            return Ok(resp);
+        }
+        let resp = if query.sentence {
+            client.translate(query.text, query.dir)?
+        } else {
+            client.lookup_word_in(query.text, query.dir)?
+        };
+        cache_fetched(db, &resp);
+        Ok(resp)
+    });
+
+    explain_result(db, &word, result, fmt, opts)
+}
+
+/// The response of `query` looked up before by any entry point, from the
+/// cache or the running daemon, if it's a word in the detected direction as
+/// they look up, and unless `opts` asks to look up again
+fn shared_lookup(db: Option<&Db>, query: &Query, opts: &LookupOptions) -> Option<YdResponse> {
+    if opts.refresh || query.sentence || query.dir != Direction::detect(query.text) {
+        return None;
+    }
+
+    let cached = db.and_then(|db| {
+        db.cached(query.text)
+            .inspect_err(|e| warn!("Failed to read the cache: {e:#}"))
+            .ok()
+            .flatten()
+    });
+    if let Some(resp) = cached {
+        ydclient::report_cache_hit(query.text);
+        return Some(resp);
+    }
+
+    // looked up by the daemon, which has its connections warm, and recorded
+    // here as any other lookup
+    #[cfg(unix)]
+    if let Ok(socket) = daemon::default_socket()
+        && socket.exists()
+    {
+        return daemon::lookup(&socket, query, false)
+            .inspect_err(|e| log::debug!("Failed to look up with the daemon: {e:#}"))
+            .ok();
+    }
+
+    None
+}
+
+/// Look up all `words` a few at once, explaining each as soon as it's looked
+/// up, in order unless `ordered` is false
+fn lookup_explain_all(
+    client: &BlockingYdClient,
+    db: Option<&Db>,
+    words: &[String],
+    fmt: &mut dyn Formatter,
+    opts: &LookupOptions,
+    ordered: bool,
+) -> Result<()> {
+    let words = words
+        .iter()
+        .map(|x| lookup_text(x.trim(), opts))
+        .collect::<Vec<_>>();
+    let mut queries = Vec::new();
+    let mut indices = Vec::new();
+    // results without looking up here: failed queries, and words shared by
+    // the cache or the daemon
+    let mut known = Vec::new();
+    for (i, query) in words.iter().map(|x| lookup_query(x, opts)).enumerate() {
+        match query {
+            Ok(query) => match shared_lookup(db, &query, opts) {
+                Some(resp) => known.push((i, Ok(resp))),
+                None => {
+                    queries.push(query);
+                    indices.push(i);
+                }
+            },
+            Err(err) => known.push((i, Err(err))),
+        }
+    }
+    let mut known = known.into_iter().peekable();
+
+    client.lookup_each(&queries, ordered, |i, result| {
+        let i = indices[i];
+        // Known results go before the following results, or first of all
+        // when the results come in any order
+        let end = if ordered { i } else { words.len() };
+        while let Some((j, result)) = known.next_if(|(j, _)| *j < end) {
+            explain_result(db, &words[j], result, fmt, opts)?;
+        }
+        if let Ok(resp) = &result {
+            cache_fetched(db, resp);
+        }
+        explain_result(db, &words[i], result.map_err(Into::into), fmt, opts).map(|_| ())
+    })?;
+    for (j, result) in known {
+        explain_result(db, &words[j], result, fmt, opts)?;
+    }
+
+    Ok(())
+}
+
+/// Cache `resp` looked up on the network for the following lookups of any
+/// entry point, only warning on failure
+fn cache_fetched(db: Option<&Db>, resp: &YdResponse) {
+    if let Some(db) = db {
+        db.cache_response(resp)
+            .inspect_err(|e| warn!("Failed to cache {}: {e}", resp.query()))
+            .ok();
+    }
+}
+
+/// Record, print and act on the result of looking up `word`
+fn explain_result(
+    db: Option<&Db>,
+    word: &str,
+    result: Result<YdResponse>,
+    fmt: &mut dyn Formatter,
+    opts: &LookupOptions,
+) -> Result<Option<YdResponse>> {
+    if let (Some(db), Ok(_)) = (db, &result)
+        && opts.history
+    {
+        db.record_lookup(word)
+            .inspect_err(|e| warn!("Failed to record lookup of {word}: {e}"))
+            .ok();
+    }
+
+    if let (Some(tag), Ok(result)) = (&opts.filter_tag, &result)
+        && !result.exams().iter().any(|x| x.eq_ignore_ascii_case(tag))
+    {
+        return Ok(None);
+    }
+
+    let result = if opts.raw {
+        let result = result?;
+        println!("{}", serde_json::to_string(&result)?);
+        result
+    } else if let Some(format) = opts.format {
+        match result {
+            Ok(result) => {
+                println!("{}", format.result(&result, &opts.explain));
+                result
+            }
+            Err(err) => {
+                println!("{}", format.error(word, &err));
+                return Ok(None);
+            }
+        }
     } else {
-        match client.lookup_word(word) {
-            Ok(ref result) => {
-                let exp = result.explain(fmt);
+        match result {
+            Ok(result) => {
+                let exp = result.explain_with(fmt, &opts.explain);
+                #[cfg(feature = "traditional")]
+                let exp = match opts.traditional {
+                    Some(variant) => lang::to_traditional(&exp, variant),
+                    None => exp,
+                };
                 fmt.print(word, &exp);
+                result
+            }
+            Err(err) => {
+                let hint = match err.downcast_ref::<YdcvError>() {
+                    Some(YdcvError::RateLimited) => {
+                        "\nLooking up too often, try a lower --concurrency"
+                    }
+                    Some(YdcvError::Captcha) => {
+                        "\nOpen https://www.youdao.com in a browser and solve the captcha"
+                    }
+                    Some(YdcvError::ParseFailed { .. }) => {
+                        "\nThe page may have changed, please report it with --dump-html"
+                    }
+                    _ => "",
+                };
+                fmt.print(
+                    word,
+                    &format!("Error looking-up word {word}: {err:?}{hint}"),
+                );
+                return Ok(None);
+            }
+        }
+    };
+
+    if opts.add {
+        add_to_wordbook(db, Some(&result));
+    }
+    if opts.speak && result.has_result() {
+        speak(result.query(), opts.accent);
+    }
+
+    #[cfg(feature = "tts")]
+    if result.is_translation() {
+        let translation = result.translation().join(" ");
+        for (enabled, text) in [
+            (opts.speak_source, result.query()),
+            (opts.speak_target, translation.as_str()),
+        ] {
+            if enabled {
+                tts::speak(text, tts::Voice::for_text(text, opts.accent))
+                    .inspect_err(|e| eprintln!("Failed to speak {text}: {e}"))
+                    .ok();
             }
-            Err(err) => fmt.print(word, &format!("Error looking-up word {word}: {err:?}")),
         }
     }
 
-    Ok(())
-}
+    Ok(Some(result))
+}
+
+/// Get the response of `word` from the cache, or look it up and cache it
+fn cached_or_lookup(client: &impl YdClient, db: &Db, word: &str) -> Result<YdResponse> {
+    if let Some(resp) = db.cached(word)? {
+        ydclient::report_cache_hit(word);
+        return Ok(resp);
+    }
+
+    let resp = client.lookup_word(word)?;
+    db.cache_response(&resp)?;
+
+    Ok(resp)
+}
+
+/// Like `cached_or_lookup`, or just look up without a database
+fn lookup_maybe_cached(client: &impl YdClient, db: Option<&Db>, word: &str) -> Result<YdResponse> {
+    match db {
+        Some(db) => cached_or_lookup(client, db, word),
+        None => Ok(client.lookup_word(word)?),
+    }
+}
+
+/// Like `cached_or_lookup` for all `words`, looking up the uncached ones a
+/// few at once
+fn cached_or_lookup_all(
+    client: &impl YdClient,
+    db: &Db,
+    words: &[&str],
+) -> Result<Vec<YdResponse>> {
+    let cached = words
+        .iter()
+        .map(|x| db.cached(x))
+        .collect::<Result<Vec<_>>>()?;
+    let uncached = words
+        .iter()
+        .zip(&cached)
+        .filter(|(_, resp)| resp.is_none())
+        .map(|(word, _)| *word)
+        .collect::<Vec<_>>();
+    let mut looked_up = client.lookup_words(&uncached).into_iter();
+
+    cached
+        .into_iter()
+        .zip(words)
+        .map(|(resp, word)| match resp {
+            Some(resp) => {
+                ydclient::report_cache_hit(word);
+                Ok(resp)
+            }
+            None => {
+                let resp = looked_up.next().expect("a result for every query")?;
+                db.cache_response(&resp)?;
+                Ok(resp)
+            }
+        })
+        .collect()
+}
+
+/// Add the result of a lookup to the wordbook, only warning on failure
+fn add_to_wordbook(db: Option<&Db>, resp: Option<&YdResponse>) {
+    match (db, resp) {
+        (Some(db), Some(resp)) => {
+            wordbook::add(db, resp)
+                .inspect_err(|e| warn!("Failed to add {} to wordbook: {e}", resp.query()))
+                .ok();
+        }
+        (None, _) => warn!("Wordbook is unavailable without the ydcv database"),
+        (_, None) => {}
+    }
+}
+
+/// Open the result page of `word` on YD, only warning on failure
+fn open_in_browser(word: &str) {
+    let Some(url) = ydclient::result_url(word) else {
+        return;
+    };
+    let opener = if cfg!(windows) {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    // explorer exits with 1 even when it opens the page
+    match std::process::Command::new(opener)
+        .arg(url.as_str())
+        .status()
+    {
+        Ok(status) if !status.success() && !cfg!(windows) => {
+            warn!("Failed to open {url}: {opener} {status}")
+        }
+        Err(e) => warn!("Failed to open {url}: {e}"),
+        Ok(_) => {}
+    }
+}
+
+/// Play the pronunciation of a word, only reporting failures
+fn speak(word: &str, accent: Accent) {
+    audio::speak(&CLIENT, word, accent, CONFIG.audio.player.as_deref())
+        .inspect_err(|e| eprintln!("Failed to play pronunciation of {word}: {e}"))
+        .ok();
+}
+
+/// Run a `:command` typed in interactive mode, `last` is the previous lookup
+///
+/// `lang` is the language paired with Chinese for the following lookups,
+/// changed by `:lang`.
+fn run_repl_command(
+    cmd: &str,
+    db: Option<&Db>,
+    last: Option<&YdResponse>,
+    accent: Accent,
+    lang: &mut Option<Lang>,
+) {
+    let (cmd, arg) = cmd
+        .split_once(' ')
+        .map(|(cmd, arg)| (cmd, arg.trim()))
+        .unwrap_or((cmd, ""));
+
+    match cmd {
+        "fav" if arg.is_empty() => match last {
+            Some(_) => add_to_wordbook(db, last),
+            None => println!("Nothing to add, look up a word first"),
+        },
+        "fav" => match db.map(|db| cached_or_lookup(&*YD_CLIENT, db, arg)) {
+            Some(Ok(resp)) => add_to_wordbook(db, Some(&resp)),
+            Some(Err(e)) => println!("Error looking-up word {arg}: {e:?}"),
+            None => add_to_wordbook(None, None),
+        },
+        "speak" if arg.is_empty() => match last {
+            Some(resp) => speak(resp.query(), accent),
+            None => println!("Nothing to speak, look up a word first"),
+        },
+        "speak" => speak(arg, accent),
+        "lang" if arg.is_empty() || arg == "auto" => {
+            *lang = None;
+            println!("Detecting the language of every word");
+        }
+        "lang" => match Lang::from_code(arg) {
+            Some(code) => {
+                *lang = Some(code);
+                println!("Looking up between zh and {}", code.code());
+            }
+            None => println!("Unknown language {arg}"),
+        },
+        _ => println!("Unknown command :{cmd}"),
+    }
+}
+
+#[derive(Parser)]
+#[clap(
+    version,
+    about,
+    max_term_width = 80,
+    args_conflicts_with_subcommands = true
+)]
+struct YdcvOptions {
+    #[clap(
+        long,
+        global = true,
+        value_name = "NAME",
+        help = "Use the keys of [profiles.NAME] in the config over the others (default: YDCV_PROFILE)"
+    )]
+    profile: Option<String>,
+
+    #[clap(
+        long,
+        global = true,
+        value_name = "URL",
+        help = "Proxy for every request, like http://127.0.0.1:8080 (default: network.proxy in the config, or HTTPS_PROXY)"
+    )]
+    proxy: Option<String>,
+
+    #[clap(
+        long,
+        global = true,
+        value_name = "ADDR",
+        conflicts_with = "proxy",
+        help = "SOCKS5 proxy for every request, like 127.0.0.1:1080 (default: network.socks5 in the config)"
+    )]
+    socks5: Option<String>,
+
+    #[clap(
+        long,
+        global = true,
+        value_name = "SECS",
+        help = "Timeout of every request (default: network.timeout in the config, or 30)"
+    )]
+    request_timeout: Option<u64>,
+
+    #[clap(
+        long,
+        global = true,
+        value_name = "SECS",
+        help = "Keep idle connections this long for the next lookup, 0 for ever (default: network.pool_idle_timeout in the config, or 90)"
+    )]
+    pool_idle_timeout: Option<u64>,
+
+    #[clap(
+        long,
+        global = true,
+        value_name = "N",
+        help = "Times to retry a request that timed out or got a server error (default: network.retries in the config, or 2)"
+    )]
+    retries: Option<u32>,
+
+    #[clap(
+        long,
+        global = true,
+        value_name = "N",
+        help = "Requests to run at once when looking up many words (default: network.concurrency in the config, or 4)"
+    )]
+    concurrency: Option<usize>,
+
+    #[clap(
+        long,
+        global = true,
+        value_name = "URL",
+        help = "Base URL of the dictionary and the sentence translator, like a mirror (default: network.endpoint in the config, or https://www.youdao.com)"
+    )]
+    endpoint: Option<String>,
+
+    #[clap(
+        long,
+        global = true,
+        value_name = "FILE",
+        help = "Trust the root certificates in FILE (PEM or DER) in addition to the system ones (default: network.ca_cert in the config)"
+    )]
+    ca_cert: Option<PathBuf>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Accept invalid TLS certificates, only for debugging or broken proxies"
+    )]
+    insecure: bool,
+
+    #[clap(
+        long,
+        global = true,
+        value_name = "NAME: VALUE",
+        value_parser = http::parse_header,
+        help = "Extra header of every request, can be repeated (added to network.headers in the config)"
+    )]
+    header: Vec<(String, String)>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Ask for uncompressed responses, for debugging (default: network.compression in the config)"
+    )]
+    no_compression: bool,
+
+    #[cfg(feature = "clipboard")]
+    #[clap(short = 'x', long, help = "Show explanation of current selection")]
+    selection: bool,
+
+    #[cfg(windows)]
+    #[cfg(feature = "clipboard")]
+    #[clap(
+        short,
+        long,
+        help = "Time interval between selection in msec (default: 1000 on windows and 0 on others)",
+        default_value = "1000"
+    )]
+    interval: u64,
+
+    #[cfg(unix)]
+    #[cfg(feature = "clipboard")]
+    #[clap(
+        short,
+        long,
+        help = "Time interval between selection in msec (default: 1000 on windows and 0 on others)",
+        default_value = "0"
+    )]
+    interval: u64,
+
+    #[clap(short = 'H', long, help = "HTML-style output")]
+    html: bool,
+
+    #[clap(
+        long,
+        overrides_with = "html",
+        help = "No HTML-style output even if display.html is set in the config"
+    )]
+    no_html: bool,
+
+    #[cfg(feature = "notify")]
+    #[clap(short, long, help = "Send desktop notifications (implies -H on X11)")]
+    notify: bool,
+
+    #[cfg(feature = "notify")]
+    #[clap(
+        long,
+        overrides_with = "notify",
+        help = "No desktop notifications even if notify.enabled is set in the config"
+    )]
+    no_notify: bool,
+
+    #[clap(
+        short,
+        long,
+        help = "Dump raw json reply from server",
+        conflicts_with = "html",
+        conflicts_with = "notify"
+    )]
+    raw: bool,
+
+    #[clap(
+        long,
+        value_enum,
+        conflicts_with_all = ["html", "raw"],
+        help = "Print results for other programs: alfred for Script Filter JSON of Alfred and Raycast, waybar for a Waybar custom module, line for other status bars, short like --short; all but alfred show the last looked-up word if none is given (default: display.format in the config)"
+    )]
+    format: Option<OutputFormat>,
+
+    #[clap(
+        short,
+        long,
+        conflicts_with_all = ["html", "raw", "format", "goldendict"],
+        help = "Print only the word, its phonetic and first translation on one line, for scripts and status bars (same as --format short)"
+    )]
+    short: bool,
+
+    #[clap(
+        long,
+        conflicts_with_all = ["html", "raw", "format"],
+        help = "Print a whole HTML document, for a \"programs\" source of GoldenDict running ydcv --goldendict %GDWORD%"
+    )]
+    goldendict: bool,
+
+    #[clap(
+        short,
+        long,
+        help = "When to color the output (default: display.color in the config, or auto)"
+    )]
+    color: Option<ColorChoice>,
+
+    #[cfg(unix)]
+    #[cfg(feature = "notify")]
+    #[clap(
+        short,
+        long,
+        help = "Timeout of notification (second) (default: notify.timeout in the config, or 30)"
+    )]
+    timeout: Option<i32>,
+
+    #[clap(
+        long,
+        value_name = "PATH",
+        help = "Save the fetched page of each query before parsing it, to stdout if PATH is -, or to <word>.html if PATH is a directory"
+    )]
+    dump_html: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "FILE",
+        conflicts_with = "dump_html",
+        help = "Explain a page saved by --dump-html instead of looking up, as the given word or else the file name"
+    )]
+    parse_file: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "Look up again instead of taking results from the cache or the daemon, replacing the cached ones"
+    )]
+    refresh: bool,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Report network time, parse time and cache hits of each lookup on stderr"
+    )]
+    timing: bool,
+
+    #[clap(
+        long,
+        help = "Print the result of each of many words as soon as it arrives, rather than in the given order"
+    )]
+    unordered: bool,
+
+    #[cfg(all(unix, feature = "dbus"))]
+    #[clap(
+        long,
+        help = "Serve lookups on the session bus as org.ydcv.Ydcv instead of looking up"
+    )]
+    dbus: bool,
+
+    #[clap(
+        long,
+        help = "Answer JSON-RPC requests on stdin, one per line, for editor plugins: lookup, suggest and add-to-wordbook with a word"
+    )]
+    stdio: bool,
+
+    #[cfg(unix)]
+    #[clap(
+        long,
+        requires = "free",
+        conflicts_with_all = ["from", "to", "sentence", "word", "stdio", "parse_file"],
+        help = "Forward lookups to the daemon started by ydcv daemon, skipping startup and TLS handshakes"
+    )]
+    client: bool,
+
+    #[cfg(unix)]
+    #[clap(
+        long,
+        requires = "client",
+        help = "Socket of the daemon [default: $XDG_RUNTIME_DIR/ydcv.sock]"
+    )]
+    socket: Option<PathBuf>,
+
+    #[clap(short, long, help = "Add looked-up words to the wordbook")]
+    add: bool,
+
+    #[clap(
+        long,
+        overrides_with = "add",
+        help = "Don't add looked-up words to the wordbook even if lookup.add is set in the config"
+    )]
+    no_add: bool,
+
+    #[clap(short = 'S', long, help = "Play the pronunciation of looked-up words")]
+    speak: bool,
+
+    #[clap(
+        long,
+        overrides_with = "speak",
+        help = "Don't play pronunciations even if lookup.speak is set in the config"
+    )]
+    no_speak: bool,
+
+    #[cfg(feature = "tts")]
+    #[clap(long, help = "Speak translated sentences with local TTS")]
+    speak_source: bool,
+
+    #[cfg(feature = "tts")]
+    #[clap(long, help = "Speak translations of sentences with local TTS")]
+    speak_target: bool,
+
+    #[clap(
+        long,
+        value_enum,
+        help = "Pronunciation to play and to show first (default: us, or audio.accent in the config)"
+    )]
+    accent: Option<Accent>,
+
+    #[clap(
+        long,
+        value_name = "N",
+        help = "Number of bilingual example sentences to show (default: display.examples in the config, or 0)"
+    )]
+    examples: Option<usize>,
+
+    #[clap(
+        long,
+        value_name = "EXAM",
+        requires = "free",
+        help = "Only show words in the word list of an exam, e.g. CET4, CET6, 考研, TOEFL, GRE"
+    )]
+    filter_tag: Option<String>,
+
+    #[clap(
+        long,
+        value_name = "LABEL",
+        help = "Only show senses with this domain or register label, e.g. 计 or 口, can be given more than once"
+    )]
+    sense_label: Vec<String>,
+
+    #[clap(
+        long,
+        help = "Translate the words as a sentence even if they look like a word",
+        conflicts_with = "word"
+    )]
+    sentence: bool,
+
+    #[clap(
+        long,
+        help = "Look up the words in the dictionary even if they look like a sentence"
+    )]
+    word: bool,
+
+    #[clap(
+        long,
+        value_enum,
+        visible_alias = "lang",
+        help = "Language of the words (default: detected, ja for words with kana)"
+    )]
+    from: Option<Lang>,
+
+    #[clap(
+        long,
+        value_enum,
+        help = "Language to translate to (default: en for Chinese words, zh otherwise)"
+    )]
+    to: Option<Lang>,
+
+    #[cfg(feature = "traditional")]
+    #[clap(
+        long,
+        help = "Convert traditional Chinese words to simplified before looking them up"
+    )]
+    simplify: bool,
+
+    #[cfg(feature = "traditional")]
+    #[clap(
+        long,
+        value_enum,
+        value_name = "VARIANT",
+        num_args = 0..=1,
+        default_missing_value = "hant",
+        help = "Show results in traditional Chinese"
+    )]
+    traditional: Option<ZhVariant>,
+
+    #[clap(
+        long,
+        value_enum,
+        value_name = "STYLE",
+        help = "Show the pinyin of Chinese queries in this tone style (default: display.pinyin in the config, or hidden)"
+    )]
+    pinyin: Option<PinyinStyle>,
+
+    #[clap(
+        long,
+        value_enum,
+        value_name = "STYLE",
+        help = "Show phonetics as given, as clean IPA, or as a simplified respelling (default: display.phonetics in the config, or raw)"
+    )]
+    phonetics: Option<PhoneticStyle>,
+
+    #[clap(long, help = "Show numbered senses from the Collins dictionary")]
+    collins: bool,
+
+    #[clap(
+        long,
+        overrides_with = "collins",
+        help = "Hide the Collins senses even if display.collins is set in the config"
+    )]
+    no_collins: bool,
+
+    #[clap(
+        long,
+        help = "Show English definitions instead of Chinese explanations and hide Chinese glosses"
+    )]
+    en_en: bool,
+
+    #[clap(
+        long,
+        overrides_with = "en_en",
+        help = "Show Chinese explanations even if display.en_en is set in the config"
+    )]
+    no_en_en: bool,
 
-#[derive(Parser)]
-#[clap(version, about, max_term_width = 80)]
-struct YdcvOptions {
-    #[cfg(feature = "clipboard")]
-    #[clap(short = 'x', long, help = "Show explanation of current selection")]
-    selection: bool,
+    #[clap(
+        long,
+        value_name = "N",
+        help = "Number of common phrases to show, 0 to hide them (default: display.phrases in the config, or 0)"
+    )]
+    phrases: Option<usize>,
 
-    #[cfg(windows)]
-    #[cfg(feature = "clipboard")]
     #[clap(
-        short,
         long,
-        help = "Time interval between selection in msec (default: 1000 on windows and 0 on others)",
-        default_value = "1000"
+        help = "Show the inflected forms of words and the lemma of forms"
     )]
-    interval: u64,
+    forms: bool,
 
-    #[cfg(unix)]
-    #[cfg(feature = "clipboard")]
     #[clap(
-        short,
         long,
-        help = "Time interval between selection in msec (default: 1000 on windows and 0 on others)",
-        default_value = "0"
+        overrides_with = "forms",
+        help = "Hide the forms even if display.forms is set in the config"
     )]
-    interval: u64,
+    no_forms: bool,
 
-    #[clap(short = 'H', long, help = "HTML-style output")]
-    html: bool,
+    #[clap(long, help = "Show synonyms and antonyms")]
+    synonyms: bool,
 
-    #[cfg(feature = "notify")]
-    #[clap(short, long, help = "Send desktop notifications (implies -H on X11)")]
-    notify: bool,
+    #[clap(
+        long,
+        overrides_with = "synonyms",
+        help = "Hide synonyms and antonyms even if display.synonyms is set in the config"
+    )]
+    no_synonyms: bool,
 
     #[clap(
-        short,
         long,
-        help = "Dump raw json reply from server",
-        conflicts_with = "html",
-        conflicts_with = "notify"
+        help = "Show senses from the Chinese-English dictionary for Chinese queries"
     )]
-    raw: bool,
+    ce_dict: bool,
 
-    #[clap(short, long, default_value = "auto")]
-    color: ColorChoice,
+    #[clap(
+        long,
+        overrides_with = "ce_dict",
+        help = "Hide the Chinese-English senses even if display.ce_dict is set in the config"
+    )]
+    no_ce_dict: bool,
 
-    #[cfg(unix)]
-    #[cfg(feature = "notify")]
     #[clap(
-        short,
         long,
-        help = "Timeout of notification (second)",
-        default_value = "30"
+        help = "Show the daily lookup streak when entering interactive mode"
     )]
-    timeout: i32,
+    streak: bool,
 
-    #[clap(value_name = "WORDS", help = "Words to lookup")]
+    #[clap(
+        value_name = "WORDS",
+        help = "Words to lookup, after -- if the first is also a subcommand, e.g. ydcv -- review"
+    )]
     free: Vec<String>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[clap(about = "Manage lookup history")]
+    History {
+        #[clap(subcommand)]
+        command: HistoryCommand,
+    },
+
+    #[clap(about = "Manage the Youdao account session used for syncing")]
+    Account {
+        #[clap(subcommand)]
+        command: AccountCommand,
+    },
+
+    #[clap(about = "Read and change the config file")]
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommand,
+    },
+
+    #[clap(about = "Write a config file by answering a few questions")]
+    Init,
+
+    #[clap(about = "Manage the wordbook")]
+    Wb {
+        #[clap(subcommand)]
+        command: WbCommand,
+    },
+
+    #[clap(about = "Manage pronunciation audio")]
+    Audio {
+        #[clap(subcommand)]
+        command: AudioCommand,
+    },
+
+    #[clap(about = "Study standard word lists day by day")]
+    Plan {
+        #[clap(subcommand)]
+        command: PlanCommand,
+    },
+
+    #[clap(about = "Review due words in the wordbook with spaced repetition")]
+    Review {
+        #[clap(
+            short = 'n',
+            long,
+            help = "Maximum number of words to review",
+            default_value = "20"
+        )]
+        limit: usize,
+
+        #[clap(short, long, value_enum, default_value = "flashcard")]
+        mode: QuizMode,
+    },
+
+    #[clap(about = "Send a desktop notification if words are due for review, at most once a day")]
+    Remind {
+        #[clap(short, long, help = "Keep running and check every --interval minutes")]
+        daemon: bool,
+
+        #[clap(
+            short,
+            long,
+            help = "Minutes between checks in daemon mode or the systemd timer",
+            default_value = "60",
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        interval: u64,
+
+        #[clap(
+            long,
+            help = "Print systemd user units running the reminder periodically",
+            conflicts_with = "daemon"
+        )]
+        systemd: bool,
+    },
+
+    #[clap(about = "Show lookup statistics and daily streak")]
+    Stats {
+        #[clap(
+            short,
+            long,
+            help = "Number of recent days to show",
+            default_value = "7"
+        )]
+        days: u64,
+    },
+
+    #[clap(about = "Run a language server explaining words on hover in prose files")]
+    Lsp,
+
+    #[cfg(unix)]
+    #[clap(
+        about = "Answer lookups of ydcv --client, and JSON-RPC requests like --stdio, on a unix socket"
+    )]
+    Daemon {
+        #[clap(
+            long,
+            help = "Socket to listen on [default: $XDG_RUNTIME_DIR/ydcv.sock]"
+        )]
+        socket: Option<PathBuf>,
+
+        #[clap(
+            long,
+            help = "Seconds without requests after which to exit, when started by systemd socket activation, 0 to never exit",
+            default_value = "600"
+        )]
+        idle_timeout: u64,
+
+        #[clap(long, help = "Print systemd user units starting it on the first query")]
+        systemd: bool,
+
+        #[clap(
+            long,
+            value_name = "PER_MINUTE",
+            help = "Lookups allowed a minute, shared by all local clients, 0 for no limit (default: server.rate_limit in the config, or 60)"
+        )]
+        rate_limit: Option<u32>,
+
+        #[clap(
+            long,
+            help = "Lookups allowed at once (default: server.burst in the config, or 10)"
+        )]
+        burst: Option<u32>,
+    },
+
+    #[clap(
+        about = "Serve lookups over HTTP at /lookup?word=...[&format=html], and metrics at /metrics"
+    )]
+    Serve {
+        #[clap(long, help = "Address to listen on", default_value = "127.0.0.1:8090")]
+        listen: SocketAddr,
+
+        #[clap(
+            long,
+            help = "Seconds without requests after which to exit, when started by systemd socket activation, 0 to never exit",
+            default_value = "600"
+        )]
+        idle_timeout: u64,
+
+        #[clap(long, help = "Print systemd user units starting it on the first query")]
+        systemd: bool,
+
+        #[clap(
+            long,
+            value_name = "PER_MINUTE",
+            help = "Lookups allowed to each client a minute, 0 for no limit (default: server.rate_limit in the config, or 60)"
+        )]
+        rate_limit: Option<u32>,
+
+        #[clap(
+            long,
+            help = "Lookups allowed to each client at once (default: server.burst in the config, or 10)"
+        )]
+        burst: Option<u32>,
+
+        #[clap(
+            long,
+            value_name = "PEM",
+            requires = "tls_key",
+            help = "Certificate chain to serve HTTPS with"
+        )]
+        tls_cert: Option<PathBuf>,
+
+        #[clap(
+            long,
+            value_name = "PEM",
+            requires = "tls_cert",
+            help = "Private key of --tls-cert"
+        )]
+        tls_key: Option<PathBuf>,
+
+        #[clap(
+            long,
+            value_name = "PATH",
+            help = "File with a token requests must send as \"Authorization: Bearer <token>\""
+        )]
+        token_file: Option<PathBuf>,
+    },
+
+    #[clap(about = "Serve lookups over the DICT protocol (RFC 2229) for dict clients")]
+    DictServer {
+        #[clap(long, help = "Address to listen on", default_value = "127.0.0.1:2628")]
+        listen: SocketAddr,
+    },
+
+    #[cfg(feature = "grpc")]
+    #[clap(about = "Serve the Lookup, Suggest and AddToWordbook RPCs over gRPC")]
+    Grpc {
+        #[clap(long, help = "Address to listen on", default_value = "127.0.0.1:50051")]
+        listen: SocketAddr,
+    },
+
+    #[clap(about = "Run a Telegram bot answering words sent to it with their explanations")]
+    Bot {
+        #[clap(
+            long,
+            value_name = "PATH",
+            help = "File with the token of the bot given by @BotFather [default: YDCV_TELEGRAM_TOKEN]"
+        )]
+        token_file: Option<PathBuf>,
+
+        #[clap(
+            long = "allow-chat",
+            value_name = "CHAT_ID",
+            help = "Only answer this chat, can be given more than once [default: any chat]"
+        )]
+        allowed_chats: Vec<i64>,
+    },
+
+    #[clap(about = "Explain words in a tmux popup sized to fit, closed by any key")]
+    TmuxPopup {
+        #[clap(long, hide = true, help = "Show the explanation in the popup opened")]
+        inside: bool,
+
+        #[clap(required = true)]
+        words: Vec<String>,
+    },
+
+    #[clap(about = "List words looked up repeatedly in recent days")]
+    ReviewCandidates {
+        #[clap(
+            short = 'n',
+            long,
+            help = "Only list words looked up more than this many times",
+            default_value = "2"
+        )]
+        min_count: u32,
+
+        #[clap(
+            short,
+            long,
+            help = "Number of recent days to consider",
+            default_value = "30",
+            value_parser = clap::value_parser!(i64).range(1..)
+        )]
+        days: i64,
+
+        #[clap(short, long, help = "Show lookup counts next to the words")]
+        verbose: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum HistoryCommand {
+    #[clap(about = "Export lookup history with cached translations")]
+    Export {
+        #[clap(short, long, value_enum, default_value = "csv")]
+        format: ExportFormat,
+
+        #[clap(short, long, help = "Write to file instead of stdout")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum WbCommand {
+    #[clap(about = "Add words to the wordbook")]
+    Add {
+        #[clap(value_name = "WORDS", required = true)]
+        words: Vec<String>,
+
+        #[clap(short, long, value_delimiter = ',', help = "Comma-separated tags")]
+        tag: Vec<String>,
+
+        #[clap(short, long, help = "Free-form note")]
+        note: Option<String>,
+    },
+
+    #[clap(about = "Change tags and note of a word in the wordbook")]
+    Edit {
+        word: String,
+
+        #[clap(
+            short,
+            long,
+            value_delimiter = ',',
+            help = "Comma-separated tags to add"
+        )]
+        tag: Vec<String>,
+
+        #[clap(
+            short,
+            long,
+            value_delimiter = ',',
+            help = "Comma-separated tags to remove"
+        )]
+        untag: Vec<String>,
+
+        #[clap(short, long, help = "Replace the note, an empty note removes it")]
+        note: Option<String>,
+    },
+
+    #[clap(about = "Remove words from the wordbook")]
+    Remove {
+        #[clap(value_name = "WORDS", required = true)]
+        words: Vec<String>,
+    },
+
+    #[clap(about = "Export the wordbook")]
+    Export {
+        #[clap(
+            long,
+            value_name = "FILE",
+            help = "Write an Anki deck, tab-separated if FILE ends with .tsv or .txt, - for stdout"
+        )]
+        anki: PathBuf,
+
+        #[clap(long, help = "Style the fields with HTML")]
+        html: bool,
+    },
+
+    #[clap(
+        about = "Search definitions and notes of the wordbook (marked with *) and looked-up words"
+    )]
+    Search {
+        #[clap(required = true)]
+        text: Vec<String>,
+    },
+
+    #[clap(about = "Sync the wordbook with the Youdao online wordbook")]
+    Sync {
+        #[clap(
+            long,
+            help = "Only add online words to the local wordbook",
+            conflicts_with = "push_only"
+        )]
+        pull_only: bool,
+
+        #[clap(long, help = "Only add local words to the online wordbook")]
+        push_only: bool,
+    },
+
+    #[clap(about = "List words in the wordbook")]
+    List {
+        #[clap(short, long, help = "Only list words with this tag")]
+        tag: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AccountCommand {
+    #[clap(about = "Save the session cookie of a browser logged in to dict.youdao.com")]
+    Login {
+        #[clap(help = "Value of the Cookie header, read from stdin if omitted")]
+        cookie: Option<String>,
+    },
+
+    #[clap(about = "Forget the saved session")]
+    Logout,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    #[clap(about = "Print the value of a key, like display.color")]
+    Get { key: String },
+
+    #[clap(about = "Set a key to a TOML value, taken as a string if it isn't one")]
+    Set { key: String, value: String },
+
+    #[clap(about = "Open the config file in $VISUAL or $EDITOR and check it after")]
+    Edit,
+
+    #[clap(about = "Print the path of the config file")]
+    Path,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum AudioSource {
+    Wordbook,
+}
+
+#[derive(Subcommand)]
+enum AudioCommand {
+    #[clap(about = "Download pronunciations into a folder, e.g. for Anki media import")]
+    Fetch {
+        #[clap(long, value_enum, help = "Words to download")]
+        from: AudioSource,
+
+        #[clap(short, long, help = "Only download words with this tag")]
+        tag: Option<String>,
+
+        #[clap(
+            short,
+            long,
+            help = "Folder to save the MP3 files to",
+            default_value = "."
+        )]
+        output: PathBuf,
+
+        #[clap(long, value_enum, help = "Accent of the pronunciations")]
+        accent: Option<Accent>,
+    },
+}
+
+#[derive(Subcommand)]
+enum PlanCommand {
+    #[clap(about = "Start a plan, or change the daily amount of a started one")]
+    Start {
+        #[clap(help = "Name of the plan, which its words are tagged with")]
+        name: String,
+
+        #[clap(
+            short,
+            long,
+            help = "Number of new words per day",
+            default_value = "30"
+        )]
+        per_day: u32,
+
+        #[clap(
+            short,
+            long,
+            value_name = "FILE_OR_URL",
+            help = "Word list with one word per line (default: <NAME>.txt in the lists directory)"
+        )]
+        file: Option<String>,
+    },
+
+    #[clap(about = "Stop a plan, keeping its words in the wordbook")]
+    Stop { name: String },
+
+    #[clap(about = "Show progress of all plans")]
+    Status,
+}
+
+/// Exit after `idle_timeout` seconds without requests, unless it's 0
+fn idle_exit(idle_timeout: u64) -> Option<IdleExit> {
+    (idle_timeout > 0).then(|| IdleExit::new(Duration::from_secs(idle_timeout)))
+}
+
+/// Limits of `rate_limit` lookups a minute and `burst` at once, unless it's 0
+fn rate_limiter<K: Hash + Eq + Clone>(
+    rate_limit: Option<u32>,
+    burst: Option<u32>,
+) -> Option<RateLimiter<K>> {
+    let rate_limit = rate_limit.or(CONFIG.server.rate_limit).unwrap_or(60);
+    let burst = burst.or(CONFIG.server.burst).unwrap_or(10);
+    (rate_limit > 0).then(|| RateLimiter::new(rate_limit, burst))
+}
+
+/// A switch of the command line, turned on by `on` or off by `off`, whichever
+/// was given last, or else as in the config
+fn switch(on: bool, off: bool, config: bool) -> bool {
+    on || !off && config
+}
+
+/// How explanations are shown by the display keys of the config, for the
+/// servers and the lookups given options on the command line
+fn config_explain() -> ExplainOptions {
+    ExplainOptions {
+        accent: CONFIG.audio.accent,
+        examples: CONFIG.display.examples.unwrap_or(0),
+        collins: CONFIG.display.collins,
+        ce_dict: CONFIG.display.ce_dict,
+        forms: CONFIG.display.forms,
+        synonyms: CONFIG.display.synonyms,
+        phrases: CONFIG.display.phrases.unwrap_or(0),
+        pinyin: CONFIG.display.pinyin,
+        en_en: CONFIG.display.en_en,
+        sense_labels: vec![],
+        romanize: CONFIG.display.romanize,
+        phonetics: CONFIG.display.phonetics.unwrap_or_default(),
+        labels: CONFIG.labels.clone(),
+    }
+}
+
+/// The database of `storage.database` in the config, or the default one,
+/// recording no lookups if `lookup.history` is off
+fn open_db() -> Result<Db> {
+    let db = match &CONFIG.storage.database {
+        Some(path) => Db::open(path)?,
+        None => Db::open_default()?,
+    };
+    Ok(match CONFIG.lookup.history {
+        Some(false) => db.without_history(),
+        _ => db,
+    })
+}
+
+fn run_config_command(command: ConfigCommand) -> Result<()> {
+    match command {
+        ConfigCommand::Get { key } => config::get(&key),
+        ConfigCommand::Set { key, value } => config::set(&key, &value),
+        ConfigCommand::Edit => config::edit(),
+        ConfigCommand::Path => {
+            println!("{}", Config::path()?.display());
+            Ok(())
+        }
+    }
+}
+
+fn run_command(command: Command) -> Result<()> {
+    let db = open_db()?;
+
+    match command {
+        Command::History {
+            command: HistoryCommand::Export { format, output },
+        } => match output {
+            Some(path) => history::export(&db, format, &mut File::create(path)?),
+            None => history::export(&db, format, &mut stdout().lock()),
+        },
+        Command::Account { command } => match command {
+            AccountCommand::Login { cookie } => match cookie {
+                Some(cookie) => account::login(&cookie),
+                None => account::login(&std::io::read_to_string(std::io::stdin())?),
+            },
+            AccountCommand::Logout => account::logout(),
+        },
+        Command::Config { command } => run_config_command(command),
+        Command::Init => config::init(),
+        Command::Wb { command } => match command {
+            WbCommand::Add { words, tag, note } => {
+                let words = words.iter().map(|x| x.trim()).collect::<Vec<_>>();
+                for resp in cached_or_lookup_all(&*YD_CLIENT, &db, &words)? {
+                    wordbook::add(&db, &resp)?;
+                    if resp.has_result() {
+                        wordbook::annotate(&db, resp.query(), &tag, &[], note.as_deref())?;
+                    }
+                }
+                Ok(())
+            }
+            WbCommand::Edit {
+                word,
+                tag,
+                untag,
+                note,
+            } => wordbook::annotate(&db, &word, &tag, &untag, note.as_deref()),
+            WbCommand::Remove { words } => {
+                for word in words {
+                    wordbook::remove(&db, word.trim())?;
+                }
+                Ok(())
+            }
+            WbCommand::Export { anki, html } => {
+                let delimiter = match anki.extension().and_then(|x| x.to_str()) {
+                    Some("tsv" | "txt") => b'\t',
+                    _ => b',',
+                };
+                let accent = CONFIG.audio.accent.unwrap_or_default();
+                if anki.as_os_str() == "-" {
+                    anki::export(&db, &mut stdout().lock(), delimiter, html, accent)
+                } else {
+                    anki::export(&db, &mut File::create(anki)?, delimiter, html, accent)
+                }
+            }
+            WbCommand::Search { text } => wordbook::search(&db, &text.join(" ")),
+            WbCommand::Sync {
+                pull_only,
+                push_only,
+            } => account::sync(
+                &db,
+                &CLIENT,
+                &|word| cached_or_lookup(&*YD_CLIENT, &db, word),
+                !push_only,
+                !pull_only,
+            ),
+            WbCommand::List { tag } => wordbook::list(&db, tag.as_deref()),
+        },
+        Command::Audio {
+            command:
+                AudioCommand::Fetch {
+                    from: AudioSource::Wordbook,
+                    tag,
+                    output,
+                    accent,
+                },
+        } => {
+            let words = db
+                .wordbook()?
+                .into_iter()
+                .filter(|x| tag.as_ref().is_none_or(|t| x.tags.contains(t)))
+                .map(|x| x.word)
+                .collect::<Vec<_>>();
+            let accent = accent.or(CONFIG.audio.accent).unwrap_or_default();
+            let copied = audio::fetch_all(&CLIENT, &words, accent, &output)?;
+            println!(
+                "Saved {copied} of {} pronunciation(s) to {}",
+                words.len(),
+                output.display()
+            );
+            Ok(())
+        }
+        Command::Plan { command } => match command {
+            PlanCommand::Start {
+                name,
+                per_day,
+                file,
+            } => plan::start(&db, &CLIENT, &name, file.as_deref(), per_day),
+            PlanCommand::Stop { name } => {
+                if db.plan_stop(&name)? {
+                    println!("Stopped plan {name}");
+                } else {
+                    println!("No plan named {name}");
+                }
+                Ok(())
+            }
+            PlanCommand::Status => plan::status(&db),
+        },
+        Command::Review { limit, mode } => {
+            let added = plan::feed(&db, &|word| cached_or_lookup(&*YD_CLIENT, &db, word))?;
+            if added > 0 {
+                println!("Added {added} new word(s) from study plans");
+            }
+
+            let speak = |word: &str| {
+                audio::speak(
+                    &CLIENT,
+                    word,
+                    CONFIG.audio.accent.unwrap_or_default(),
+                    CONFIG.audio.player.as_deref(),
+                )
+            };
+            if stdout().is_terminal() {
+                review::run(&db, &mut AnsiFormatter::new(false), limit, mode, &speak)
+            } else {
+                review::run(&db, &mut PlainFormatter::new(false), limit, mode, &speak)
+            }
+        }
+        Command::Remind {
+            daemon,
+            interval,
+            systemd,
+        } => {
+            if systemd {
+                let exe = std::env::current_exe()?;
+                print!(
+                    "{}",
+                    remind::systemd_units(&exe.to_string_lossy(), interval)
+                );
+                return Ok(());
+            }
+
+            #[cfg(all(unix, feature = "notify"))]
+            let mut fmt = HtmlFormatter::new(true);
+            #[cfg(all(windows, feature = "notify"))]
+            let mut fmt = WinFormatter::new(true);
+            #[cfg(not(feature = "notify"))]
+            let mut fmt = PlainFormatter::new(false);
+
+            if daemon {
+                remind::daemon(
+                    &db,
+                    &mut fmt,
+                    Duration::from_secs(interval.saturating_mul(60)),
+                )
+            } else {
+                if remind::reminded_today(&db)? {
+                    println!("Already reminded today");
+                } else if !remind::remind(&db, &mut fmt)? {
+                    println!("No words due for review");
+                }
+                Ok(())
+            }
+        }
+        Command::Stats { days } => {
+            Stats::load(&db)?.print(days);
+            Ok(())
+        }
+        Command::Lsp => lsp::serve(
+            &*YD_CLIENT,
+            Some(&db),
+            &config_explain(),
+            stdin().lock(),
+            stdout().lock(),
+        ),
+        #[cfg(unix)]
+        Command::Daemon {
+            socket,
+            idle_timeout,
+            systemd,
+            rate_limit,
+            burst,
+        } => {
+            if systemd {
+                let exe = std::env::current_exe()?;
+                let listen = match socket {
+                    Some(socket) => socket.to_string_lossy().into_owned(),
+                    None => "%t/ydcv.sock".to_string(),
+                };
+                print!(
+                    "{}",
+                    activation::systemd_units(
+                        &exe.to_string_lossy(),
+                        "daemon",
+                        &listen,
+                        &format!("--socket {listen} --idle-timeout {idle_timeout}")
+                    )
+                );
+                return Ok(());
+            }
+
+            let (listener, idle) = match activation::listener() {
+                Some(listener) => (listener, idle_exit(idle_timeout)),
+                None => {
+                    let socket = match socket {
+                        Some(socket) => socket,
+                        None => daemon::default_socket()?,
+                    };
+                    (daemon::bind(&socket)?, None)
+                }
+            };
+            daemon::serve(
+                &*YD_CLIENT,
+                open_db,
+                &config_explain(),
+                listener,
+                idle.as_ref(),
+                rate_limiter(rate_limit, burst),
+            )
+        }
+        Command::Serve {
+            listen,
+            idle_timeout,
+            systemd,
+            rate_limit,
+            burst,
+            tls_cert,
+            tls_key,
+            token_file,
+        } => {
+            if systemd {
+                let exe = std::env::current_exe()?;
+                print!(
+                    "{}",
+                    activation::systemd_units(
+                        &exe.to_string_lossy(),
+                        "serve",
+                        &listen.to_string(),
+                        &format!("--listen {listen} --idle-timeout {idle_timeout}")
+                    )
+                );
+                return Ok(());
+            }
+
+            let token = match token_file {
+                Some(path) => {
+                    let token = fs::read_to_string(&path)
+                        .with_context(|| format!("Failed to read token {}", path.display()))?;
+                    if token.trim().is_empty() {
+                        anyhow::bail!("Empty token in {}", path.display());
+                    }
+                    Some(token.trim().to_string())
+                }
+                None => None,
+            };
+            if token.is_none() && !listen.ip().is_loopback() {
+                warn!("Anyone reaching {listen} can look up, set a token with --token-file");
+            }
+            #[cfg(not(feature = "rustls"))]
+            if tls_cert.is_some() || tls_key.is_some() {
+                anyhow::bail!("HTTPS needs ydcv built with the rustls feature");
+            }
+            let access = server::Access {
+                limiter: rate_limiter(rate_limit, burst),
+                token,
+                #[cfg(feature = "rustls")]
+                tls: match (tls_cert, tls_key) {
+                    (Some(cert), Some(key)) => Some(server::tls_config(&cert, &key)?),
+                    _ => None,
+                },
+            };
+
+            let (listener, idle) = match activation::listener() {
+                Some(listener) => (listener, idle_exit(idle_timeout)),
+                None => (
+                    TcpListener::bind(listen)
+                        .with_context(|| format!("Failed to listen on {listen}"))?,
+                    None,
+                ),
+            };
+            server::serve(
+                &*YD_CLIENT,
+                open_db,
+                &config_explain(),
+                listener,
+                idle.as_ref(),
+                access,
+            )
+        }
+        Command::DictServer { listen } => {
+            let listener = TcpListener::bind(listen)
+                .with_context(|| format!("Failed to listen on {listen}"))?;
+            dict::serve(&*YD_CLIENT, open_db, &config_explain(), listener)
+        }
+        #[cfg(feature = "grpc")]
+        Command::Grpc { listen } => {
+            let client = http::build_async_client(HTTP_OPTIONS.get_or_init(HttpOptions::default))?;
+            grpc::serve(
+                lookups::Lookups::new(client, Some(db), config_explain()),
+                listen,
+            )
+        }
+        Command::Bot {
+            token_file,
+            allowed_chats,
+        } => bot::run(
+            &CLIENT,
+            &bot::token(token_file.as_deref())?,
+            &*YD_CLIENT,
+            &db,
+            &config_explain(),
+            &allowed_chats,
+        ),
+        Command::TmuxPopup { inside, words } => {
+            let word = words.join(" ");
+            let resp = cached_or_lookup(&*YD_CLIENT, &db, &word)?;
+            let options = config_explain();
+            if inside {
+                println!(
+                    "{}",
+                    resp.explain_with(&AnsiFormatter::new(false), &options)
+                );
+                tmux::wait_for_key()
+            } else {
+                // looked up before opening the popup so that failures show in
+                // the pane, and the popup finds it in the cache
+                let plain = resp.explain_with(&PlainFormatter::new(false), &options);
+                tmux::open_popup(&word, &plain)
+            }
+        }
+        Command::ReviewCandidates {
+            min_count,
+            days,
+            verbose,
+        } => {
+            let since = TimeDelta::try_days(days)
+                .and_then(|x| Local::now().checked_sub_signed(x))
+                .with_context(|| format!("Too many days: {days}"))?;
+            for (word, count) in db.frequent_lookups(since, min_count)? {
+                if verbose {
+                    println!("{word}\t{count}");
+                } else {
+                    println!("{word}");
+                }
+            }
+            Ok(())
+        }
+    }
 }
 
 fn main() -> Result<()> {
@@ -118,9 +1873,85 @@ fn main() -> Result<()> {
     env_logger::init();
 
     let ydcv_options = YdcvOptions::parse();
+    // these fix a broken config, so they run before it is loaded
+    match ydcv_options.command {
+        Some(Command::Config { command }) => return run_config_command(command),
+        Some(Command::Init) => return config::init(),
+        _ => {}
+    }
+    paths::migrate()?;
+    let profile = ydcv_options
+        .profile
+        .clone()
+        .or_else(|| std::env::var("YDCV_PROFILE").ok());
+    // a broken config or a missing profile fails rather than falling back to
+    // the default config
+    Config::load(profile.as_deref())?;
+    PROFILE.get_or_init(|| profile);
+    HTTP_OPTIONS.get_or_init(|| HttpOptions {
+        proxy: ydcv_options
+            .proxy
+            .clone()
+            .or_else(|| ydcv_options.socks5.as_deref().map(http::socks5_url))
+            .or_else(|| CONFIG.network.proxy.clone())
+            .or_else(|| CONFIG.network.socks5.as_deref().map(http::socks5_url)),
+        timeout: ydcv_options
+            .request_timeout
+            .or(CONFIG.network.timeout)
+            .map(Duration::from_secs),
+        retries: ydcv_options.retries.or(CONFIG.network.retries).unwrap_or(2),
+        ca_cert: ydcv_options
+            .ca_cert
+            .clone()
+            .or_else(|| CONFIG.network.ca_cert.clone()),
+        insecure: ydcv_options.insecure || CONFIG.network.insecure,
+        user_agents: CONFIG.network.user_agents.clone(),
+        headers: CONFIG
+            .network
+            .headers
+            .clone()
+            .into_iter()
+            .chain(ydcv_options.header.clone())
+            .collect(),
+        cookie_jar: CONFIG
+            .network
+            .cookies
+            .unwrap_or(true)
+            .then(|| paths::cache_dir().ok().map(|x| x.join("cookies.json")))
+            .flatten(),
+        no_compression: ydcv_options.no_compression || CONFIG.network.compression == Some(false),
+        pool_idle_timeout: ydcv_options
+            .pool_idle_timeout
+            .or(CONFIG.network.pool_idle_timeout)
+            .map(Duration::from_secs),
+    });
+    if ydcv_options.timing {
+        ydclient::set_timing();
+    }
+    if let Some(concurrency) = ydcv_options.concurrency.or(CONFIG.network.concurrency) {
+        ydclient::set_concurrency(concurrency);
+    }
+    if let Some(path) = ydcv_options.dump_html.clone() {
+        ydclient::set_dump_html(path);
+    }
+    if let Some(endpoint) = ydcv_options
+        .endpoint
+        .as_deref()
+        .or(CONFIG.network.endpoint.as_deref())
+    {
+        ydclient::set_endpoint(endpoint);
+    }
+
+    if let Some(command) = ydcv_options.command {
+        return run_command(command);
+    }
 
     #[cfg(feature = "notify")]
-    let notify_enabled = ydcv_options.notify;
+    let notify_enabled = switch(
+        ydcv_options.notify,
+        ydcv_options.no_notify,
+        CONFIG.notify.enabled,
+    );
     #[cfg(not(feature = "notify"))]
     let notify_enabled = false;
 
@@ -133,41 +1964,173 @@ fn main() -> Result<()> {
     #[cfg(not(feature = "clipboard"))]
     let selection_enabled = false;
 
-    let mut html = HtmlFormatter::new(notify_enabled);
-    let mut ansi = AnsiFormatter::new(notify_enabled);
-    let mut plain = PlainFormatter::new(notify_enabled);
-    #[cfg(windows)]
-    #[cfg(feature = "notify")]
-    let mut win = WinFormatter::new(notify_enabled);
+    let color = ydcv_options
+        .color
+        .or(CONFIG.display.color)
+        .unwrap_or(ColorChoice::Auto);
+    // only the chosen formatter is built, so a one-shot lookup doesn't set up
+    // notifications it never sends
+    let mut fmt: Box<dyn Formatter> = if ydcv_options.goldendict {
+        Box::new(GoldenDictFormatter::new(false))
+    } else if ydcv_options.html
+        || switch(false, ydcv_options.no_html, CONFIG.display.html)
+        || (notify_enabled && cfg!(unix) && cfg!(feature = "notify"))
+    {
+        #[allow(unused_mut)]
+        let mut html = HtmlFormatter::new(notify_enabled);
+        #[cfg(unix)]
+        #[cfg(feature = "notify")]
+        html.set_timeout(ydcv_options.timeout.or(CONFIG.notify.timeout).unwrap_or(30) * 1000);
+        Box::new(html)
+    } else if notify_enabled {
+        #[cfg(all(windows, feature = "notify"))]
+        {
+            Box::new(WinFormatter::new(notify_enabled))
+        }
+        #[cfg(not(all(windows, feature = "notify")))]
+        {
+            Box::new(PlainFormatter::new(notify_enabled))
+        }
+    } else if color == ColorChoice::Always || stdout().is_terminal() && color != ColorChoice::Never
+    {
+        Box::new(AnsiFormatter::new(notify_enabled))
+    } else {
+        Box::new(PlainFormatter::new(notify_enabled))
+    };
+    let fmt = fmt.as_mut();
 
+    let accent = ydcv_options.accent.or(CONFIG.audio.accent);
+    let explain = config_explain();
+    let lookup_options = LookupOptions {
+        raw: ydcv_options.raw,
+        format: ydcv_options
+            .format
+            .or(ydcv_options.short.then_some(OutputFormat::Short))
+            .or(CONFIG.display.format)
+            .filter(|_| !(ydcv_options.html || ydcv_options.raw || ydcv_options.goldendict)),
+        add: switch(ydcv_options.add, ydcv_options.no_add, CONFIG.lookup.add),
+        speak: switch(
+            ydcv_options.speak,
+            ydcv_options.no_speak,
+            CONFIG.lookup.speak,
+        ),
+        #[cfg(feature = "tts")]
+        speak_source: ydcv_options.speak_source,
+        #[cfg(feature = "tts")]
+        speak_target: ydcv_options.speak_target,
+        accent: accent.unwrap_or_default(),
+        history: CONFIG.lookup.history.unwrap_or(true),
+        // pages are only saved when fetched
+        refresh: ydcv_options.refresh || ydcv_options.dump_html.is_some(),
+        explain: ExplainOptions {
+            accent,
+            examples: ydcv_options.examples.unwrap_or(explain.examples),
+            collins: switch(
+                ydcv_options.collins,
+                ydcv_options.no_collins,
+                explain.collins,
+            ),
+            ce_dict: switch(
+                ydcv_options.ce_dict,
+                ydcv_options.no_ce_dict,
+                explain.ce_dict,
+            ),
+            forms: switch(ydcv_options.forms, ydcv_options.no_forms, explain.forms),
+            synonyms: switch(
+                ydcv_options.synonyms,
+                ydcv_options.no_synonyms,
+                explain.synonyms,
+            ),
+            phrases: ydcv_options.phrases.unwrap_or(explain.phrases),
+            pinyin: ydcv_options.pinyin.or(explain.pinyin),
+            en_en: switch(ydcv_options.en_en, ydcv_options.no_en_en, explain.en_en),
+            phonetics: ydcv_options.phonetics.unwrap_or(explain.phonetics),
+            sense_labels: ydcv_options.sense_label,
+            ..explain
+        },
+        sentence: match (ydcv_options.sentence, ydcv_options.word) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        },
+        from: ydcv_options.from,
+        to: ydcv_options.to,
+        filter_tag: ydcv_options.filter_tag,
+        #[cfg(feature = "traditional")]
+        simplify: ydcv_options.simplify,
+        #[cfg(feature = "traditional")]
+        traditional: ydcv_options.traditional,
+    };
+
+    // the daemon records and caches the lookups itself
     #[cfg(unix)]
-    #[cfg(feature = "notify")]
-    html.set_timeout(ydcv_options.timeout * 1000);
+    if ydcv_options.client {
+        let socket = match &ydcv_options.socket {
+            Some(socket) => socket.clone(),
+            None => daemon::default_socket()?,
+        };
+        for word in &ydcv_options.free {
+            let word = lookup_text(word.trim(), &lookup_options);
+            let result = lookup_query(&word, &lookup_options)
+                .and_then(|query| daemon::lookup(&socket, &query, lookup_options.history));
+            explain_result(None, &word, result, fmt, &lookup_options)?;
+        }
+        return Ok(());
+    }
 
-    let fmt: &mut dyn Formatter =
-        if ydcv_options.html || (notify_enabled && cfg!(unix) && cfg!(feature = "notify")) {
-            &mut html
-        } else if notify_enabled {
-            #[cfg(all(windows, feature = "notify"))]
-            {
-                &mut win
-            }
-            #[cfg(not(all(windows, feature = "notify")))]
-            {
-                &mut plain
-            }
-        } else if ydcv_options.color == ColorChoice::Always
-            || stdout().is_terminal() && ydcv_options.color != ColorChoice::Never
-        {
-            &mut ansi
-        } else {
-            &mut plain
+    let db = open_db()
+        .inspect_err(|e| warn!("Failed to open ydcv database: {e}"))
+        .ok();
+
+    #[cfg(all(unix, feature = "dbus"))]
+    if ydcv_options.dbus {
+        let client = http::build_async_client(HTTP_OPTIONS.get_or_init(HttpOptions::default))?;
+        return dbus::serve(lookups::Lookups::new(client, db, lookup_options.explain));
+    }
+
+    if ydcv_options.stdio {
+        return rpc::serve(
+            &*YD_CLIENT,
+            db.as_ref(),
+            &lookup_options.explain,
+            None,
+            stdin().lock(),
+            stdout().lock(),
+        );
+    }
+
+    if let Some(path) = &ydcv_options.parse_file {
+        // --dump-html saves pages as <word>.html
+        let word = match ydcv_options.free.first() {
+            Some(word) => word.clone(),
+            None => path
+                .file_stem()
+                .context("Failed to get the word from the file name")?
+                .to_string_lossy()
+                .into_owned(),
         };
+        let result = YdResponse::from_html_file(path, &word).map_err(Into::into);
+        explain_result(None, &word, result, fmt, &lookup_options)?;
+        return Ok(());
+    }
+
+    // status bars run ydcv again and again to show the last looked-up word,
+    // which isn't recorded as a lookup each time
+    if let Some(format) = lookup_options.format.filter(|x| x.is_bar())
+        && ydcv_options.free.is_empty()
+        && !selection_enabled
+    {
+        let db = db.context("The last looked-up word is unavailable without the ydcv database")?;
+        if let Some(word) = db.last_lookup()? {
+            match cached_or_lookup(&*YD_CLIENT, &db, &word) {
+                Ok(resp) => println!("{}", format.result(&resp, &lookup_options.explain)),
+                Err(err) => println!("{}", format.error(&word, &err)),
+            }
+        }
+        return Ok(());
+    }
 
-    let history_path = cache_dir()
-        .context("Failed to get cache dir path")?
-        .join("ydcv")
-        .join("history");
+    let history_path = paths::state_dir()?.join("history");
 
     let history_parent = history_path.parent().unwrap();
 
@@ -175,7 +2138,11 @@ fn main() -> Result<()> {
         create_dir_all(history_parent)?;
     }
 
-    let mut history_file = fs::OpenOptions::new().append(true).open(&history_path);
+    let mut history_file = fs::OpenOptions::new()
+        .append(true)
+        .open(&history_path)
+        .ok()
+        .filter(|_| lookup_options.history);
 
     if ydcv_options.free.is_empty() {
         if selection_enabled {
@@ -192,9 +2159,9 @@ fn main() -> Result<()> {
                         let curr = curr.trim_matches('\u{0}').trim();
                         if !curr.is_empty() && last != curr {
                             last = curr.to_owned();
-                            lookup_explain(&CLIENT, curr, fmt, ydcv_options.raw)?;
+                            lookup_explain(&*YD_CLIENT, db.as_ref(), curr, fmt, &lookup_options)?;
 
-                            if let Ok(ref mut history_file) = history_file {
+                            if let Some(ref mut history_file) = history_file {
                                 history_file.write_all(format!("{last}\n").as_bytes())?;
                             }
 
@@ -207,34 +2174,147 @@ fn main() -> Result<()> {
             let mut reader = Editor::<(), FileHistory>::with_config(
                 Builder::new().auto_add_history(true).build(),
             )?;
+            let pressed = keys::bind(&mut reader, &CONFIG.keys)?;
 
-            if history_path.is_file() {
+            if lookup_options.history && history_path.is_file() {
                 reader
                     .load_history(&history_path)
                     .inspect_err(|e| warn!("Failed to load ydcv lookup history: {e}"))
                     .ok();
             }
 
+            if (ydcv_options.streak || CONFIG.display.streak)
+                && let Some(ref db) = db
+            {
+                match Stats::load(db) {
+                    Ok(stats) => println!("{}", stats.streak_line()),
+                    Err(e) => warn!("Failed to load lookup statistics: {e}"),
+                }
+            }
+
+            let mut last: Option<YdResponse> = None;
+            let mut repl_lang = None;
             while let Ok(w) = reader.readline("> ") {
-                let word = w.trim();
-                if !word.is_empty() {
-                    lookup_explain(&CLIENT, word, fmt, ydcv_options.raw)?;
+                let action = pressed.lock().unwrap().take();
+                if let Some(action) = action {
+                    match (action, &last) {
+                        (_, None) => println!("Nothing to act on, look up a word first"),
+                        (keys::Action::Speak, Some(resp)) => {
+                            speak(resp.query(), lookup_options.accent)
+                        }
+                        (keys::Action::AddToWordbook, Some(resp)) => {
+                            add_to_wordbook(db.as_ref(), Some(resp))
+                        }
+                        (keys::Action::OpenBrowser, Some(resp)) => open_in_browser(resp.query()),
+                        (keys::Action::Redisplay, Some(resp)) => {
+                            let exp = resp.explain_with(fmt, &lookup_options.explain);
+                            fmt.print(resp.query(), &exp);
+                        }
+                        (keys::Action::LookUpSuggestion, Some(resp)) => {
+                            match resp.suggestions().first() {
+                                Some(suggestion) => {
+                                    last = lookup_explain(
+                                        &*YD_CLIENT,
+                                        db.as_ref(),
+                                        suggestion,
+                                        fmt,
+                                        &lookup_options,
+                                    )?;
+                                }
+                                None => println!("No spelling suggestion to look up"),
+                            }
+                        }
+                    }
+                    continue;
+                }
+                let word = w.trim().to_string();
+                if let Some(cmd) = word.strip_prefix(':') {
+                    run_repl_command(
+                        cmd,
+                        db.as_ref(),
+                        last.as_ref(),
+                        lookup_options.accent,
+                        &mut repl_lang,
+                    );
+                } else if !word.is_empty() {
+                    let (prefix, word) = lang::split_prefix(&word);
+                    let options = match prefix.or(repl_lang) {
+                        Some(lang) => {
+                            let dir = Direction::pair(word, lang);
+                            LookupOptions {
+                                from: Some(dir.from),
+                                to: Some(dir.to),
+                                ..lookup_options.clone()
+                            }
+                        }
+                        None => lookup_options.clone(),
+                    };
+                    last = lookup_explain(&*YD_CLIENT, db.as_ref(), word, fmt, &options)?;
+                    if let Some(suggestion) = last.as_ref().and_then(|x| x.suggestions().first()) {
+                        println!(
+                            "Press {} to look up {suggestion}",
+                            keys::suggestion_key(&CONFIG.keys)
+                        );
+                    }
+                }
+                if lookup_options.history {
+                    reader
+                        .save_history(&history_path)
+                        .inspect_err(|e| warn!("Failed to load ydcv lookup history: {e}"))
+                        .ok();
                 }
-                reader
-                    .save_history(&history_path)
-                    .inspect_err(|e| warn!("Failed to load ydcv lookup history: {e}"))
-                    .ok();
             }
         }
     } else {
-        for word in &ydcv_options.free {
-            lookup_explain(&CLIENT, word.trim(), fmt, ydcv_options.raw)?;
-        }
+        lookup_explain_all(
+            &YD_CLIENT,
+            db.as_ref(),
+            &ydcv_options.free,
+            fmt,
+            &lookup_options,
+            !ydcv_options.unordered,
+        )?;
 
-        if let Ok(ref mut history_file) = history_file {
+        if let Some(ref mut history_file) = history_file {
             history_file.write_all(format!("{}\n", ydcv_options.free.join(" ")).as_bytes())?;
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switch() {
+        let options = YdcvOptions::parse_from(["ydcv", "--add", "--no-add", "--speak"]);
+        assert!(!switch(options.add, options.no_add, true));
+        assert!(switch(options.speak, options.no_speak, false));
+        let options = YdcvOptions::parse_from(["ydcv", "--no-collins", "--collins"]);
+        assert!(switch(options.collins, options.no_collins, false));
+        assert!(switch(options.en_en, options.no_en_en, true));
+    }
+
+    #[test]
+    fn test_words_after_dash() {
+        let options = YdcvOptions::parse_from(["ydcv", "review"]);
+        assert!(options.command.is_some() && options.free.is_empty());
+        let options = YdcvOptions::parse_from(["ydcv", "--", "review"]);
+        assert!(options.command.is_none());
+        assert_eq!(vec!["review"], options.free);
+        let options = YdcvOptions::parse_from(["ydcv", "-H", "--", "history", "export"]);
+        assert_eq!(vec!["history", "export"], options.free);
+    }
+
+    #[test]
+    fn test_review_candidates_days() {
+        for days in ["0", "-1"] {
+            assert!(
+                YdcvOptions::try_parse_from(["ydcv", "review-candidates", "--days", days]).is_err()
+            );
+        }
+        assert!(YdcvOptions::try_parse_from(["ydcv", "review-candidates", "--days", "7"]).is_ok());
+    }
+}
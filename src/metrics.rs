@@ -0,0 +1,144 @@
+//! Counters of `ydcv serve`, exposed at `/metrics` in the text format of
+//! Prometheus
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Upper bounds in seconds of the buckets of the backend latency histogram
+const LATENCY_BUCKETS: [f64; 8] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Default)]
+struct Counters {
+    /// Requests by status code
+    requests: BTreeMap<u16, u64>,
+    lookups: u64,
+    cache_hits: u64,
+    lookup_errors: u64,
+    /// Backend lookups in each of `LATENCY_BUCKETS`, not cumulative
+    latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    latency_sum: f64,
+    latency_count: u64,
+}
+
+/// Counters shared by the threads answering requests
+#[derive(Default)]
+pub struct Metrics(Mutex<Counters>);
+
+impl Metrics {
+    fn counters(&self) -> MutexGuard<'_, Counters> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Count a request answered with `status`, e.g. "200 OK"
+    pub fn request(&self, status: &str) {
+        let code = status
+            .split_whitespace()
+            .next()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or_default();
+        *self.counters().requests.entry(code).or_default() += 1;
+    }
+
+    /// Count a lookup, answered from the cache if `cache_hit`
+    pub fn lookup(&self, cache_hit: bool) {
+        let mut counters = self.counters();
+        counters.lookups += 1;
+        if cache_hit {
+            counters.cache_hits += 1;
+        }
+    }
+
+    pub fn lookup_error(&self) {
+        self.counters().lookup_errors += 1;
+    }
+
+    /// Count a lookup by the backend that took `latency`
+    pub fn backend_latency(&self, latency: Duration) {
+        let seconds = latency.as_secs_f64();
+        let mut counters = self.counters();
+        if let Some(i) = LATENCY_BUCKETS.iter().position(|x| seconds <= *x) {
+            counters.latency_buckets[i] += 1;
+        }
+        counters.latency_sum += seconds;
+        counters.latency_count += 1;
+    }
+
+    /// All metrics in the text exposition format
+    pub fn render(&self) -> String {
+        let counters = self.counters();
+        let mut out = String::new();
+        let mut counter = |name: &str, help: &str, value: u64| {
+            let _ = write!(
+                out,
+                "# HELP {name} {help}\n# TYPE {name} counter\n{name} {value}\n"
+            );
+        };
+        counter("ydcv_lookups_total", "Words looked up", counters.lookups);
+        counter(
+            "ydcv_cache_hits_total",
+            "Lookups answered from the cache, over ydcv_lookups_total for the hit ratio",
+            counters.cache_hits,
+        );
+        counter(
+            "ydcv_lookup_errors_total",
+            "Lookups that failed",
+            counters.lookup_errors,
+        );
+
+        out.push_str("# HELP ydcv_requests_total HTTP requests by status code\n");
+        out.push_str("# TYPE ydcv_requests_total counter\n");
+        for (code, count) in counters.requests.iter() {
+            let _ = writeln!(out, "ydcv_requests_total{{code=\"{code}\"}} {count}");
+        }
+
+        out.push_str("# HELP ydcv_backend_latency_seconds Latency of lookups on YD\n");
+        out.push_str("# TYPE ydcv_backend_latency_seconds histogram\n");
+        let mut cumulative = 0;
+        for (le, count) in LATENCY_BUCKETS.iter().zip(&counters.latency_buckets) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "ydcv_backend_latency_seconds_bucket{{le=\"{le}\"}} {cumulative}"
+            );
+        }
+        let _ = write!(
+            out,
+            "ydcv_backend_latency_seconds_bucket{{le=\"+Inf\"}} {count}\n\
+             ydcv_backend_latency_seconds_sum {}\n\
+             ydcv_backend_latency_seconds_count {count}\n",
+            counters.latency_sum,
+            count = counters.latency_count,
+        );
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::default();
+        metrics.request("200 OK");
+        metrics.request("200 OK");
+        metrics.request("404 Not Found");
+        metrics.lookup(true);
+        metrics.lookup(false);
+        metrics.backend_latency(Duration::from_millis(300));
+        metrics.backend_latency(Duration::from_secs(60));
+
+        let text = metrics.render();
+        assert!(text.contains("\nydcv_lookups_total 2\n"));
+        assert!(text.contains("\nydcv_cache_hits_total 1\n"));
+        assert!(text.contains("\nydcv_requests_total{code=\"200\"} 2\n"));
+        assert!(text.contains("\nydcv_requests_total{code=\"404\"} 1\n"));
+        assert!(text.contains("\nydcv_backend_latency_seconds_bucket{le=\"0.25\"} 0\n"));
+        assert!(text.contains("\nydcv_backend_latency_seconds_bucket{le=\"0.5\"} 1\n"));
+        assert!(text.contains("\nydcv_backend_latency_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(text.contains("\nydcv_backend_latency_seconds_count 2\n"));
+    }
+}
@@ -0,0 +1,120 @@
+//! Directories of the files of `ydcv`, following the XDG base directories
+//! on every platform that sets them
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::db::Db;
+
+/// `ydcv` in `$<var>` if set, or else in the dir of the platform
+fn xdg_dir(var: &str, platform: impl FnOnce() -> Option<PathBuf>) -> Result<PathBuf> {
+    env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|x| x.is_absolute())
+        .or_else(platform)
+        .map(|x| x.join("ydcv"))
+        .with_context(|| format!("Failed to get ${var} path"))
+}
+
+/// Where the config file is
+pub fn config_dir() -> Result<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", dirs::config_dir)
+}
+
+/// Where files that can be fetched again are, like pronunciations
+pub fn cache_dir() -> Result<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME", dirs::cache_dir)
+}
+
+/// Where the database with the wordbook, the reviews and the lookups is
+pub fn data_dir() -> Result<PathBuf> {
+    xdg_dir("XDG_DATA_HOME", dirs::data_dir)
+}
+
+/// Where the history of the prompt is, in the local data dir on platforms
+/// without a state dir
+pub fn state_dir() -> Result<PathBuf> {
+    xdg_dir("XDG_STATE_HOME", || {
+        dirs::state_dir().or_else(dirs::data_local_dir)
+    })
+}
+
+/// Move the files kept in the cache dir by older versions to their dirs,
+/// leaving alone the ones already in place
+pub fn migrate() -> Result<()> {
+    let Some(old) = dirs::cache_dir().map(|x| x.join("ydcv")) else {
+        return Ok(());
+    };
+    if !old.is_dir() {
+        return Ok(());
+    }
+
+    let (from, to) = (old.join("ydcv.db"), Db::default_path()?);
+    if from != to && from.exists() && !to.exists() {
+        Db::move_file(&from, &to)?;
+    }
+    let moves = [
+        ("youdao-session", data_dir()?),
+        ("lists", data_dir()?),
+        ("history", state_dir()?),
+        ("audio", cache_dir()?),
+        ("cookies.json", cache_dir()?),
+    ];
+    for (name, dir) in moves {
+        let (from, to) = (old.join(name), dir.join(name));
+        if from == to || !from.exists() || to.exists() {
+            continue;
+        }
+        move_path(&from, &to).with_context(|| {
+            format!(
+                "Failed to move {} to {}, move it by hand",
+                from.display(),
+                to.display()
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Rename `from` to `to`, or copy it and remove it across filesystems
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to.parent().unwrap())?;
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            // copied next to `to` first, so a failed copy isn't taken for
+            // a moved file
+            let mut part = to.as_os_str().to_owned();
+            part.push(".part");
+            let part = PathBuf::from(part);
+            if part.is_dir() {
+                fs::remove_dir_all(&part)?;
+            }
+            copy_all(from, &part)?;
+            fs::rename(&part, to)?;
+            if from.is_dir() {
+                fs::remove_dir_all(from)
+            } else {
+                fs::remove_file(from)
+            }
+        }
+        x => x,
+    }
+}
+
+/// Copy the file or the dir `from` to `to`
+fn copy_all(from: &Path, to: &Path) -> io::Result<()> {
+    if !from.is_dir() {
+        return fs::copy(from, to).map(|_| ());
+    }
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        copy_all(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
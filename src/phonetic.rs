@@ -0,0 +1,165 @@
+//! normalization of phonetic notation
+
+use clap::ValueEnum;
+
+/// How phonetic notation is shown
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum PhoneticStyle {
+    /// As given by the dictionary
+    #[default]
+    Raw,
+    /// IPA without slashes or brackets, with unified stress and length marks
+    Clean,
+    /// A simplified English respelling, e.g. huh-LOH
+    Respell,
+}
+
+/// Look-alike characters used for IPA marks, and the marks they stand for
+const MARKS: [(char, char); 8] = [
+    ('\'', 'ˈ'),
+    ('’', 'ˈ'),
+    ('ˋ', 'ˈ'),
+    (',', 'ˌ'),
+    ('ˏ', 'ˌ'),
+    (':', 'ː'),
+    ('ɡ', 'g'),
+    ('ɹ', 'r'),
+];
+
+/// Respelling of IPA sounds, longer sounds first
+const RESPELLING: [(&str, &str); 33] = [
+    ("aɪə", "ire"),
+    ("aʊə", "our"),
+    ("tʃ", "ch"),
+    ("dʒ", "j"),
+    ("eɪ", "ay"),
+    ("aɪ", "eye"),
+    ("ɔɪ", "oy"),
+    ("aʊ", "ow"),
+    ("əʊ", "oh"),
+    ("oʊ", "oh"),
+    ("ɪə", "eer"),
+    ("eə", "air"),
+    ("ʊə", "oor"),
+    ("iː", "ee"),
+    ("uː", "oo"),
+    ("ɑː", "ah"),
+    ("ɔː", "aw"),
+    ("ɜː", "ur"),
+    ("ɝ", "ur"),
+    ("ɚ", "er"),
+    ("ʃ", "sh"),
+    ("ʒ", "zh"),
+    ("θ", "th"),
+    ("ð", "dh"),
+    ("ŋ", "ng"),
+    ("j", "y"),
+    ("æ", "a"),
+    ("ɒ", "o"),
+    ("ɑ", "ah"),
+    ("ə", "uh"),
+    ("ʌ", "u"),
+    ("ɪ", "i"),
+    ("ʊ", "uu"),
+];
+
+/// `phonetic` shown in `style`
+pub fn normalize(phonetic: &str, style: PhoneticStyle) -> String {
+    let one: fn(&str) -> String = match style {
+        PhoneticStyle::Raw => return phonetic.to_string(),
+        PhoneticStyle::Clean => clean,
+        PhoneticStyle::Respell => |x| respell(&clean(x)),
+    };
+
+    // a comma before a space separates alternatives rather than marking
+    // secondary stress
+    phonetic
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .split(", ")
+        .map(one)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// One of the alternatives of a phonetic as cleaned IPA
+fn clean(phonetic: &str) -> String {
+    phonetic
+        .trim_end_matches(',')
+        .trim_matches(|x| matches!(x, '/' | '[' | ']'))
+        .chars()
+        .map(|x| {
+            MARKS
+                .iter()
+                .find(|(from, _)| *from == x)
+                .map_or(x, |(_, to)| *to)
+        })
+        .collect()
+}
+
+/// Respell cleaned IPA, with syllables split at stress marks and the
+/// stressed one in capitals
+fn respell(ipa: &str) -> String {
+    let mut syllables: Vec<(bool, String)> = vec![(false, String::new())];
+    let mut rest = ipa;
+    while let Some(ch) = rest.chars().next() {
+        let syllable = &mut syllables.last_mut().unwrap().1;
+        if let Some((ipa, latin)) = RESPELLING.iter().find(|(x, _)| rest.starts_with(x)) {
+            syllable.push_str(latin);
+            rest = &rest[ipa.len()..];
+            continue;
+        }
+
+        match ch {
+            'ˈ' | 'ˌ' => syllables.push((ch == 'ˈ', String::new())),
+            'ː' | '(' | ')' => {}
+            _ => syllable.push(ch),
+        }
+        rest = &rest[ch.len_utf8()..];
+    }
+
+    syllables
+        .into_iter()
+        .filter(|(_, x)| !x.is_empty())
+        .map(|(stressed, x)| if stressed { x.to_uppercase() } else { x })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean() {
+        assert_eq!("həˈləʊ", normalize("/hə'ləʊ/", PhoneticStyle::Clean));
+        assert_eq!(
+            "ˌʌndəˈstænd",
+            normalize("[ˏʌndə’stænd]", PhoneticStyle::Clean)
+        );
+        assert_eq!("/hə'ləʊ/", normalize("/hə'ləʊ/", PhoneticStyle::Raw));
+        assert_eq!(
+            "ˈdeɪtə, ˈdɑːtə",
+            normalize("/ˈdeɪtə/, /ˈdɑːtə/", PhoneticStyle::Clean)
+        );
+        assert_eq!(
+            "ˌɪntəˈnæʃənl, ˌɪntərˈnæʃənl",
+            normalize("ˌɪntəˈnæʃənl,  ˌɪntərˈnæʃənl", PhoneticStyle::Clean)
+        );
+    }
+
+    #[test]
+    fn test_respell() {
+        assert_eq!("huh-LOH", normalize("həˈləʊ", PhoneticStyle::Respell));
+        assert_eq!("KOMENT", normalize("/ˈkɒment/", PhoneticStyle::Respell));
+        assert_eq!(
+            "unduh-STAND",
+            normalize("ˌʌndəˈstænd", PhoneticStyle::Respell)
+        );
+        assert_eq!(
+            "KOMENT, KAHMENT",
+            normalize("ˈkɒment, ˈkɑːment", PhoneticStyle::Respell)
+        );
+    }
+}
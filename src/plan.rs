@@ -0,0 +1,179 @@
+//! study plans feeding word lists, such as those of exams, into the review
+//! system
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, bail};
+use chrono::Local;
+use log::warn;
+use reqwest::blocking::Client;
+
+use crate::db::Db;
+use crate::http::SendRetrying;
+use crate::paths;
+use crate::ydresponse::YdResponse;
+
+/// Review interval in days from which a word counts as mastered
+const MASTERED_INTERVAL: u32 = 21;
+
+/// Directory where word lists named after their plan are looked up
+pub fn lists_dir() -> Result<PathBuf> {
+    Ok(paths::data_dir()?.join("lists"))
+}
+
+/// Words of a list, one per line, anything after a tab is ignored
+fn parse_list(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|x| x.split('\t').next())
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+        .map(|x| x.to_string())
+        .collect()
+}
+
+/// Read the word list of plan `name` from `source`, which is a file or an
+/// http(s) URL, or from `<lists dir>/<name>.txt` if not given
+fn load_list(client: &Client, name: &str, source: Option<&str>) -> Result<Vec<String>> {
+    let text = match source {
+        Some(url) if url.starts_with("http://") || url.starts_with("https://") => client
+            .get(url)
+            .send_retrying()
+            .and_then(|x| x.error_for_status())
+            .and_then(|x| x.text())
+            .with_context(|| format!("Failed to download word list from {url}"))?,
+        Some(path) => {
+            fs::read_to_string(path).with_context(|| format!("Failed to read word list {path}"))?
+        }
+        None => {
+            let path = lists_dir()?.join(format!("{name}.txt"));
+            if !path.is_file() {
+                bail!(
+                    "No word list for {name}, pass one with --file or put it at {}",
+                    path.display()
+                );
+            }
+            fs::read_to_string(&path)?
+        }
+    };
+
+    let words = parse_list(&text);
+    if words.is_empty() {
+        bail!("Word list for {name} is empty");
+    }
+
+    Ok(words)
+}
+
+pub fn start(
+    db: &Db,
+    client: &Client,
+    name: &str,
+    source: Option<&str>,
+    per_day: u32,
+) -> Result<()> {
+    if db.plans()?.iter().any(|x| x.name == name) {
+        db.plan_start(name, &[], per_day)?;
+        println!("Plan {name} now introduces {per_day} word(s) per day");
+        return Ok(());
+    }
+
+    let words = load_list(client, name, source)?;
+    db.plan_start(name, &words, per_day)?;
+    println!(
+        "Started plan {name} with {} word(s), {per_day} per day",
+        words.len()
+    );
+
+    Ok(())
+}
+
+/// Add today's batch of every plan to the wordbook, tagged with the plan name.
+///
+/// Plans already fed today are skipped. Returns the number of added words.
+pub fn feed(db: &Db, lookup: &dyn Fn(&str) -> Result<YdResponse>) -> Result<usize> {
+    let today = Local::now().date_naive();
+    let mut added = 0;
+
+    for plan in db.plans()? {
+        if plan.last_fed == Some(today) || plan.position >= plan.total {
+            continue;
+        }
+
+        let mut position = plan.position;
+        for word in db.plan_words(&plan.name, plan.position, plan.per_day)? {
+            let resp = match lookup(&word) {
+                Ok(resp) => resp,
+                Err(e) => {
+                    warn!("Failed to look up {word} for plan {}: {e}", plan.name);
+                    break;
+                }
+            };
+
+            if resp.has_result() {
+                if db.wordbook_add(&resp)? {
+                    added += 1;
+                }
+                db.wordbook_tag(resp.query(), std::slice::from_ref(&plan.name))?;
+            }
+            position += 1;
+        }
+
+        if position > plan.position {
+            db.plan_advance(&plan.name, position, today)?;
+        }
+    }
+
+    Ok(added)
+}
+
+pub fn status(db: &Db) -> Result<()> {
+    let plans = db.plans()?;
+    if plans.is_empty() {
+        println!("No study plan, start one with `ydcv plan start <name> --file <word list>`");
+        println!(
+            "No word list comes with ydcv, put yours in {}",
+            lists_dir()?.display()
+        );
+    }
+
+    for plan in plans {
+        let (reviewed, mastered) = db.plan_progress(&plan.name, MASTERED_INTERVAL)?;
+        println!(
+            "{}: {}/{} word(s) introduced ({} per day), {} reviewed, {} mastered",
+            plan.name, plan.position, plan.total, plan.per_day, reviewed, mastered
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fake;
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(
+            vec!["abandon", "give up", "ability"],
+            parse_list("# CET-6\nabandon\tv. 放弃\n\n  give up \nability\n")
+        );
+    }
+
+    #[test]
+    fn test_feed() {
+        let db = Db::open_in_memory().unwrap();
+        let words = ["a", "b", "c"].map(String::from);
+        db.plan_start("cet6", &words, 2).unwrap();
+
+        let lookup = |w: &str| Ok(fake(w, "x"));
+        assert_eq!(2, feed(&db, &lookup).unwrap());
+        // already fed today
+        assert_eq!(0, feed(&db, &lookup).unwrap());
+
+        let plan = &db.plans().unwrap()[0];
+        assert_eq!((2, 3), (plan.position, plan.total));
+        assert_eq!(vec!["cet6"], db.wordbook().unwrap()[0].tags);
+    }
+}
@@ -0,0 +1,21 @@
+//! Python bindings, built into the `ydcv_saki` module by maturin
+
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+
+use crate::embed;
+
+/// Look up `word` and return the response as a dict, translating it if it's
+/// a sentence
+#[pyfunction]
+fn lookup<'py>(py: Python<'py>, word: &str) -> PyResult<Bound<'py, PyAny>> {
+    let json = py
+        .allow_threads(|| embed::lookup(word).and_then(|x| Ok(serde_json::to_string(&x)?)))
+        .map_err(|e| PyRuntimeError::new_err(format!("{e:#}")))?;
+    py.import("json")?.call_method1("loads", (json,))
+}
+
+#[pymodule]
+fn ydcv_saki(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(lookup, m)?)
+}
@@ -0,0 +1,105 @@
+//! Token buckets limiting how often each client of the servers may look up,
+//! so that a misbehaving script can't get the shared IP blocked by YD
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Clients remembered at most, forgetting the ones with full buckets and
+/// then the least recent ones
+const MAX_CLIENTS: usize = 1024;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+pub struct RateLimiter<K> {
+    /// Tokens added per second
+    rate: f64,
+    /// Most tokens a bucket holds, i.e. requests allowed at once
+    burst: f64,
+    buckets: Mutex<HashMap<K, Bucket>>,
+}
+
+impl<K: Hash + Eq + Clone> RateLimiter<K> {
+    /// Allow each client `per_minute` requests a minute on average, and
+    /// `burst` at once
+    pub fn new(per_minute: u32, burst: u32) -> Self {
+        RateLimiter {
+            rate: f64::from(per_minute) / 60.0,
+            burst: f64::from(burst.max(1)),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether `client` may send a request now, taking a token if so
+    pub fn allow(&self, client: K) -> bool {
+        self.allow_at(client, Instant::now())
+    }
+
+    fn allow_at(&self, client: K, now: Instant) -> bool {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= MAX_CLIENTS && !buckets.contains_key(&client) {
+            buckets.retain(|_, x| self.refilled(x, now) < self.burst);
+            if buckets.len() >= MAX_CLIENTS
+                && let Some(oldest) = buckets
+                    .iter()
+                    .min_by_key(|(_, x)| x.updated)
+                    .map(|(k, _)| k.clone())
+            {
+                buckets.remove(&oldest);
+            }
+        }
+
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        bucket.tokens = self.refilled(bucket, now);
+        bucket.updated = now;
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+
+    /// Tokens of `bucket` at `now`
+    fn refilled(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        (bucket.tokens + elapsed * self.rate).min(self.burst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_allow() {
+        let limiter = RateLimiter::new(60, 2);
+        let now = Instant::now();
+        assert!(limiter.allow_at("a", now));
+        assert!(limiter.allow_at("a", now));
+        assert!(!limiter.allow_at("a", now));
+        assert!(limiter.allow_at("b", now));
+        assert!(!limiter.allow_at("a", now + Duration::from_millis(500)));
+        assert!(limiter.allow_at("a", now + Duration::from_secs(1)));
+        assert!(!limiter.allow_at("a", now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_max_clients() {
+        let limiter = RateLimiter::new(1, 1);
+        let now = Instant::now();
+        for i in 0..MAX_CLIENTS * 2 {
+            assert!(limiter.allow_at(i, now));
+        }
+        assert_eq!(MAX_CLIENTS, limiter.buckets.lock().unwrap().len());
+        assert!(!limiter.allow_at(MAX_CLIENTS * 2 - 1, now));
+    }
+}
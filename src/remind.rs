@@ -0,0 +1,89 @@
+//! desktop reminders for due reviews
+
+use std::thread::sleep;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Local;
+
+use crate::db::Db;
+use crate::formatters::Formatter;
+
+/// Number of words to review today, including today's batches of study
+/// plans that haven't been fed into the wordbook yet
+pub fn due_count(db: &Db) -> Result<usize> {
+    let today = Local::now().date_naive();
+    let due = db
+        .srs_cards(today)?
+        .iter()
+        .filter(|(_, card)| card.is_due(today))
+        .count();
+
+    let planned = db
+        .plans()?
+        .iter()
+        .filter(|x| x.last_fed != Some(today))
+        .map(|x| x.per_day.min(x.total.saturating_sub(x.position)) as usize)
+        .sum::<usize>();
+
+    Ok(due + planned)
+}
+
+/// Whether a reminder was sent today, by `remind` run by a timer or by the
+/// daemon
+pub fn reminded_today(db: &Db) -> Result<bool> {
+    Ok(db.last_reminded()? == Some(Local::now().date_naive()))
+}
+
+/// Send a reminder if anything is due, returns whether it was sent
+pub fn remind(db: &Db, fmt: &mut dyn Formatter) -> Result<bool> {
+    let count = due_count(db)?;
+    if count == 0 {
+        return Ok(false);
+    }
+    db.set_last_reminded(Local::now().date_naive())?;
+
+    let body = format!(
+        "{}\n{}",
+        fmt.default(&format!("{count} word(s) due for review")),
+        fmt.cyan("Run `ydcv review` to start")
+    );
+    fmt.print("ydcv review", &body);
+
+    Ok(true)
+}
+
+/// Check every `interval` and remind at most once a day
+pub fn daemon(db: &Db, fmt: &mut dyn Formatter, interval: Duration) -> Result<()> {
+    loop {
+        if !reminded_today(db)? {
+            remind(db, fmt)?;
+        }
+        sleep(interval);
+    }
+}
+
+/// systemd user units running `ydcv remind` every `interval` minutes
+pub fn systemd_units(exe: &str, interval: u64) -> String {
+    format!(
+        "# ~/.config/systemd/user/ydcv-remind.service
+[Unit]
+Description=Remind to review words due in ydcv
+
+[Service]
+Type=oneshot
+ExecStart={exe} remind
+
+# ~/.config/systemd/user/ydcv-remind.timer
+[Unit]
+Description=Periodically remind to review words due in ydcv
+
+[Timer]
+OnStartupSec=5min
+OnUnitActiveSec={interval}min
+
+[Install]
+WantedBy=timers.target
+"
+    )
+}
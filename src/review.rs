@@ -0,0 +1,413 @@
+//! SM-2 spaced repetition over the wordbook
+
+use std::ops::Range;
+
+use anyhow::Result;
+use chrono::{Days, Local, NaiveDate};
+use clap::ValueEnum;
+use rustyline::DefaultEditor;
+
+use crate::db::{Db, WordbookEntry};
+use crate::formatters::Formatter;
+
+/// Scheduling state of a single wordbook entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Card {
+    pub word: String,
+    pub repetitions: u32,
+    /// Days until the next review after the last successful one
+    pub interval: u32,
+    pub ease: f64,
+    pub due: NaiveDate,
+}
+
+impl Card {
+    /// A card that has never been reviewed, due immediately
+    pub fn new(word: &str, today: NaiveDate) -> Self {
+        Card {
+            word: word.to_string(),
+            repetitions: 0,
+            interval: 0,
+            ease: 2.5,
+            due: today,
+        }
+    }
+
+    pub fn is_due(&self, today: NaiveDate) -> bool {
+        self.due <= today
+    }
+
+    /// Reschedule the card with a recall quality from 0 (blackout) to 5 (perfect)
+    pub fn grade(&mut self, quality: u8, today: NaiveDate) {
+        let q = f64::from(quality.min(5));
+
+        if quality >= 3 {
+            self.interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (f64::from(self.interval) * self.ease).round() as u32,
+            };
+            self.repetitions += 1;
+        } else {
+            self.repetitions = 0;
+            self.interval = 1;
+        }
+
+        self.ease = (self.ease + 0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02)).max(1.3);
+        self.due = today + Days::new(self.interval.into());
+    }
+}
+
+/// How due words are asked during a review
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum QuizMode {
+    /// Show the word, reveal the definition and grade yourself
+    #[default]
+    Flashcard,
+    /// Pick the word matching a definition among several choices
+    Choice,
+    /// Type the word from its Chinese meaning
+    Typing,
+    /// Fill the word into a phrase containing it
+    Cloze,
+    /// Type the word from its pronunciation
+    Listening,
+}
+
+/// Number of options shown in the multiple-choice mode
+const CHOICES: usize = 4;
+
+/// Quiz the due words in the terminal and record the grades, `speak` plays
+/// the pronunciation of a word for the listening mode
+pub fn run(
+    db: &Db,
+    fmt: &mut dyn Formatter,
+    limit: usize,
+    mode: QuizMode,
+    speak: &dyn Fn(&str) -> Result<()>,
+) -> Result<()> {
+    let today = Local::now().date_naive();
+    let cards = db.srs_cards(today)?;
+    let due = cards
+        .iter()
+        .filter(|(_, card)| card.is_due(today))
+        .take(limit)
+        .collect::<Vec<_>>();
+
+    if due.is_empty() {
+        println!("No words due for review today");
+        return Ok(());
+    }
+
+    let total = due.len();
+    let mut reader = DefaultEditor::new()?;
+
+    for (i, (entry, card)) in due.into_iter().enumerate() {
+        print!("[{}/{}] ", i + 1, total);
+
+        let quality = match mode {
+            QuizMode::Flashcard => flashcard(&mut reader, entry, fmt),
+            QuizMode::Choice => choice(&mut reader, entry, &cards, fmt),
+            QuizMode::Typing => typing(&mut reader, entry, fmt),
+            QuizMode::Cloze => match cloze_phrase(entry) {
+                Some((phrase, meaning)) => {
+                    println!("{phrase}");
+                    println!("  {}", fmt.purple(&meaning));
+                    type_word(&mut reader, entry, fmt)
+                }
+                None => typing(&mut reader, entry, fmt),
+            },
+            QuizMode::Listening => listening(&mut reader, entry, speak, fmt),
+        };
+        let Some(quality) = quality else {
+            break;
+        };
+
+        let mut card = card.clone();
+        card.grade(quality, today);
+        db.srs_save(&card)?;
+        println!();
+    }
+
+    Ok(())
+}
+
+fn reveal(entry: &WordbookEntry, fmt: &mut dyn Formatter) {
+    let exp = entry.snapshot.explain(fmt);
+    fmt.print(&entry.word, &exp);
+}
+
+/// Short Chinese meaning of an entry, used as the question
+fn meaning(entry: &WordbookEntry) -> String {
+    let resp = &entry.snapshot;
+    if resp.explains().is_empty() {
+        resp.translation().join("; ")
+    } else {
+        resp.explains().join("\n  ")
+    }
+}
+
+fn flashcard(
+    reader: &mut DefaultEditor,
+    entry: &WordbookEntry,
+    fmt: &mut dyn Formatter,
+) -> Option<u8> {
+    println!("{}", fmt.underline(&entry.word));
+    reader.readline("(press Enter to reveal) ").ok()?;
+    reveal(entry, fmt);
+    ask_grade(reader)
+}
+
+/// Ask for a grade until a valid one is given, `None` if the user quits
+fn ask_grade(reader: &mut DefaultEditor) -> Option<u8> {
+    loop {
+        let line = reader
+            .readline("Grade (0 forgot .. 5 perfect, q to quit): ")
+            .ok()?;
+        match line.trim() {
+            "q" => return None,
+            x => match x.parse() {
+                Ok(q @ 0..=5) => return Some(q),
+                _ => println!("Please enter a number from 0 to 5"),
+            },
+        }
+    }
+}
+
+fn choice(
+    reader: &mut DefaultEditor,
+    entry: &WordbookEntry,
+    cards: &[(WordbookEntry, Card)],
+    fmt: &mut dyn Formatter,
+) -> Option<u8> {
+    let mut others = cards
+        .iter()
+        .map(|(x, _)| x.word.as_str())
+        .filter(|x| *x != entry.word)
+        .collect::<Vec<_>>();
+    fastrand::shuffle(&mut others);
+
+    let mut options = others.into_iter().take(CHOICES - 1).collect::<Vec<_>>();
+    options.push(&entry.word);
+    fastrand::shuffle(&mut options);
+
+    println!("{}", fmt.cyan("Which word means:"));
+    println!("  {}", meaning(entry));
+    for (i, option) in options.iter().enumerate() {
+        println!("  {}) {}", i + 1, option);
+    }
+
+    let answer = loop {
+        let line = reader.readline("Answer (q to quit): ").ok()?;
+        match line.trim() {
+            "q" => return None,
+            x => match x.parse::<usize>() {
+                Ok(n @ 1..) if n <= options.len() => break options[n - 1],
+                _ => println!("Please enter a number from 1 to {}", options.len()),
+            },
+        }
+    };
+
+    Some(judge(answer == entry.word, entry, fmt))
+}
+
+fn typing(
+    reader: &mut DefaultEditor,
+    entry: &WordbookEntry,
+    fmt: &mut dyn Formatter,
+) -> Option<u8> {
+    println!("{}", fmt.cyan("Type the word meaning:"));
+    println!("  {}", meaning(entry));
+    type_word(reader, entry, fmt)
+}
+
+/// Read the typed word and grade it, a single typo still counts as hard recall
+fn type_word(
+    reader: &mut DefaultEditor,
+    entry: &WordbookEntry,
+    fmt: &mut dyn Formatter,
+) -> Option<u8> {
+    let line = reader.readline("Word (:q to quit): ").ok()?;
+    match line.trim() {
+        ":q" => None,
+        answer => Some(grade_typed(answer, entry, fmt)),
+    }
+}
+
+fn grade_typed(answer: &str, entry: &WordbookEntry, fmt: &mut dyn Formatter) -> u8 {
+    let expected = entry.word.to_lowercase();
+    let answer = answer.to_lowercase();
+    if answer == expected {
+        println!("{}", fmt.yellow("Correct!"));
+        5
+    } else if edit_distance(&answer, &expected) == 1 {
+        println!("{} {}", fmt.yellow("Almost:"), fmt.underline(&entry.word));
+        3
+    } else {
+        judge(false, entry, fmt)
+    }
+}
+
+/// Play the word and have it typed, showing the meaning instead if the audio
+/// can't be played
+fn listening(
+    reader: &mut DefaultEditor,
+    entry: &WordbookEntry,
+    speak: &dyn Fn(&str) -> Result<()>,
+    fmt: &mut dyn Formatter,
+) -> Option<u8> {
+    println!("{}", fmt.cyan("Type the word you hear:"));
+    if let Err(e) = speak(&entry.word) {
+        println!("Failed to play pronunciation: {e}");
+        println!("{}", fmt.cyan("Type the word meaning:"));
+        println!("  {}", meaning(entry));
+    }
+
+    loop {
+        let line = reader.readline("Word (:r to replay, :q to quit): ").ok()?;
+        match line.trim() {
+            ":q" => return None,
+            ":r" => {
+                if let Err(e) = speak(&entry.word) {
+                    println!("Failed to play pronunciation: {e}");
+                }
+            }
+            answer => return Some(grade_typed(answer, entry, fmt)),
+        }
+    }
+}
+
+fn judge(correct: bool, entry: &WordbookEntry, fmt: &mut dyn Formatter) -> u8 {
+    if correct {
+        println!("{}", fmt.yellow("Correct!"));
+        4
+    } else {
+        println!("{}", fmt.red("Wrong, the answer is:"));
+        reveal(entry, fmt);
+        1
+    }
+}
+
+/// An example sentence or web phrase containing the word with the word
+/// blanked out, and its meaning
+fn cloze_phrase(entry: &WordbookEntry) -> Option<(String, String)> {
+    let examples = entry
+        .snapshot
+        .examples()
+        .iter()
+        .map(|x| (x.sentence.clone(), x.translation.clone()));
+    let web = entry
+        .snapshot
+        .web()
+        .iter()
+        .map(|x| (x.key.clone(), x.value.join("；")));
+
+    examples.chain(web).find_map(|(text, meaning)| {
+        let found = find_ignore_case(&text, &entry.word)?;
+        let blank = "_".repeat(text[found.clone()].chars().count());
+        let phrase = format!("{}{}{}", &text[..found.start], blank, &text[found.end..]);
+        (phrase != blank).then_some((phrase, meaning))
+    })
+}
+
+/// Where `word` first is in `text`, ignoring case, as bytes of `text`,
+/// whose lowercase may be of another length
+fn find_ignore_case(text: &str, word: &str) -> Option<Range<usize>> {
+    let word = word
+        .chars()
+        .flat_map(char::to_lowercase)
+        .collect::<Vec<_>>();
+    if word.is_empty() {
+        return None;
+    }
+
+    text.char_indices().find_map(|(start, _)| {
+        let mut lower = text[start..].char_indices().flat_map(|(i, c)| {
+            let end = start + i + c.len_utf8();
+            c.to_lowercase().map(move |x| (x, end))
+        });
+        let mut end = start;
+        for c in &word {
+            let (x, next) = lower.next()?;
+            if x != *c {
+                return None;
+            }
+            end = next;
+        }
+        Some(start..end)
+    })
+}
+
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            curr.push((prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1));
+        }
+        prev = curr;
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_grade() {
+        let today = date("2024-01-01");
+        let mut card = Card::new("hello", today);
+
+        card.grade(4, today);
+        assert_eq!(
+            (1, 1, date("2024-01-02")),
+            (card.repetitions, card.interval, card.due)
+        );
+        card.grade(4, today);
+        assert_eq!((2, 6), (card.repetitions, card.interval));
+        card.grade(5, today);
+        assert_eq!((3, 15), (card.repetitions, card.interval));
+        assert!((card.ease - 2.6).abs() < 1e-9);
+
+        card.grade(1, today);
+        assert_eq!((0, 1), (card.repetitions, card.interval));
+        assert!((card.ease - 2.06).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_min_ease() {
+        let today = date("2024-01-01");
+        let mut card = Card::new("hello", today);
+        for _ in 0..10 {
+            card.grade(0, today);
+        }
+        assert!((card.ease - 1.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_find_ignore_case() {
+        assert_eq!(Some(4..9), find_ignore_case("Say HELLO", "hello"));
+        // 'İ' is longer lowercased than in the text
+        let text = "İİ Straße";
+        let found = find_ignore_case(text, "straße").unwrap();
+        assert_eq!("Straße", &text[found]);
+        assert_eq!(None, find_ignore_case("hell", "hello"));
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(0, edit_distance("hello", "hello"));
+        assert_eq!(1, edit_distance("helo", "hello"));
+        assert_eq!(1, edit_distance("hallo", "hello"));
+        assert_eq!(5, edit_distance("", "hello"));
+    }
+}
@@ -0,0 +1,323 @@
+//! JSON-RPC 2.0 over stdin and stdout, a message per line, so that editor
+//! plugins can keep one `ydcv` running instead of parsing its output
+
+use std::io::{BufRead, Write};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::db::Db;
+use crate::formatters::PlainFormatter;
+use crate::lang::{self, Direction, Lang};
+use crate::lookup_maybe_cached;
+use crate::ratelimit::RateLimiter;
+use crate::ydclient::{Query, YdClient};
+use crate::ydresponse::{ExplainOptions, YdResponse};
+
+/// Error codes of JSON-RPC 2.0
+pub const PARSE_ERROR: i32 = -32700;
+pub const METHOD_NOT_FOUND: i32 = -32601;
+pub const INVALID_PARAMS: i32 = -32602;
+
+/// Error code of failed lookups, in the range left to servers
+const LOOKUP_FAILED: i32 = -32000;
+
+/// Error code of clients turned away for looking up too often
+pub const RATE_LIMITED: i32 = -32001;
+
+#[derive(Deserialize)]
+pub struct Request {
+    /// Absent for notifications, which get no response
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Serialize)]
+pub struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+impl Response {
+    pub fn new(id: Value, result: Result<Value, RpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Response {
+            jsonrpc: "2.0",
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct RpcError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl RpcError {
+    fn lookup_failed(e: impl Into<anyhow::Error>) -> Self {
+        RpcError {
+            code: LOOKUP_FAILED,
+            message: format!("{:#}", e.into()),
+        }
+    }
+}
+
+/// What the methods work with
+struct Server<'a, C> {
+    client: &'a C,
+    db: Option<&'a Db>,
+    explain: &'a ExplainOptions,
+    /// Limits of the lookups, shared by all clients, none if unlimited
+    limiter: Option<&'a RateLimiter<()>>,
+}
+
+impl<C: YdClient> Server<'_, C> {
+    fn call(&self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        match method {
+            "lookup" => {
+                let query = query_param(params)?;
+                let word = query.text;
+                let resp = self.lookup(&query)?;
+                // left to clients recording lookups themselves
+                let record = params.get("record").and_then(Value::as_bool);
+                if let Some(db) = self.db
+                    && record != Some(false)
+                {
+                    db.record_lookup(word).map_err(RpcError::lookup_failed)?;
+                }
+                let text = resp.explain_with(&PlainFormatter::new(false), self.explain);
+                Ok(json!({ "response": resp, "text": text }))
+            }
+            "suggest" => Ok(json!(self.lookup(&query_param(params)?)?.suggestions())),
+            "add-to-wordbook" => {
+                let db = self.db.ok_or_else(|| {
+                    RpcError::lookup_failed(anyhow::anyhow!(
+                        "Wordbook is unavailable without the ydcv database"
+                    ))
+                })?;
+                let resp = self.lookup(&query_param(params)?)?;
+                if !resp.has_result() {
+                    return Err(RpcError::lookup_failed(anyhow::anyhow!(
+                        "No result for {}",
+                        resp.query()
+                    )));
+                }
+                // whether it wasn't in the wordbook yet
+                Ok(json!(
+                    db.wordbook_add(&resp).map_err(RpcError::lookup_failed)?
+                ))
+            }
+            _ => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("No method {method}"),
+            }),
+        }
+    }
+
+    /// Look up `query`, translating sentences, from the cache if there's a
+    /// database and it's a word in the detected direction
+    fn lookup(&self, query: &Query) -> Result<YdResponse, RpcError> {
+        if let Some(limiter) = self.limiter
+            && !limiter.allow(())
+        {
+            return Err(RpcError {
+                code: RATE_LIMITED,
+                message: "Looking up too often".to_string(),
+            });
+        }
+        if query.sentence {
+            self.client.translate(query.text, query.dir)
+        } else if query.dir != Direction::detect(query.text) {
+            self.client.lookup_word_in(query.text, query.dir)
+        } else {
+            return lookup_maybe_cached(self.client, self.db, query.text)
+                .map_err(RpcError::lookup_failed);
+        }
+        .map_err(RpcError::lookup_failed)
+    }
+}
+
+/// The word of `{"word": ...}` or `[word]`
+fn word_param(params: &Value) -> Result<&str, RpcError> {
+    params
+        .get("word")
+        .or_else(|| params.get(0))
+        .and_then(Value::as_str)
+        .ok_or_else(|| RpcError {
+            code: INVALID_PARAMS,
+            message: "Missing word".to_string(),
+        })
+}
+
+/// The word of the params, with the languages of `from` and `to` and whether
+/// it's a sentence to translate, detected unless given
+fn query_param(params: &Value) -> Result<Query<'_>, RpcError> {
+    let invalid = |message: String| RpcError {
+        code: INVALID_PARAMS,
+        message,
+    };
+    let lang = |key: &str| {
+        params
+            .get(key)
+            .map(|x| Lang::deserialize(x).map_err(|e| invalid(format!("Bad {key}: {e}"))))
+            .transpose()
+    };
+
+    let text = word_param(params)?;
+    Ok(Query {
+        text,
+        dir: Direction::resolve(text, lang("from")?, lang("to")?)
+            .map_err(|e| invalid(e.to_string()))?,
+        sentence: params
+            .get("sentence")
+            .and_then(Value::as_bool)
+            .unwrap_or_else(|| lang::is_sentence(text)),
+    })
+}
+
+/// Answer the requests read from `input` on `output`, until `input` ends,
+/// turning lookups away when over `limiter`
+pub fn serve(
+    client: &impl YdClient,
+    db: Option<&Db>,
+    explain: &ExplainOptions,
+    limiter: Option<&RateLimiter<()>>,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> Result<()> {
+    let server = Server {
+        client,
+        db,
+        explain,
+        limiter,
+    };
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                let result = server.call(&request.method, &request.params);
+                let Some(id) = request.id else {
+                    continue;
+                };
+                Response::new(id, result)
+            }
+            Err(e) => Response::new(
+                Value::Null,
+                Err(RpcError {
+                    code: PARSE_ERROR,
+                    message: e.to_string(),
+                }),
+            ),
+        };
+        serde_json::to_writer(&mut output, &response)?;
+        writeln!(output)?;
+        output.flush()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FakeClient;
+
+    fn serve_lines(db: &Db, limiter: Option<&RateLimiter<()>>, input: &str) -> Vec<Value> {
+        let mut output = Vec::new();
+        serve(
+            &FakeClient,
+            Some(db),
+            &ExplainOptions::default(),
+            limiter,
+            input.as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|x| serde_json::from_str(x).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_serve() {
+        let db = Db::open_in_memory().unwrap();
+        let responses = serve_lines(
+            &db,
+            None,
+            r#"{"jsonrpc":"2.0","id":1,"method":"lookup","params":{"word":"hello"}}
+{"jsonrpc":"2.0","method":"add-to-wordbook","params":["hello"]}
+{"jsonrpc":"2.0","id":2,"method":"add-to-wordbook","params":["hello"]}
+{"jsonrpc":"2.0","id":3,"method":"suggest","params":{}}
+{"jsonrpc":"2.0","id":4,"method":"define"}
+not json"#,
+        );
+
+        assert_eq!(5, responses.len());
+        assert_eq!(1, responses[0]["id"]);
+        assert_eq!("hello", responses[0]["result"]["response"]["query"]);
+        assert!(
+            responses[0]["result"]["text"]
+                .as_str()
+                .unwrap()
+                .contains("译")
+        );
+        assert_eq!(Some("hello".to_string()), db.last_lookup().unwrap());
+        // added by the notification before
+        assert_eq!(false, responses[1]["result"]);
+        assert_eq!(INVALID_PARAMS, responses[2]["error"]["code"]);
+        assert_eq!(METHOD_NOT_FOUND, responses[3]["error"]["code"]);
+        assert_eq!(PARSE_ERROR, responses[4]["error"]["code"]);
+        assert_eq!(Value::Null, responses[4]["id"]);
+    }
+
+    #[test]
+    fn test_query_param() {
+        let params = json!({ "word": "你好", "to": "ja" });
+        let query = query_param(&params).unwrap();
+        assert_eq!((Lang::Zh, Lang::Ja), (query.dir.from, query.dir.to));
+        assert!(!query.sentence);
+        let params = json!(["How are you doing today?"]);
+        assert!(query_param(&params).unwrap().sentence);
+        let params = json!({ "word": "hello world", "sentence": true });
+        assert!(query_param(&params).unwrap().sentence);
+        for params in [
+            json!({ "word": "x", "to": "xx" }),
+            json!({ "word": "x", "from": "ja", "to": "fr" }),
+        ] {
+            assert_eq!(INVALID_PARAMS, query_param(&params).err().unwrap().code);
+        }
+    }
+
+    #[test]
+    fn test_serve_limited() {
+        let db = Db::open_in_memory().unwrap();
+        // one connection can't look up more than its share
+        let responses = serve_lines(
+            &db,
+            Some(&RateLimiter::new(1, 1)),
+            r#"{"jsonrpc":"2.0","id":1,"method":"lookup","params":["a"]}
+{"jsonrpc":"2.0","id":2,"method":"lookup","params":["b"]}"#,
+        );
+        assert_eq!("a", responses[0]["result"]["response"]["query"]);
+        assert_eq!(RATE_LIMITED, responses[1]["error"]["code"]);
+    }
+}
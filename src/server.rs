@@ -0,0 +1,447 @@
+//! A small HTTP server answering `GET /lookup?word=...` with JSON or an HTML
+//! page, so that browser extensions and phones on the LAN can share one
+//! `ydcv` and its cache, and `GET /metrics` for Prometheus. Over HTTPS and
+//! with a bearer token it can be exposed beyond localhost
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+#[cfg(feature = "rustls")]
+use std::path::Path;
+#[cfg(feature = "rustls")]
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "rustls")]
+use anyhow::Context;
+use anyhow::Result;
+use log::warn;
+#[cfg(feature = "rustls")]
+use rustls::pki_types::pem::PemObject;
+#[cfg(feature = "rustls")]
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+#[cfg(feature = "rustls")]
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use serde_json::{Value, json};
+use url::Url;
+
+use crate::activation::{self, IdleExit};
+use crate::db::Db;
+use crate::formatters::{GoldenDictFormatter, PlainFormatter};
+use crate::lookup_maybe_cached;
+use crate::metrics::Metrics;
+use crate::ratelimit::RateLimiter;
+use crate::ydclient::YdClient;
+use crate::ydresponse::ExplainOptions;
+
+/// How long a client may take to send its request, and each write of the
+/// reply
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest request line and headers, together
+const MAX_HEAD: u64 = 8192;
+
+struct Reply {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Reply {
+    fn json(status: &'static str, value: Value) -> Self {
+        Reply {
+            status,
+            content_type: "application/json",
+            body: value.to_string(),
+        }
+    }
+
+    fn error(status: &'static str, message: impl Into<String>) -> Self {
+        Self::json(status, json!({ "error": message.into() }))
+    }
+}
+
+/// Who may look up, and how they connect
+#[derive(Default)]
+pub struct Access {
+    /// Limits of lookups by client IP, none if unlimited
+    pub limiter: Option<RateLimiter<IpAddr>>,
+    /// Token requests must send as `Authorization: Bearer`, none if anyone
+    /// may look up
+    pub token: Option<String>,
+    /// Certificate and key to serve HTTPS with, plain HTTP if none
+    #[cfg(feature = "rustls")]
+    pub tls: Option<Arc<ServerConfig>>,
+}
+
+/// Config serving HTTPS with the PEM certificate chain `cert` and the PEM
+/// private key `key`
+#[cfg(feature = "rustls")]
+pub fn tls_config(cert: &Path, key: &Path) -> Result<Arc<ServerConfig>> {
+    let certs = CertificateDer::pem_file_iter(cert)
+        .and_then(|x| x.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read certificate {}", cert.display()))?;
+    let key = PrivateKeyDer::from_pem_file(key)
+        .with_context(|| format!("Failed to read private key {}", key.display()))?;
+    let config =
+        ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .context("Invalid certificate or private key")?;
+
+    Ok(Arc::new(config))
+}
+
+/// A connection whose reads all have to be done by `deadline`, so that a
+/// client trickling its request in doesn't hold it open
+struct Deadline {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for Deadline {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// Whether `a` and `b` are equal, in a time not depending on where they differ
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// What requests are answered with
+struct Server<'a, C> {
+    client: &'a C,
+    db: Option<&'a Db>,
+    explain: &'a ExplainOptions,
+    metrics: &'a Metrics,
+    access: &'a Access,
+}
+
+impl<C: YdClient> Server<'_, C> {
+    /// Reply to `GET target` of `client`
+    fn get(&self, target: &str, client: IpAddr) -> Reply {
+        let Ok(url) = Url::parse("http://localhost/").and_then(|x| x.join(target)) else {
+            return Reply::error("400 Bad Request", format!("Bad target {target}"));
+        };
+        match url.path() {
+            "/lookup" => {}
+            "/metrics" => {
+                return Reply {
+                    status: "200 OK",
+                    content_type: "text/plain; version=0.0.4",
+                    body: self.metrics.render(),
+                };
+            }
+            path => return Reply::error("404 Not Found", format!("No path {path}")),
+        }
+
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.into_owned())
+        };
+        let Some(word) = param("word").filter(|x| !x.trim().is_empty()) else {
+            return Reply::error("400 Bad Request", "Missing word");
+        };
+        if let Some(limiter) = &self.access.limiter
+            && !limiter.allow(client)
+        {
+            return Reply::error("429 Too Many Requests", "Looking up too often");
+        }
+        let cache_hit = self
+            .db
+            .is_some_and(|db| matches!(db.cached(&word), Ok(Some(_))));
+        let started = Instant::now();
+        let result = lookup_maybe_cached(self.client, self.db, &word);
+        self.metrics.lookup(cache_hit);
+        if !cache_hit {
+            self.metrics.backend_latency(started.elapsed());
+        }
+        let resp = match result {
+            Ok(resp) => resp,
+            Err(e) => {
+                self.metrics.lookup_error();
+                return Reply::error("502 Bad Gateway", format!("{e:#}"));
+            }
+        };
+        if let Some(db) = self.db
+            && let Err(e) = db.record_lookup(&word)
+        {
+            warn!("Failed to record lookup of {word}: {e:#}");
+        }
+
+        match param("format").as_deref() {
+            None | Some("json") => {
+                let text = resp.explain_with(&PlainFormatter::new(false), self.explain);
+                Reply::json("200 OK", json!({ "response": resp, "text": text }))
+            }
+            Some("html") => Reply {
+                status: "200 OK",
+                content_type: "text/html; charset=utf-8",
+                body: GoldenDictFormatter::document(
+                    &word,
+                    &resp.explain_with(&GoldenDictFormatter::new(false), self.explain),
+                ),
+            },
+            Some(format) => Reply::error("400 Bad Request", format!("No format {format}")),
+        }
+    }
+
+    /// Whether a request with the `Authorization` header `authorization`
+    /// may be answered
+    fn authorized(&self, authorization: Option<&str>) -> bool {
+        let Some(token) = &self.access.token else {
+            return true;
+        };
+        authorization
+            .and_then(|x| x.strip_prefix("Bearer "))
+            .is_some_and(|x| constant_time_eq(x.trim().as_bytes(), token.as_bytes()))
+    }
+
+    /// Read a request of `client` from `stream` and write the reply
+    fn respond(&self, stream: &mut (impl Read + Write), client: IpAddr) -> Result<()> {
+        let mut reader = BufReader::new(&mut *stream).take(MAX_HEAD);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let mut authorization = None;
+        let mut ended = false;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 {
+                break;
+            }
+            if header.trim().is_empty() {
+                ended = true;
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':')
+                && name.eq_ignore_ascii_case("authorization")
+            {
+                authorization = Some(value.trim().to_string());
+            }
+        }
+        let reply = if !ended && reader.limit() == 0 {
+            Reply::error(
+                "431 Request Header Fields Too Large",
+                format!("Request line and headers over {MAX_HEAD} bytes"),
+            )
+        } else if !self.authorized(authorization.as_deref()) {
+            Reply::error("401 Unauthorized", "Missing or wrong bearer token")
+        } else {
+            match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["GET", target, _] => self.get(target, client),
+                [_, _, _] => Reply::error("405 Method Not Allowed", "Only GET is supported"),
+                _ => Reply::error("400 Bad Request", "Bad request line"),
+            }
+        };
+        self.metrics.request(reply.status);
+
+        let challenge = if reply.status.starts_with("401") {
+            "WWW-Authenticate: Bearer\r\n"
+        } else {
+            ""
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n{challenge}\
+             Access-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+            reply.status,
+            reply.content_type,
+            reply.body.len(),
+            reply.body
+        )?;
+        stream.flush()?;
+
+        Ok(())
+    }
+
+    /// Answer the request on `stream` over TLS if configured, closing it
+    /// after
+    fn answer(&self, stream: TcpStream) -> Result<()> {
+        stream.set_write_timeout(Some(READ_TIMEOUT))?;
+        let client = stream.peer_addr()?.ip();
+        let mut stream = Deadline {
+            stream,
+            deadline: Instant::now() + READ_TIMEOUT,
+        };
+        #[cfg(feature = "rustls")]
+        if let Some(tls) = &self.access.tls {
+            let mut stream = StreamOwned::new(ServerConnection::new(tls.clone())?, stream);
+            self.respond(&mut stream, client)?;
+            stream.conn.send_close_notify();
+            stream.flush()?;
+            return Ok(());
+        }
+        self.respond(&mut stream, client)
+    }
+}
+
+/// Answer each request of `listener` on a thread of its own with a database
+/// from `open_db`, forever or until `idle` exits, to the clients `access`
+/// lets in
+pub fn serve(
+    client: &(impl YdClient + Sync),
+    open_db: impl Fn() -> Result<Db> + Sync,
+    explain: &ExplainOptions,
+    listener: TcpListener,
+    idle: Option<&IdleExit>,
+    access: Access,
+) -> Result<()> {
+    let metrics = Metrics::default();
+    #[cfg(feature = "rustls")]
+    let scheme = if access.tls.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    #[cfg(not(feature = "rustls"))]
+    let scheme = "http";
+    eprintln!("Listening on {scheme}://{}", listener.local_addr()?);
+    activation::serve_each(&listener, idle, |stream| {
+        let db = open_db()
+            .inspect_err(|e| warn!("Failed to open ydcv database: {e:#}"))
+            .ok();
+        let server = Server {
+            client,
+            db: db.as_ref(),
+            explain,
+            metrics: &metrics,
+            access: &access,
+        };
+        if let Err(e) = server.answer(stream) {
+            warn!("Failed to answer a request: {e:#}");
+        }
+    })?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FakeClient;
+
+    #[test]
+    fn test_get() {
+        let db = Db::open_in_memory().unwrap();
+        let server = Server {
+            client: &FakeClient,
+            db: Some(&db),
+            explain: &ExplainOptions::default(),
+            metrics: &Metrics::default(),
+            access: &Access {
+                limiter: Some(RateLimiter::new(60, 4)),
+                ..Default::default()
+            },
+        };
+        let get = |target: &str| server.get(target, IpAddr::from([127, 0, 0, 1]));
+
+        let reply = get("/lookup?word=a%20b");
+        assert_eq!("200 OK", reply.status);
+        let value: Value = serde_json::from_str(&reply.body).unwrap();
+        assert_eq!("a b", value["response"]["query"]);
+        assert_eq!(Some("a b".to_string()), db.last_lookup().unwrap());
+
+        let reply = get("/lookup?word=hello&format=html");
+        assert_eq!("text/html; charset=utf-8", reply.content_type);
+        assert!(reply.body.contains("<title>hello</title>"));
+
+        assert_eq!("400 Bad Request", get("/lookup").status);
+        assert_eq!("400 Bad Request", get("/lookup?word=x&format=x").status);
+        assert_eq!("404 Not Found", get("/").status);
+        assert!(get("/metrics").body.contains("\nydcv_lookups_total 3\n"));
+        assert_eq!("200 OK", get("/lookup?word=y").status);
+        assert_eq!("429 Too Many Requests", get("/lookup?word=z").status);
+    }
+
+    /// A request read from `input`, answered into `output`
+    struct Exchange<'a> {
+        input: &'a [u8],
+        output: Vec<u8>,
+    }
+
+    impl Read for Exchange<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Exchange<'_> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_respond() {
+        let server = Server {
+            client: &FakeClient,
+            db: None,
+            explain: &ExplainOptions::default(),
+            metrics: &Metrics::default(),
+            access: &Access::default(),
+        };
+        let respond = |request: &str| {
+            let mut exchange = Exchange {
+                input: request.as_bytes(),
+                output: Vec::new(),
+            };
+            server
+                .respond(&mut exchange, IpAddr::from([127, 0, 0, 1]))
+                .unwrap();
+            String::from_utf8(exchange.output).unwrap()
+        };
+
+        assert!(
+            respond("GET /lookup?word=hello HTTP/1.1\r\nHost: x\r\n\r\n")
+                .starts_with("HTTP/1.1 200 OK\r\n")
+        );
+        let padding = "x".repeat(MAX_HEAD as usize);
+        assert!(
+            respond(&format!(
+                "GET /lookup?word=hello HTTP/1.1\r\nX: {padding}\r\n\r\n"
+            ))
+            .starts_with("HTTP/1.1 431 ")
+        );
+    }
+
+    #[test]
+    fn test_authorized() {
+        let db = Db::open_in_memory().unwrap();
+        let server = Server {
+            client: &FakeClient,
+            db: Some(&db),
+            explain: &ExplainOptions::default(),
+            metrics: &Metrics::default(),
+            access: &Access {
+                token: Some("secret".to_string()),
+                ..Default::default()
+            },
+        };
+        assert!(server.authorized(Some("Bearer secret")));
+        assert!(!server.authorized(Some("Bearer secre")));
+        assert!(!server.authorized(Some("secret")));
+        assert!(!server.authorized(None));
+    }
+}
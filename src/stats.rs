@@ -0,0 +1,167 @@
+//! lookup statistics and daily streaks
+
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::Result;
+use chrono::{Days, Local, NaiveDate, TimeDelta};
+
+use crate::db::{Db, HistoryEntry};
+
+/// Lookups further apart than this are counted as separate study sessions
+const SESSION_GAP: TimeDelta = TimeDelta::minutes(5);
+
+/// Activity of a single day
+#[derive(Default)]
+pub struct DayActivity {
+    pub lookups: usize,
+    pub study_time: TimeDelta,
+}
+
+pub struct Stats {
+    pub total_lookups: usize,
+    pub unique_words: usize,
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    pub days: BTreeMap<NaiveDate, DayActivity>,
+}
+
+impl Stats {
+    pub fn load(db: &Db) -> Result<Self> {
+        Ok(Self::from_history(
+            &db.history()?,
+            Local::now().date_naive(),
+        ))
+    }
+
+    fn from_history(history: &[HistoryEntry], today: NaiveDate) -> Self {
+        let mut days: BTreeMap<NaiveDate, DayActivity> = BTreeMap::new();
+
+        for (i, entry) in history.iter().enumerate() {
+            let day = days.entry(entry.looked_up_at.date_naive()).or_default();
+            day.lookups += 1;
+
+            if let Some(prev) = i.checked_sub(1).map(|x| &history[x]) {
+                let gap = entry.looked_up_at - prev.looked_up_at;
+                if prev.looked_up_at.date_naive() == entry.looked_up_at.date_naive()
+                    && gap <= SESSION_GAP
+                {
+                    day.study_time += gap;
+                }
+            }
+        }
+
+        let (current_streak, longest_streak) = streaks(&days.keys().copied().collect(), today);
+
+        Stats {
+            total_lookups: history.len(),
+            unique_words: history
+                .iter()
+                .map(|x| x.word.as_str())
+                .collect::<HashSet<_>>()
+                .len(),
+            current_streak,
+            longest_streak,
+            days,
+        }
+    }
+
+    /// One-line summary of the streak, used as the REPL greeting
+    pub fn streak_line(&self) -> String {
+        let today = self
+            .days
+            .get(&Local::now().date_naive())
+            .map(|x| x.lookups)
+            .unwrap_or_default();
+
+        format!(
+            "Streak: {} day(s), {} lookup(s) today",
+            self.current_streak, today
+        )
+    }
+
+    /// Print full statistics with the activity of the last `days` days
+    pub fn print(&self, days: u64) {
+        println!("Total lookups:  {}", self.total_lookups);
+        println!("Unique words:   {}", self.unique_words);
+        println!("Current streak: {} day(s)", self.current_streak);
+        println!("Longest streak: {} day(s)", self.longest_streak);
+
+        let today = Local::now().date_naive();
+        let Some(first) = today.checked_sub_days(Days::new(days.saturating_sub(1))) else {
+            return;
+        };
+
+        println!();
+        for day in first.iter_days().take_while(|x| *x <= today) {
+            let activity = self.days.get(&day);
+            println!(
+                "  {}  {:>4} lookup(s)  {:>3} min",
+                day,
+                activity.map(|x| x.lookups).unwrap_or_default(),
+                activity
+                    .map(|x| x.study_time.num_minutes())
+                    .unwrap_or_default()
+            );
+        }
+    }
+}
+
+/// Compute the (current, longest) streak of consecutive active days.
+///
+/// The current streak is still alive if the user hasn't looked up anything
+/// today but did yesterday.
+fn streaks(active: &HashSet<NaiveDate>, today: NaiveDate) -> (u32, u32) {
+    let run_ending_at = |mut day: NaiveDate| {
+        let mut len = 0;
+        while active.contains(&day) {
+            len += 1;
+            match day.pred_opt() {
+                Some(prev) => day = prev,
+                None => break,
+            }
+        }
+        len
+    };
+
+    let current = match run_ending_at(today) {
+        0 => today.pred_opt().map(run_ending_at).unwrap_or_default(),
+        n => n,
+    };
+
+    let longest = active
+        .iter()
+        .filter(|x| !x.succ_opt().is_some_and(|next| active.contains(&next)))
+        .map(|x| run_ending_at(*x))
+        .max()
+        .unwrap_or_default();
+
+    (current, longest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_streaks() {
+        let active = [
+            "2024-01-01",
+            "2024-01-02",
+            "2024-01-03",
+            "2024-01-06",
+            "2024-01-07",
+        ]
+        .into_iter()
+        .map(date)
+        .collect();
+
+        assert_eq!((2, 3), streaks(&active, date("2024-01-07")));
+        assert_eq!((2, 3), streaks(&active, date("2024-01-08")));
+        assert_eq!((0, 3), streaks(&active, date("2024-01-09")));
+        assert_eq!((0, 0), streaks(&HashSet::new(), date("2024-01-09")));
+    }
+}
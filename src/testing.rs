@@ -0,0 +1,29 @@
+//! Helpers shared by the tests of `ydcv`
+
+use crate::error;
+use crate::lang::Direction;
+use crate::ydclient::YdClient;
+use crate::ydresponse::YdResponse;
+
+/// A minimal response with a single translation, like the ones of the
+/// translation endpoint
+pub fn fake(query: &str, translation: &str) -> YdResponse {
+    YdResponse::from_translation(
+        query,
+        vec![translation.to_string()],
+        Direction::detect(query),
+    )
+}
+
+/// Answers every lookup with the same translation
+pub struct FakeClient;
+
+impl YdClient for FakeClient {
+    fn lookup_word_in(&self, word: &str, _: Direction) -> error::Result<YdResponse> {
+        Ok(fake(word, "译"))
+    }
+
+    fn translate(&self, text: &str, _: Direction) -> error::Result<YdResponse> {
+        Ok(fake(text, "译"))
+    }
+}
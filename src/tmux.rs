@@ -0,0 +1,120 @@
+//! Explanations in tmux popups, for looking up words while reading in a pane
+
+use std::env;
+use std::io::{Read, stdin};
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+
+/// Columns and rows taken by the border and padding of a popup
+const FRAME: (usize, usize) = (4, 2);
+
+/// Open a popup sized to fit `body`, the explanation of `word`, running
+/// `ydcv tmux-popup --inside` in it to show the explanation with colors
+pub fn open_popup(word: &str, body: &str) -> Result<()> {
+    if env::var_os("TMUX").is_none() {
+        bail!("tmux-popup only works inside tmux");
+    }
+
+    let client = tmux(&["display-message", "-p", "#{client_width} #{client_height}"])?;
+    let client = client
+        .split_once(' ')
+        .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)))
+        .context("Failed to get the size of the tmux client")?;
+    let (width, height) = popup_size(body, client);
+
+    let exe = env::current_exe()?;
+    let command = [
+        &*exe.to_string_lossy(),
+        "tmux-popup",
+        "--inside",
+        "--",
+        word,
+    ]
+    .map(shell_quote)
+    .join(" ");
+    tmux(&[
+        "display-popup",
+        "-E",
+        "-w",
+        &width.to_string(),
+        "-h",
+        &height.to_string(),
+        &command,
+    ])?;
+
+    Ok(())
+}
+
+/// Wait for a key, with the terminal in non-canonical mode so that any key
+/// counts rather than only Enter
+pub fn wait_for_key() -> Result<()> {
+    let stty = |args: &[&str]| Command::new("stty").args(args).status();
+    stty(&["-icanon", "-echo"])?;
+    let read = stdin().read(&mut [0]);
+    stty(&["icanon", "echo"])?;
+    read?;
+
+    Ok(())
+}
+
+/// Output of the tmux command `args`
+fn tmux(args: &[&str]) -> Result<String> {
+    let output = Command::new("tmux")
+        .args(args)
+        .output()
+        .context("Failed to run tmux")?;
+    if !output.status.success() {
+        bail!(
+            "tmux {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// `arg` quoted for the shell running the command of a popup
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Width and height of a popup showing `body` whole, at most `max`
+fn popup_size(body: &str, max: (usize, usize)) -> (usize, usize) {
+    let width = body.lines().map(display_width).max().unwrap_or(0) + FRAME.0;
+    let height = body.lines().count() + FRAME.1;
+
+    (width.min(max.0), height.min(max.1))
+}
+
+/// Columns taken by `line` in a terminal
+fn display_width(line: &str) -> usize {
+    line.chars().map(|c| if is_wide(c) { 2 } else { 1 }).sum()
+}
+
+/// Whether `c` is an East Asian wide character, taking two columns
+fn is_wide(c: char) -> bool {
+    matches!(
+        c,
+        '\u{1100}'..='\u{115F}'
+            | '\u{2E80}'..='\u{A4CF}'
+            | '\u{AC00}'..='\u{D7A3}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{FE30}'..='\u{FE4F}'
+            | '\u{FF00}'..='\u{FF60}'
+            | '\u{FFE0}'..='\u{FFE6}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_popup_size() {
+        assert_eq!((10, 4), popup_size("hello\n你好吗", (80, 24)));
+        assert_eq!((8, 3), popup_size("hello\n你好吗", (8, 3)));
+        assert_eq!(r"'it'\''s'", shell_quote("it's"));
+    }
+}
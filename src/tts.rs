@@ -0,0 +1,88 @@
+//! local text-to-speech, used when online audio is unavailable
+
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, bail};
+
+use crate::lang::is_chinese;
+use crate::ydresponse::Accent;
+
+/// Language and variant spoken by the synthesizer
+#[derive(Clone, Copy)]
+pub enum Voice {
+    English(Accent),
+    Chinese,
+}
+
+impl Voice {
+    /// Chinese for text containing Chinese characters, English otherwise
+    pub fn for_text(text: &str, accent: Accent) -> Self {
+        if is_chinese(text) {
+            Voice::Chinese
+        } else {
+            Voice::English(accent)
+        }
+    }
+}
+
+/// Command line speaking `text` with the platform's speech synthesizer
+fn command(text: &str, voice: Voice) -> Vec<String> {
+    if cfg!(windows) {
+        let script = format!(
+            "Add-Type -AssemblyName System.Speech; \
+             $s = New-Object System.Speech.Synthesis.SpeechSynthesizer; \
+             $s.SelectVoiceByHints('NotSet', 'NotSet', 0, [Globalization.CultureInfo]'{}'); \
+             $s.Speak('{}')",
+            match voice {
+                Voice::English(Accent::Uk) => "en-GB",
+                Voice::English(Accent::Us) => "en-US",
+                Voice::Chinese => "zh-CN",
+            },
+            text.replace('\'', "''")
+        );
+        vec![
+            "powershell".to_string(),
+            "-NoProfile".to_string(),
+            "-Command".to_string(),
+            script,
+        ]
+    } else if cfg!(target_os = "macos") {
+        let mut cmd = vec!["say".to_string()];
+        if let Voice::Chinese = voice {
+            cmd.extend(["-v".to_string(), "Tingting".to_string()]);
+        }
+        cmd.extend(["--".to_string(), text.to_string()]);
+        cmd
+    } else {
+        vec![
+            "espeak-ng".to_string(),
+            "-v".to_string(),
+            match voice {
+                Voice::English(Accent::Uk) => "en-gb",
+                Voice::English(Accent::Us) => "en-us",
+                Voice::Chinese => "cmn",
+            }
+            .to_string(),
+            "--".to_string(),
+            text.to_string(),
+        ]
+    }
+}
+
+/// Speak `text` with espeak-ng on Linux, `say` on macOS or SAPI on Windows
+pub fn speak(text: &str, voice: Voice) -> Result<()> {
+    let cmd = command(text, voice);
+
+    let status = Command::new(&cmd[0])
+        .args(&cmd[1..])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()
+        .with_context(|| format!("Failed to run {} for speech synthesis", cmd[0]))?;
+
+    if !status.success() {
+        bail!("{} exited with {status}", cmd[0]);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! built-in wordbook
+
+use anyhow::Result;
+
+use crate::db::Db;
+use crate::ydresponse::YdResponse;
+
+/// Add the looked-up word to the wordbook and report what happened
+pub fn add(db: &Db, resp: &YdResponse) -> Result<()> {
+    let word = resp.query();
+
+    if !resp.has_result() {
+        println!("No result for {word}, not added to wordbook");
+    } else if db.wordbook_add(resp)? {
+        println!("Added {word} to wordbook");
+    } else {
+        println!("{word} is already in wordbook");
+    }
+
+    Ok(())
+}
+
+pub fn remove(db: &Db, word: &str) -> Result<()> {
+    if db.wordbook_remove(word)? {
+        println!("Removed {word} from wordbook");
+    } else {
+        println!("{word} is not in wordbook");
+    }
+
+    Ok(())
+}
+
+/// Attach tags and a note to a word already in the wordbook
+pub fn annotate(
+    db: &Db,
+    word: &str,
+    tags: &[String],
+    untags: &[String],
+    note: Option<&str>,
+) -> Result<()> {
+    if !db.wordbook_contains(word)? {
+        println!("{word} is not in wordbook");
+        return Ok(());
+    }
+
+    db.wordbook_tag(word, tags)?;
+    db.wordbook_untag(word, untags)?;
+    if let Some(note) = note {
+        db.wordbook_set_note(word, Some(note).filter(|x| !x.is_empty()))?;
+    }
+
+    Ok(())
+}
+
+/// List wordbook entries, only those tagged `tag` if given
+pub fn list(db: &Db, tag: Option<&str>) -> Result<()> {
+    for entry in db.wordbook()? {
+        if tag.is_some_and(|tag| !entry.tags.iter().any(|x| x == tag)) {
+            continue;
+        }
+
+        let mut line = format!(
+            "{}  {}  {}",
+            entry.added_at.format("%Y-%m-%d"),
+            entry.word,
+            entry.snapshot.translation().join("; ")
+        );
+        if !entry.tags.is_empty() {
+            line.push_str(&format!("  [{}]", entry.tags.join(", ")));
+        }
+        println!("{line}");
+
+        if let Some(note) = entry.note {
+            println!("    {note}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Search the wordbook and cached definitions, printing where `text` matched
+pub fn search(db: &Db, text: &str) -> Result<()> {
+    for hit in db.search(text)? {
+        let context = hit
+            .note
+            .as_deref()
+            .and_then(|x| excerpt(x, text))
+            .or_else(|| excerpt(&hit.definition, text))
+            .unwrap_or_default();
+
+        println!(
+            "{}{}  {}",
+            hit.word,
+            if hit.in_wordbook { " *" } else { "" },
+            context
+        );
+    }
+
+    Ok(())
+}
+
+/// A few characters around the first case-insensitive occurrence of `text`
+fn excerpt(haystack: &str, text: &str) -> Option<String> {
+    const CONTEXT: usize = 20;
+
+    let chars = haystack.chars().collect::<Vec<_>>();
+    let needle = text.to_lowercase().chars().collect::<Vec<_>>();
+    let lower = haystack.to_lowercase().chars().collect::<Vec<_>>();
+    if needle.is_empty() || lower.len() != chars.len() {
+        return None;
+    }
+
+    let start = lower.windows(needle.len()).position(|x| x == needle)?;
+    let from = start.saturating_sub(CONTEXT);
+    let to = (start + needle.len() + CONTEXT).min(chars.len());
+
+    Some(format!(
+        "{}{}{}",
+        if from > 0 { "…" } else { "" },
+        chars[from..to].iter().collect::<String>(),
+        if to < chars.len() { "…" } else { "" }
+    ))
+}
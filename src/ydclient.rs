@@ -1,12 +1,131 @@
 //! ydclient is client wrapper for Client
 
+use super::error::{Result, YdcvError};
+use super::http::SendRetryingAsync;
+use super::lang::{Direction, Lang};
 use super::ydresponse::YdResponse;
-use anyhow::Result;
-use reqwest::blocking::Client;
-use reqwest::header::{REFERER, USER_AGENT};
-use std::io::Read;
+use futures_util::{Stream, StreamExt, stream};
+use log::debug;
+use reqwest::header::REFERER;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use std::fs;
+use std::io::{self, Write, stdout};
+use std::path::{Path, PathBuf};
+use std::pin::pin;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio::runtime::{self, Runtime};
+use tokio::time;
 
-/// Wrapper trait on `reqwest::Client`
+/// Base URL of the dictionary, see `set_endpoint`
+static ENDPOINT: OnceLock<String> = OnceLock::new();
+
+const DEFAULT_ENDPOINT: &str = "https://www.youdao.com";
+
+/// Base URL of the sentence translator, unless an endpoint is set
+const DEFAULT_TRANSLATE_ENDPOINT: &str = "https://fanyi.youdao.com";
+
+/// Look up words, translate sentences and open results on `endpoint`, like
+/// a mirror or a test server, instead of YD. Only the first call has effect.
+pub fn set_endpoint(endpoint: &str) {
+    ENDPOINT.get_or_init(|| endpoint.trim_end_matches('/').to_string());
+}
+
+/// Where to save fetched pages, see `set_dump_html`
+static DUMP_HTML: OnceLock<PathBuf> = OnceLock::new();
+
+/// Save every fetched page to `path` before parsing it: to stdout if it's
+/// `-`, to `<word>.html` inside it if it's a directory, or else to the file.
+/// Only the first call has effect.
+pub fn set_dump_html(path: PathBuf) {
+    DUMP_HTML.get_or_init(|| path);
+}
+
+/// Save the fetched page of `word`, see `set_dump_html`
+fn dump_html(path: &Path, word: &str, body: &str) -> Result<()> {
+    if path == Path::new("-") {
+        return Ok(stdout().write_all(body.as_bytes())?);
+    }
+
+    let path = if path.is_dir() {
+        path.join(format!("{}.html", word.replace(['/', '\\'], "_")))
+    } else {
+        path.to_path_buf()
+    };
+    fs::write(&path, body).map_err(|e| {
+        io::Error::new(e.kind(), format!("Failed to write {}: {e}", path.display())).into()
+    })
+}
+
+/// Requests run at once by `AsyncYdClient::lookup_stream`, see `set_concurrency`
+static CONCURRENCY: OnceLock<usize> = OnceLock::new();
+
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Longest random delay before each request of a batch but the first, so that
+/// they don't reach YD all at once
+const MAX_JITTER: Duration = Duration::from_millis(300);
+
+/// Run at most `concurrency` requests at once when looking up many words.
+/// Only the first call has effect.
+pub fn set_concurrency(concurrency: usize) {
+    CONCURRENCY.get_or_init(|| concurrency.max(1));
+}
+
+/// Whether to report how long each lookup takes, see `set_timing`
+static TIMING: AtomicBool = AtomicBool::new(false);
+
+/// Report the network and parse time of every lookup, and every cache hit,
+/// on stderr
+pub fn set_timing() {
+    TIMING.store(true, Ordering::Relaxed);
+}
+
+/// Report that `word` was found in the cache, see `set_timing`
+pub fn report_cache_hit(word: &str) {
+    if TIMING.load(Ordering::Relaxed) {
+        eprintln!("[timing] {word}: cache hit");
+    }
+}
+
+/// Report the time looking up `word` took, see `set_timing`
+fn report_timing(word: &str, network: Duration, parse: Duration) {
+    if TIMING.load(Ordering::Relaxed) {
+        eprintln!("{}", timing_line(word, network, parse));
+    }
+}
+
+fn timing_line(word: &str, network: Duration, parse: Duration) -> String {
+    format!(
+        "[timing] {word}: network {:.1}ms, parse {:.1}ms",
+        network.as_secs_f64() * 1000.0,
+        parse.as_secs_f64() * 1000.0
+    )
+}
+
+fn endpoint() -> &'static str {
+    ENDPOINT.get().map_or(DEFAULT_ENDPOINT, |x| x.as_str())
+}
+
+fn translate_endpoint() -> &'static str {
+    ENDPOINT
+        .get()
+        .map_or(DEFAULT_TRANSLATE_ENDPOINT, |x| x.as_str())
+}
+
+/// URL of the result page of `word` on the dictionary, to open in a browser
+pub fn result_url(word: &str) -> Option<reqwest::Url> {
+    let lang = Direction::detect(word).dict().code();
+    reqwest::Url::parse_with_params(
+        &format!("{}/result", endpoint()),
+        [("word", word), ("lang", lang)],
+    )
+    .ok()
+}
+
+/// Blocking lookups on YD, see `AsyncYdClient` for the async core
 pub trait YdClient {
     /// lookup a word on YD and returns a `YdPreponse`
     ///
@@ -14,52 +133,530 @@ pub trait YdClient {
     ///
     /// lookup "hello" and compare the result:
     ///
+    /// ```no_run
+    /// use ydcv_saki::http::{self, HttpOptions};
+    /// use ydcv_saki::ydclient::{BlockingYdClient, YdClient};
+    ///
+    /// let client = BlockingYdClient::new(http::build_async_client(&HttpOptions::default())?)?;
+    /// assert_eq!("hello", client.lookup_word("hello")?.query());
+    /// # anyhow::Ok(())
     /// ```
-    /// assert_eq!("YdResponse('hello')",
-    ///        format!("{}", Client::new().lookup_word("hello").unwrap()));
-    /// ```
-    fn lookup_word(&self, word: &str) -> Result<YdResponse>;
+    fn lookup_word(&self, word: &str) -> Result<YdResponse> {
+        self.lookup_word_in(word, Direction::detect(word))
+    }
+
+    /// lookup a word in the given direction
+    fn lookup_word_in(&self, word: &str, dir: Direction) -> Result<YdResponse>;
+
+    /// translate a sentence in the given direction
+    fn translate(&self, text: &str, dir: Direction) -> Result<YdResponse>;
+
+    /// lookup all `words` and return the results in the same order
+    fn lookup_words(&self, words: &[&str]) -> Vec<Result<YdResponse>> {
+        words.iter().map(|x| self.lookup_word(x)).collect()
+    }
+}
+
+/// A word or sentence of a batch, see `AsyncYdClient::lookup_stream`
+#[derive(Clone)]
+pub struct Query<'a> {
+    pub text: &'a str,
+    pub dir: Direction,
+    /// Translate `text` as a sentence instead of looking it up as a word
+    pub sentence: bool,
+}
+
+/// Async lookups on YD, so that many can run at once
+pub trait AsyncYdClient {
+    /// Async counterpart of `YdClient::lookup_word`, for callers already on
+    /// a runtime such as GUIs and servers
+    fn lookup_word_async(&self, word: &str) -> impl Future<Output = Result<YdResponse>> {
+        self.lookup_word_in(word, Direction::detect(word))
+    }
+
+    /// lookup a word in the given direction
+    fn lookup_word_in(
+        &self,
+        word: &str,
+        dir: Direction,
+    ) -> impl Future<Output = Result<YdResponse>>;
+
+    /// translate a sentence in the given direction
+    fn translate(&self, text: &str, dir: Direction) -> impl Future<Output = Result<YdResponse>>;
+
+    /// Look up all `queries`, running a few at once with a random delay
+    /// before each, and yield each result with the index of its query as
+    /// soon as it arrives if not `ordered`, or else in the same order
+    fn lookup_stream(
+        &self,
+        queries: &[Query],
+        ordered: bool,
+    ) -> impl Stream<Item = (usize, Result<YdResponse>)> {
+        let concurrency = *CONCURRENCY.get().unwrap_or(&DEFAULT_CONCURRENCY);
+        let lookups = queries
+            .iter()
+            .enumerate()
+            .map(move |(i, query)| async move {
+                if i > 0 {
+                    time::sleep(MAX_JITTER.mul_f64(fastrand::f64())).await;
+                }
+                let result = if query.sentence {
+                    self.translate(query.text, query.dir).await
+                } else {
+                    self.lookup_word_in(query.text, query.dir).await
+                };
+                (i, result)
+            });
+
+        if ordered {
+            stream::iter(lookups).buffered(concurrency).left_stream()
+        } else {
+            stream::iter(lookups)
+                .buffer_unordered(concurrency)
+                .right_stream()
+        }
+    }
+
+    /// Look up all `queries` like `lookup_stream` and return the results in
+    /// the same order
+    fn lookup_batch(&self, queries: &[Query]) -> impl Future<Output = Vec<Result<YdResponse>>> {
+        self.lookup_stream(queries, true)
+            .map(|(_, result)| result)
+            .collect()
+    }
+
+    /// Like `lookup_batch` for `words` in their detected directions
+    fn lookup_words(&self, words: &[&str]) -> impl Future<Output = Vec<Result<YdResponse>>> {
+        let queries = words
+            .iter()
+            .map(|x| Query {
+                text: x,
+                dir: Direction::detect(x),
+                sentence: false,
+            })
+            .collect::<Vec<_>>();
+
+        async move { self.lookup_batch(&queries).await }
+    }
 }
 
-/// Implement wrapper client trait on `reqwest::Client`
-impl YdClient for Client {
-    /// lookup a word on YD and returns a `YdResponse`
+/// Fetches the pages of YD, so that tests and other programs can answer
+/// lookups without the network, e.g. from saved pages
+pub trait Transport {
+    /// Body of `url` with `query` as its query string, sent from `referer`.
+    /// Error pages are still returned, e.g. for their typo corrections.
+    fn fetch(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        referer: &str,
+    ) -> impl Future<Output = Result<String>>;
+}
+
+/// Fetch pages with `reqwest::Client`, retrying like the blocking client
+impl Transport for Client {
     #[cfg(any(feature = "native-tls", feature = "rustls"))]
-    fn lookup_word(&self, word: &str) -> Result<YdResponse> {
-        let body = lookup_word(word, self)?;
-        let res = YdResponse::from_html(&body, word)?;
+    async fn fetch(&self, url: &str, query: &[(&str, &str)], referer: &str) -> Result<String> {
+        let resp = self
+            .get(url)
+            .header(REFERER, referer)
+            .query(query)
+            .send_retrying()
+            .await?;
+        if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+            return Err(YdcvError::RateLimited);
+        }
+
+        Ok(resp.text().await?)
+    }
+}
+
+/// Look up on YD with the pages fetched by any `Transport`
+impl<T: Transport> AsyncYdClient for T {
+    /// lookup a word in the given direction and returns a `YdResponse`
+    async fn lookup_word_in(&self, word: &str, dir: Direction) -> Result<YdResponse> {
+        if !YdResponse::parses_pages(dir) {
+            return self.translate(word, dir).await;
+        }
+        let start = Instant::now();
+        let body = lookup_page(self, word, dir, endpoint()).await?;
+        let network = start.elapsed();
+        if let Some(path) = DUMP_HTML.get() {
+            dump_html(path, word, &body)?;
+        }
+        let start = Instant::now();
+        let res = YdResponse::from_html(&body, word, dir)?;
+        report_timing(word, network, start.elapsed());
 
         Ok(res)
     }
+
+    /// translate a sentence in the given direction and returns a `YdResponse`
+    async fn translate(&self, text: &str, dir: Direction) -> Result<YdResponse> {
+        let start = Instant::now();
+        let endpoint = translate_endpoint();
+        let body = self
+            .fetch(
+                &format!("{endpoint}/translate"),
+                &[("doctype", "json"), ("type", fanyi_type(dir)), ("i", text)],
+                endpoint,
+            )
+            .await?;
+        let network = start.elapsed();
+
+        let start = Instant::now();
+        let translation = parse_fanyi(&body)?;
+        report_timing(text, network, start.elapsed());
+        Ok(YdResponse::from_translation(text, translation, dir))
+    }
 }
 
-fn lookup_word(word: &str, client: &Client) -> Result<String> {
-    let mut body = String::new();
-    client
-        .get("https://www.youdao.com/result")
-        .header(REFERER, "https://www.youdao.com")
-        .header(
-            USER_AGENT,
-            "Mozilla/5.0 (X11; AOSC OS; Linux x86_64; rv:109.0) Gecko/20100101 Firefox/117.0",
-        )
-        .query(&[("word", word), ("lang", "en")])
-        .send()?
-        .read_to_string(&mut body)?;
+/// Blocking shim over an `AsyncYdClient`, running each lookup to completion
+/// on a runtime of its own
+pub struct BlockingYdClient<C = Client> {
+    client: C,
+    runtime: Runtime,
+}
 
-    Ok(body)
+impl<C> BlockingYdClient<C> {
+    /// Wrap `client` with a single-threaded runtime to block on
+    pub fn new(client: C) -> Result<Self> {
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(BlockingYdClient { client, runtime })
+    }
+}
+
+impl<C: AsyncYdClient> BlockingYdClient<C> {
+    /// Call `f` with each result of `AsyncYdClient::lookup_stream` as soon as
+    /// it's yielded, stopping at the first error `f` returns. `f` runs outside
+    /// the runtime so that it may block, e.g. to play audio.
+    pub fn lookup_each<E>(
+        &self,
+        queries: &[Query],
+        ordered: bool,
+        mut f: impl FnMut(usize, Result<YdResponse>) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let mut results = pin!(self.client.lookup_stream(queries, ordered));
+        while let Some((i, result)) = self.runtime.block_on(results.next()) {
+            f(i, result)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<C: AsyncYdClient> YdClient for BlockingYdClient<C> {
+    fn lookup_word(&self, word: &str) -> Result<YdResponse> {
+        self.runtime.block_on(self.client.lookup_word_async(word))
+    }
+
+    fn lookup_word_in(&self, word: &str, dir: Direction) -> Result<YdResponse> {
+        self.runtime.block_on(self.client.lookup_word_in(word, dir))
+    }
+
+    fn translate(&self, text: &str, dir: Direction) -> Result<YdResponse> {
+        self.runtime.block_on(self.client.translate(text, dir))
+    }
+
+    /// lookup all `words` a few at once, see `AsyncYdClient::lookup_batch`
+    fn lookup_words(&self, words: &[&str]) -> Vec<Result<YdResponse>> {
+        self.runtime.block_on(self.client.lookup_words(words))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FanyiReply {
+    error_code: i32,
+    #[serde(default)]
+    translate_result: Vec<Vec<FanyiSegment>>,
+}
+
+#[derive(Deserialize)]
+struct FanyiSegment {
+    tgt: String,
+}
+
+/// Value of the `type` parameter of YD's translation API
+fn fanyi_type(dir: Direction) -> &'static str {
+    match (dir.from, dir.to) {
+        (Lang::Zh, Lang::En) => "ZH_CN2EN",
+        (Lang::Zh, Lang::Ja) => "ZH_CN2JA",
+        (Lang::Zh, Lang::Ko) => "ZH_CN2KR",
+        (Lang::Zh, Lang::Fr) => "ZH_CN2FR",
+        (Lang::Zh, Lang::Ru) => "ZH_CN2RU",
+        (Lang::Zh, Lang::Es) => "ZH_CN2SP",
+        (Lang::En, Lang::Zh) => "EN2ZH_CN",
+        (Lang::Ja, Lang::Zh) => "JA2ZH_CN",
+        (Lang::Ko, Lang::Zh) => "KR2ZH_CN",
+        (Lang::Fr, Lang::Zh) => "FR2ZH_CN",
+        (Lang::Ru, Lang::Zh) => "RU2ZH_CN",
+        (Lang::Es, Lang::Zh) => "SP2ZH_CN",
+        _ => "AUTO",
+    }
+}
+
+/// Translated paragraphs of a reply of YD's translation API
+fn parse_fanyi(body: &str) -> Result<Vec<String>> {
+    let reply: FanyiReply = serde_json::from_str(body).map_err(|_| YdcvError::ParseFailed {
+        selector: "translateResult",
+    })?;
+    if reply.error_code != 0 {
+        debug!("Translation failed with error code {}", reply.error_code);
+        return Err(YdcvError::NoResult);
+    }
+
+    Ok(reply
+        .translate_result
+        .into_iter()
+        .map(|x| x.into_iter().map(|x| x.tgt).collect::<String>())
+        .collect())
+}
+
+/// Result page of `word` on the dictionary at `endpoint`
+async fn lookup_page(
+    transport: &impl Transport,
+    word: &str,
+    dir: Direction,
+    endpoint: &str,
+) -> Result<String> {
+    transport
+        .fetch(
+            &format!("{endpoint}/result"),
+            &[("word", word), ("lang", dir.dict().code())],
+            endpoint,
+        )
+        .await
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::CLIENT;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+    use std::sync::atomic::AtomicUsize;
+    use std::thread;
+
+    use crate::YD_CLIENT;
 
     use super::*;
 
+    #[test]
+    fn test_parse_fanyi() {
+        let body = r#"{"type":"EN2ZH_CN","errorCode":0,"elapsedTime":1,"translateResult":[[{"src":"Hello.","tgt":"你好。"},{"src":"Bye.","tgt":"再见。"}],[{"src":"Hi","tgt":"嗨"}]]}"#;
+        assert_eq!(vec!["你好。再见。", "嗨"], parse_fanyi(body).unwrap());
+        assert!(parse_fanyi(r#"{"errorCode":40}"#).is_err());
+    }
+
+    #[test]
+    fn test_result_url() {
+        assert_eq!(
+            "https://www.youdao.com/result?word=%E4%BD%A0%E5%A5%BD&lang=en",
+            result_url("你好").unwrap().as_str()
+        );
+    }
+
+    #[test]
+    fn test_endpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request_line = String::new();
+            BufReader::new(&stream)
+                .read_line(&mut request_line)
+                .unwrap();
+            let body = "<div class=\"no-data-prompt\">没有找到</div>";
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+            request_line
+        });
+
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let body = runtime
+            .block_on(lookup_page(
+                &Client::new(),
+                "hello",
+                Direction::detect("hello"),
+                &endpoint,
+            ))
+            .unwrap();
+        assert!(body.contains("no-data-prompt"));
+        assert!(
+            server
+                .join()
+                .unwrap()
+                .starts_with("GET /result?word=hello&lang=en ")
+        );
+    }
+
+    /// Answers every lookup after a while, counting the lookups in flight
+    #[derive(Default)]
+    struct SlowClient {
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    impl AsyncYdClient for SlowClient {
+        async fn lookup_word_in(&self, word: &str, dir: Direction) -> Result<YdResponse> {
+            let n = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(n, Ordering::SeqCst);
+            time::sleep(Duration::from_millis(20)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(YdResponse::from_translation(word, vec![], dir))
+        }
+
+        async fn translate(&self, _: &str, _: Direction) -> Result<YdResponse> {
+            Err(YdcvError::NoResult)
+        }
+    }
+
+    #[test]
+    fn test_lookup_batch() {
+        let client = BlockingYdClient::new(SlowClient::default()).unwrap();
+        let words = (0..10).map(|x| x.to_string()).collect::<Vec<_>>();
+        let mut queries = words
+            .iter()
+            .map(|x| Query {
+                text: x,
+                dir: Direction::detect(x),
+                sentence: false,
+            })
+            .collect::<Vec<_>>();
+        queries[3].sentence = true;
+
+        let mut results = Vec::new();
+        client
+            .lookup_each(&queries, true, |i, result| {
+                results.push((i, result));
+                Ok::<_, YdcvError>(())
+            })
+            .unwrap();
+        assert!(results[3].1.is_err());
+        for (i, (j, result)) in results.iter().enumerate() {
+            assert_eq!(i, *j);
+            if i != 3 {
+                assert_eq!(i.to_string(), result.as_ref().unwrap().query());
+            }
+        }
+
+        let mut seen = vec![false; queries.len()];
+        client
+            .lookup_each(&queries, false, |i, _| {
+                seen[i] = true;
+                Ok::<_, YdcvError>(())
+            })
+            .unwrap();
+        assert!(seen.into_iter().all(|x| x));
+        let max_in_flight = client.client.max_in_flight.load(Ordering::SeqCst);
+        assert!((1..=DEFAULT_CONCURRENCY).contains(&max_in_flight));
+
+        let queries = client
+            .lookup_words(&["world", "hello"])
+            .into_iter()
+            .map(|x| x.unwrap().query().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["world", "hello"], queries);
+    }
+
+    #[test]
+    fn test_lookup_word_async() {
+        let client = BlockingYdClient::new(SlowClient::default()).unwrap();
+        let resp = client
+            .runtime
+            .block_on(client.client.lookup_word_async("hello"))
+            .unwrap();
+        assert_eq!("hello", resp.query());
+        assert_eq!("hello", client.lookup_word("hello").unwrap().query());
+    }
+
+    #[test]
+    fn test_timing_line() {
+        assert_eq!(
+            "[timing] hello: network 312.0ms, parse 4.5ms",
+            timing_line(
+                "hello",
+                Duration::from_millis(312),
+                Duration::from_micros(4500)
+            )
+        );
+    }
+
+    #[test]
+    fn test_dump_html() {
+        let dir = std::env::temp_dir().join("ydcv-test-dump");
+        fs::create_dir_all(&dir).unwrap();
+        dump_html(&dir, "a/b", "<html></html>").unwrap();
+        assert_eq!(
+            "<html></html>",
+            fs::read_to_string(dir.join("a_b.html")).unwrap()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Answers every fetch with `body`, keeping the URL of the last
+    #[derive(Default)]
+    struct RecordingTransport {
+        body: &'static str,
+        last_url: Mutex<String>,
+    }
+
+    impl Transport for RecordingTransport {
+        async fn fetch(&self, url: &str, query: &[(&str, &str)], _: &str) -> Result<String> {
+            let query = query
+                .iter()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect::<Vec<_>>();
+            *self.last_url.lock().unwrap() = format!("{url}?{}", query.join("&"));
+            Ok(self.body.to_string())
+        }
+    }
+
+    #[test]
+    fn test_transport() {
+        let client = BlockingYdClient::new(RecordingTransport {
+            body: r#"<div class="no-data-prompt">没有找到</div>"#,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            "YdResponse('<+*>?_')",
+            format!("{}", client.lookup_word("<+*>?_").unwrap())
+        );
+        assert_eq!(
+            format!("{DEFAULT_ENDPOINT}/result?word=<+*>?_&lang=en"),
+            *client.client.last_url.lock().unwrap()
+        );
+
+        let client = BlockingYdClient::new(RecordingTransport {
+            body: r#"{"errorCode":0,"translateResult":[[{"tgt":"你好。"}]]}"#,
+            ..Default::default()
+        })
+        .unwrap();
+        let resp = client
+            .translate("Hello.", Direction::detect("Hello."))
+            .unwrap();
+        assert_eq!(vec!["你好。"], resp.translation());
+        assert_eq!(
+            "https://fanyi.youdao.com/translate?doctype=json&type=EN2ZH_CN&i=Hello.",
+            *client.client.last_url.lock().unwrap()
+        );
+    }
+
     #[test]
     fn test_lookup_word_0() {
         assert_eq!(
             "YdResponse('hello')",
-            format!("{}", CLIENT.lookup_word("hello").unwrap())
+            format!("{}", YD_CLIENT.lookup_word("hello").unwrap())
         );
     }
 
@@ -67,7 +664,7 @@ mod tests {
     fn test_lookup_word_1() {
         assert_eq!(
             "YdResponse('world')",
-            format!("{}", CLIENT.lookup_word("world").unwrap())
+            format!("{}", YD_CLIENT.lookup_word("world").unwrap())
         );
     }
 
@@ -75,7 +672,7 @@ mod tests {
     fn test_lookup_word_2() {
         assert_eq!(
             "YdResponse('<+*>?_')",
-            format!("{}", CLIENT.lookup_word("<+*>?_").unwrap())
+            format!("{}", YD_CLIENT.lookup_word("<+*>?_").unwrap())
         );
     }
 }
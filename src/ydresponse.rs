@@ -1,25 +1,251 @@
 //! parser for the returned result from YD
 
-use crate::{formatters::Formatter, lang::is_chinese};
-use anyhow::{Result, anyhow};
-use scraper::{Html, Selector, error::SelectorErrorKind};
+use crate::{
+    error::{Result, YdcvError},
+    formatters::{Formatter, Line, Segment, Style},
+    lang::{Direction, Lang, PinyinStyle, detect_lang, is_chinese, is_japanese, pinyin, romanize},
+    phonetic::{self, PhoneticStyle},
+};
+use clap::ValueEnum;
+use scraper::{ElementRef, Html, Selector};
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::path::Path;
+use std::{fs, io};
+use url::Url;
+
+/// A `&'static Selector` compiled on first use, for the hot path of the
+/// clipboard loop
+macro_rules! selector {
+    ($css:literal) => {{
+        static SELECTOR: std::sync::LazyLock<Selector> =
+            std::sync::LazyLock::new(|| Selector::parse($css).unwrap());
+        &*SELECTOR
+    }};
+}
+
+const DICTVOICE: &str = "https://dict.youdao.com/dictvoice";
+
+/// Pronunciation variant
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Accent {
+    Uk,
+    #[default]
+    Us,
+}
+
+impl Accent {
+    pub fn name(self) -> &'static str {
+        match self {
+            Accent::Uk => "uk",
+            Accent::Us => "us",
+        }
+    }
+
+    /// URL of YD's pronunciation audio of `word`
+    pub fn speech_url(self, word: &str) -> String {
+        let voice_type = match self {
+            Accent::Uk => "1",
+            Accent::Us => "2",
+        };
+
+        Url::parse_with_params(DICTVOICE, [("audio", word), ("type", voice_type)])
+            .map(String::from)
+            .unwrap_or_default()
+    }
+}
+
+/// Options changing what `YdResponse::explain_with` shows
+#[derive(Clone, Default)]
+pub struct ExplainOptions {
+    /// Pronunciation whose phonetic is shown first and highlighted
+    pub accent: Option<Accent>,
+    /// Maximum number of example sentences to show
+    pub examples: usize,
+    /// Whether to show the senses from the Collins dictionary
+    pub collins: bool,
+    /// Whether to show the senses from the Chinese-English dictionary
+    pub ce_dict: bool,
+    /// Whether to show the inflected forms and the lemma of a form
+    pub forms: bool,
+    /// Whether to show the synonyms and the antonyms
+    pub synonyms: bool,
+    /// Maximum number of common phrases to show
+    pub phrases: usize,
+    /// Style of the per-character pinyin of Chinese queries, `None` to hide it
+    pub pinyin: Option<PinyinStyle>,
+    /// Show English definitions from the Collins dictionary instead of
+    /// Chinese explanations, and hide every Chinese gloss
+    pub en_en: bool,
+    /// Only show the senses with one of these labels, e.g. `计`, all of them
+    /// if empty
+    pub sense_labels: Vec<String>,
+    /// Add a romanized line under non-Latin readings and explanations
+    pub romanize: bool,
+    /// Notation of the phonetics in the headword line
+    pub phonetics: PhoneticStyle,
+    /// Labels replacing the default ones of sections, by the names in
+    /// `LABELS`
+    pub labels: BTreeMap<String, String>,
+}
+
+/// Names of the section labels `ExplainOptions::labels` can replace
+pub const LABELS: [&str; 15] = [
+    "did_you_mean",
+    "translation",
+    "pinyin",
+    "romanization",
+    "lemma",
+    "forms",
+    "definitions",
+    "word_explanation",
+    "web_reference",
+    "synonyms",
+    "antonyms",
+    "phrases",
+    "collins",
+    "ce_dict",
+    "examples",
+];
+
+impl ExplainOptions {
+    /// Label of the section `name`, like `default` with its indent
+    fn label(&self, name: &str, default: &'static str) -> Cow<'static, str> {
+        match self.labels.get(name) {
+            Some(label) => {
+                let indent = default.len() - default.trim_start().len();
+                Cow::Owned(format!("{}{label}", &default[..indent]))
+            }
+            None => Cow::Borrowed(default),
+        }
+    }
+}
 
 /// Basic result structure
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct YdBasic {
     explains: Vec<String>,
     phonetic: Option<String>,
     us_phonetic: Option<String>,
     uk_phonetic: Option<String>,
+    #[serde(default)]
+    us_speech: Option<String>,
+    #[serde(default)]
+    uk_speech: Option<String>,
+    /// `explains` split into part of speech, labels and meaning
+    #[serde(default)]
+    senses: Vec<YdSense>,
+}
+
+/// Sense of a word with its domain and register labels kept apart
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct YdSense {
+    pub pos: Option<String>,
+    /// Labels with their brackets, e.g. `〔计〕` or `〈口〉`
+    pub labels: Vec<String>,
+    pub meaning: String,
+}
+
+impl YdSense {
+    /// Split the leading labels off `text`
+    fn new(pos: Option<&str>, text: &str) -> Self {
+        let mut labels = vec![];
+        let mut rest = text.trim();
+        while let Some(close) = ["〔", "〈", "【"]
+            .iter()
+            .zip(["〕", "〉", "】"])
+            .find_map(|(open, close)| rest.starts_with(open).then_some(close))
+            && let Some(end) = rest.find(close)
+        {
+            let end = end + close.len();
+            labels.push(rest[..end].to_string());
+            rest = rest[end..].trim_start();
+        }
+
+        YdSense {
+            pos: pos.map(|x| x.to_string()),
+            labels,
+            meaning: rest.to_string(),
+        }
+    }
+
+    /// Whether the sense has the label `name`, given with or without brackets
+    pub fn has_label(&self, name: &str) -> bool {
+        let bare = |x: &str| {
+            x.trim_matches(['〔', '〕', '〈', '〉', '【', '】'])
+                .to_string()
+        };
+        let name = bare(name);
+        self.labels.iter().any(|x| bare(x) == name)
+    }
 }
 
 /// Web result structure
 #[derive(Serialize, Deserialize, Debug)]
 pub struct YdWeb {
-    key: String,
-    value: Vec<String>,
+    pub key: String,
+    pub value: Vec<String>,
+}
+
+/// Bilingual example sentence
+#[derive(Serialize, Deserialize, Debug)]
+pub struct YdExample {
+    pub sentence: String,
+    pub translation: String,
+}
+
+/// Inflected form of a word, e.g. 复数 (plural)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct YdWordForm {
+    pub name: String,
+    pub value: String,
+}
+
+/// Synonyms or antonyms for one meaning of the word
+#[derive(Serialize, Deserialize, Debug)]
+pub struct YdThesaurus {
+    pub meaning: Option<String>,
+    pub words: Vec<String>,
+}
+
+/// Common phrase containing the word
+#[derive(Serialize, Deserialize, Debug)]
+pub struct YdPhrase {
+    pub phrase: String,
+    pub translation: String,
+}
+
+/// Numbered sense of an authoritative dictionary such as Collins
+#[derive(Serialize, Deserialize, Debug)]
+pub struct YdDictSense {
+    pub pos: Option<String>,
+    pub definition: String,
+    pub examples: Vec<YdExample>,
+}
+
+/// Language detected for the query and the direction it was looked up in
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct YdLookup {
+    pub detected: Lang,
+    /// See `Detection::confidence`
+    pub confidence: f32,
+    pub from: Lang,
+    pub to: Lang,
+}
+
+impl YdLookup {
+    fn new(query: &str, dir: Direction) -> Self {
+        let detection = detect_lang(query);
+        YdLookup {
+            detected: detection.lang,
+            confidence: detection.confidence,
+            from: dir.from,
+            to: dir.to,
+        }
+    }
 }
 
 /// Full response structure
@@ -27,198 +253,1121 @@ pub struct YdWeb {
 #[serde(rename_all = "camelCase")]
 pub struct YdResponse {
     query: String,
+    /// Spelling corrections offered when there's no entry for the query
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    suggestions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lookup: Option<YdLookup>,
     #[serde(flatten)]
     inner: Option<YdResponseInner>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 struct YdResponseInner {
     translation: Vec<String>,
     basic: YdBasic,
     web: Vec<YdWeb>,
+    #[serde(default)]
+    exams: Vec<String>,
+    #[serde(default)]
+    examples: Vec<YdExample>,
+    #[serde(default)]
+    collins: Vec<YdDictSense>,
+    /// Senses of the 新汉英大辞典 for Chinese queries
+    #[serde(default)]
+    ce_senses: Vec<YdDictSense>,
+    #[serde(default)]
+    phrases: Vec<YdPhrase>,
+    #[serde(default)]
+    forms: Vec<YdWordForm>,
+    #[serde(default)]
+    synonyms: Vec<YdThesaurus>,
+    #[serde(default)]
+    antonyms: Vec<YdThesaurus>,
+    /// Base form when the query itself is an inflected form
+    #[serde(default)]
+    lemma: Option<String>,
+    /// Whether this came from the translation endpoint
+    #[serde(default)]
+    translated: bool,
+}
+
+/// Trimmed text of the first non-empty match of `selector` under `element`
+fn first_text(element: ElementRef, selector: &Selector) -> Option<String> {
+    element
+        .select(selector)
+        .map(|x| x.text().collect::<String>().trim().to_string())
+        .find(|x| !x.is_empty())
+}
+
+/// Blocks of a result page, gathered in a single walk over the document so
+/// that each field is then only searched for inside its own block
+#[derive(Default)]
+struct Sections<'a> {
+    no_data: bool,
+    typo: Vec<ElementRef<'a>>,
+    phone: Vec<ElementRef<'a>>,
+    basic: Vec<ElementRef<'a>>,
+    web: Vec<ElementRef<'a>>,
+    exams: Vec<ElementRef<'a>>,
+    examples: Vec<ElementRef<'a>>,
+    collins: Vec<ElementRef<'a>>,
+    ce_senses: Vec<ElementRef<'a>>,
+    phrases: Vec<ElementRef<'a>>,
+    forms: Vec<ElementRef<'a>>,
+    synonyms: Vec<ElementRef<'a>>,
+    antonyms: Vec<ElementRef<'a>>,
+}
+
+impl<'a> Sections<'a> {
+    fn find(html: &'a Html) -> Self {
+        let mut sections = Sections::default();
+        for x in html
+            .root_element()
+            .descendants()
+            .filter_map(ElementRef::wrap)
+        {
+            for class in x.value().classes() {
+                let section = match class {
+                    "no-data-prompt" => {
+                        sections.no_data |= x.text().next().is_some();
+                        continue;
+                    }
+                    "typo-rel" => &mut sections.typo,
+                    "phone_con" => &mut sections.phone,
+                    "basic" => &mut sections.basic,
+                    "web_trans" => &mut sections.web,
+                    "exam_type" => &mut sections.exams,
+                    "blng_sents_part" => &mut sections.examples,
+                    "collins" => &mut sections.collins,
+                    "ce_new" => &mut sections.ce_senses,
+                    "phrs" => &mut sections.phrases,
+                    "word-wfs-less" => &mut sections.forms,
+                    "syno" => &mut sections.synonyms,
+                    "antonym" => &mut sections.antonyms,
+                    _ => continue,
+                };
+                // blocks come in document order, so a nested one can only be
+                // inside the last one found
+                if !section
+                    .last()
+                    .is_some_and(|last| x.ancestors().any(|a| a.id() == last.id()))
+                {
+                    section.push(x);
+                }
+            }
+        }
+
+        sections
+    }
+
+    /// Whether the page has none of the blocks, e.g. an error or captcha page
+    fn is_empty(&self) -> bool {
+        !self.no_data
+            && [
+                &self.typo,
+                &self.phone,
+                &self.basic,
+                &self.web,
+                &self.exams,
+                &self.examples,
+                &self.collins,
+                &self.ce_senses,
+                &self.phrases,
+                &self.forms,
+                &self.synonyms,
+                &self.antonyms,
+            ]
+            .iter()
+            .all(|x| x.is_empty())
+    }
+}
+
+/// Matches of `selector` inside any of the `sections`
+fn select_in<'a>(
+    sections: &[ElementRef<'a>],
+    selector: &'static Selector,
+) -> impl Iterator<Item = ElementRef<'a>> {
+    sections.iter().flat_map(move |x| x.select(selector))
+}
+
+/// Names YD gives to inflected forms in explanations like `go的过去式`
+const FORM_NAMES: &[&str] = &[
+    "过去式",
+    "过去分词",
+    "现在分词",
+    "第三人称单数",
+    "复数",
+    "比较级",
+    "最高级",
+];
+
+/// Base form mentioned in an explanation of an inflected form, e.g. `go` in
+/// `v. 去（go 的过去式）`
+fn lemma_of(explain: &str) -> Option<String> {
+    explain.match_indices('的').find_map(|(i, _)| {
+        let rest = &explain[i + '的'.len_utf8()..];
+        if !FORM_NAMES.iter().any(|x| rest.starts_with(x)) {
+            return None;
+        }
+
+        let lemma = explain[..i]
+            .trim_end()
+            .rsplit(|c: char| !(c.is_ascii_alphabetic() || c == '-' || c == '\''))
+            .next()?;
+        (!lemma.is_empty()).then(|| lemma.to_string())
+    })
+}
+
+/// `text` without Chinese characters, for definitions followed by a gloss
+fn english_only(text: &str) -> String {
+    text.split_whitespace()
+        .filter(|x| !is_chinese(x))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Where the lines of an explanation are written a segment at a time, so
+/// that rendering them needs no intermediate lines
+trait Sink<'a> {
+    /// Start a new line
+    fn start(&mut self) -> fmt::Result;
+
+    /// Add `segment` to the current line
+    fn push(&mut self, segment: Segment<'a>) -> fmt::Result;
+
+    /// Add `segments` to the current line
+    fn extend<const N: usize>(&mut self, segments: [Segment<'a>; N]) -> fmt::Result {
+        segments.into_iter().try_for_each(|x| self.push(x))
+    }
+
+    /// Start a new line with `segments`
+    fn line<const N: usize>(&mut self, segments: [Segment<'a>; N]) -> fmt::Result {
+        self.start()?;
+        self.extend(segments)
+    }
+}
+
+/// Lines kept as they are, see `YdResponse::segments`
+struct Collect<'a>(Vec<Line<'a>>);
+
+impl<'a> Sink<'a> for Collect<'a> {
+    fn start(&mut self) -> fmt::Result {
+        self.0.push(vec![]);
+        Ok(())
+    }
+
+    fn push(&mut self, segment: Segment<'a>) -> fmt::Result {
+        self.0
+            .last_mut()
+            .expect("a line is started first")
+            .push(segment);
+        Ok(())
+    }
+}
+
+/// Lines rendered with `fmt` into `out` as they come, separated by newlines
+struct Render<'w, W> {
+    out: &'w mut W,
+    fmt: &'w dyn Formatter,
+    empty: bool,
+}
+
+impl<W: Write> Sink<'_> for Render<'_, W> {
+    fn start(&mut self) -> fmt::Result {
+        if !self.empty {
+            self.out.write_char('\n')?;
+        }
+        self.empty = false;
+        Ok(())
+    }
+
+    fn push(&mut self, segment: Segment<'_>) -> fmt::Result {
+        self.out.write_str(&segment.render(self.fmt))
+    }
+}
+
+/// Add `items` to the current line with `sep` between them
+fn push_joined<'a>(
+    out: &mut impl Sink<'a>,
+    sep: &'static str,
+    items: impl IntoIterator<Item = Segment<'a>>,
+) -> fmt::Result {
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            out.push(Style::Plain.of(sep))?;
+        }
+        out.push(item)?;
+    }
+    Ok(())
+}
+
+/// Write the romanization of `text` as its own line if enabled and non-Latin
+fn push_romanized<'a>(
+    out: &mut impl Sink<'a>,
+    options: &ExplainOptions,
+    text: &str,
+) -> fmt::Result {
+    if !options.romanize {
+        return Ok(());
+    }
+    if let Some(roman) = romanize(text, options.pinyin.unwrap_or(PinyinStyle::Marks)) {
+        out.line([Style::Plain.of("       "), Style::Yellow.of(roman)])?;
+    }
+    Ok(())
+}
+
+/// Render numbered dictionary senses with their examples under `title`
+fn push_dict_senses<'a>(
+    out: &mut impl Sink<'a>,
+    title: Cow<'static, str>,
+    senses: &'a [YdDictSense],
+    en_en: bool,
+) -> fmt::Result {
+    if senses.is_empty() {
+        return Ok(());
+    }
+
+    out.line([Style::Cyan.of(title)])?;
+    for (i, sense) in senses.iter().enumerate() {
+        let pos = sense
+            .pos
+            .as_ref()
+            .map(|x| format!("[{x}] "))
+            .unwrap_or_default();
+        let definition = if en_en {
+            Cow::Owned(english_only(&sense.definition))
+        } else {
+            Cow::Borrowed(&sense.definition)
+        };
+        out.line([Style::Default.of(format!("     {}. {pos}{definition}", i + 1))])?;
+        for example in &sense.examples {
+            out.line([
+                Style::Plain.of("        e.g. "),
+                Style::Yellow.of(&example.sentence),
+            ])?;
+            if !en_en {
+                out.line([
+                    Style::Plain.of("             "),
+                    Style::Purple.of(&example.translation),
+                ])?;
+            }
+        }
+    }
+    Ok(())
 }
 
 impl YdResponse {
-    pub fn from_html(body: &str, word: &str) -> Result<Self> {
+    /// Parse the result page of looking up `word` in direction `dir`
+    pub fn from_html(body: &str, word: &str, dir: Direction) -> Result<Self> {
         let html = Html::parse_document(body);
-        let is_chinese = is_chinese(word);
+        let sections = Sections::find(&html);
 
-        let no_data = Selector::parse(".no-data-prompt").map_err(|e| anyhow!("{e}"))?;
-        let mut is_no_data = false;
-        html.select(&no_data).for_each(|x| {
-            x.text().for_each(|_| {
-                is_no_data = true;
-            });
-        });
+        if sections.is_empty() {
+            let body = body.to_lowercase();
+            if body.contains("captcha") || body.contains("验证码") {
+                return Err(YdcvError::Captcha);
+            }
+        }
 
-        if is_no_data {
+        let suggestions = Self::parse_suggestions(&sections.typo);
+        // pages without any known block are taken for no result too
+        if sections.no_data || sections.is_empty() {
             return Ok(YdResponse {
                 query: word.to_string(),
+                suggestions,
+                lookup: Some(YdLookup::new(word, dir)),
                 inner: None,
             });
         }
 
-        let res = if is_chinese {
-            Self::zh2en(&html)
-        } else {
-            Self::en2zh(&html)
+        let mut res = match dir.from {
+            Lang::Zh => Self::zh2en(&sections),
+            Lang::En => Self::en2zh(&sections),
+            _ => Self::ja2zh(&sections),
+        };
+
+        res.exams = Self::parse_exams(&sections.exams);
+        res.examples = Self::parse_examples(&sections.examples);
+        res.collins = Self::parse_dict_senses(&sections.collins);
+        res.ce_senses = Self::parse_dict_senses(&sections.ce_senses);
+        res.phrases = Self::parse_phrases(&sections.phrases);
+        res.forms = Self::parse_forms(&sections.forms);
+        res.synonyms = Self::parse_thesaurus(&sections.synonyms);
+        res.antonyms = Self::parse_thesaurus(&sections.antonyms);
+        res.lemma = res
+            .basic
+            .explains
+            .iter()
+            .find_map(|x| lemma_of(x))
+            .filter(|x| !x.eq_ignore_ascii_case(word));
+        if dir.from == Lang::En {
+            res.basic.us_speech = Some(Accent::Us.speech_url(word));
+            res.basic.uk_speech = Some(Accent::Uk.speech_url(word));
         }
-        .map_err(|e| anyhow!("{e}"))?;
+
+        // a typo-correction page has no entry but isn't marked as no data
+        let is_empty =
+            res.translation.is_empty() && res.basic.explains.is_empty() && res.web.is_empty();
 
         Ok(YdResponse {
             query: word.to_string(),
-            inner: Some(res),
+            lookup: Some(YdLookup::new(word, dir)),
+            inner: (!is_empty || suggestions.is_empty()).then_some(res),
+            suggestions,
         })
     }
 
+    /// Whether the dictionary pages of `dir` have a parser, the other
+    /// directions being translated instead until their pages are known
+    pub fn parses_pages(dir: Direction) -> bool {
+        matches!(
+            (dir.from, dir.to),
+            (Lang::Zh, Lang::En) | (Lang::En, Lang::Zh) | (Lang::Ja, Lang::Zh)
+        )
+    }
+
+    /// Parse the result page of `word` saved at `path`, e.g. by `--dump-html`,
+    /// in the direction detected for `word`
+    pub fn from_html_file(path: impl AsRef<Path>, word: &str) -> Result<Self> {
+        let path = path.as_ref();
+        let body = fs::read_to_string(path).map_err(|e| {
+            io::Error::new(e.kind(), format!("Failed to read {}: {e}", path.display()))
+        })?;
+        Self::from_html(&body, word, Direction::detect(word))
+    }
+
+    /// A plain translation of a sentence in direction `dir`
+    pub fn from_translation(query: &str, translation: Vec<String>, dir: Direction) -> Self {
+        YdResponse {
+            query: query.to_string(),
+            suggestions: vec![],
+            lookup: Some(YdLookup::new(query, dir)),
+            inner: Some(YdResponseInner {
+                translation,
+                translated: true,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// The word or phrase that was looked up
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Words YD suggests when the query looks misspelled
+    pub fn suggestions(&self) -> &[String] {
+        &self.suggestions
+    }
+
+    /// Whether YD has any result for the query
+    pub fn has_result(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Whether this is a plain translation of a sentence rather than a
+    /// dictionary entry
+    pub fn is_translation(&self) -> bool {
+        self.inner.as_ref().is_some_and(|x| x.translated)
+    }
+
+    /// Short translations of the query, empty if there's no result
+    pub fn translation(&self) -> &[String] {
+        self.inner
+            .as_ref()
+            .map(|x| x.translation.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Preferred phonetic, or else the US or the UK one, if any
+    pub fn phonetic(&self) -> Option<&str> {
+        let basic = &self.inner.as_ref()?.basic;
+        [&basic.phonetic, &basic.us_phonetic, &basic.uk_phonetic]
+            .into_iter()
+            .filter_map(|x| x.as_deref())
+            .find(|x| !x.is_empty())
+    }
+
+    /// Detailed explanations, one per sense
+    pub fn explains(&self) -> &[String] {
+        self.inner
+            .as_ref()
+            .map(|x| x.basic.explains.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Web reference phrases with their translations
+    pub fn web(&self) -> &[YdWeb] {
+        self.inner
+            .as_ref()
+            .map(|x| x.web.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Exams whose word lists include the word, e.g. CET4 or 考研
+    pub fn exams(&self) -> &[String] {
+        self.inner
+            .as_ref()
+            .map(|x| x.exams.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Bilingual example sentences
+    pub fn examples(&self) -> &[YdExample] {
+        self.inner
+            .as_ref()
+            .map(|x| x.examples.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Phonetic in `accent`, if YD gives the accents apart
+    pub fn accent_phonetic(&self, accent: Accent) -> Option<&str> {
+        self.inner
+            .as_ref()
+            .and_then(|x| match accent {
+                Accent::Uk => x.basic.uk_phonetic.as_deref(),
+                Accent::Us => x.basic.us_phonetic.as_deref(),
+            })
+            .filter(|x| !x.is_empty())
+    }
+
+    /// `explains` split into part of speech, labels and meaning
+    pub fn senses(&self) -> &[YdSense] {
+        self.inner
+            .as_ref()
+            .map(|x| x.basic.senses.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Senses of the Collins dictionary
+    pub fn collins(&self) -> &[YdDictSense] {
+        self.inner
+            .as_ref()
+            .map(|x| x.collins.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Senses of the 新汉英大辞典 for Chinese queries
+    pub fn ce_senses(&self) -> &[YdDictSense] {
+        self.inner
+            .as_ref()
+            .map(|x| x.ce_senses.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Common phrases containing the word
+    pub fn phrases(&self) -> &[YdPhrase] {
+        self.inner
+            .as_ref()
+            .map(|x| x.phrases.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Inflected forms of the word
+    pub fn forms(&self) -> &[YdWordForm] {
+        self.inner
+            .as_ref()
+            .map(|x| x.forms.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Synonyms grouped by meaning
+    pub fn synonyms(&self) -> &[YdThesaurus] {
+        self.inner
+            .as_ref()
+            .map(|x| x.synonyms.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Antonyms grouped by meaning
+    pub fn antonyms(&self) -> &[YdThesaurus] {
+        self.inner
+            .as_ref()
+            .map(|x| x.antonyms.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Base form when the query itself is an inflected form
+    pub fn lemma(&self) -> Option<&str> {
+        self.inner.as_ref().and_then(|x| x.lemma.as_deref())
+    }
+
+    /// Languages the query was detected in and looked up between, missing
+    /// from responses cached before they were recorded
+    pub fn lookup(&self) -> Option<&YdLookup> {
+        self.lookup.as_ref()
+    }
+
     /// Explain the result in text format using a formatter
     pub fn explain(&self, fmt: &dyn Formatter) -> String {
-        let mut result: Vec<String> = vec![];
+        self.explain_with(fmt, &ExplainOptions::default())
+    }
 
-        match &self.inner {
-            Some(YdResponseInner {
-                translation,
-                basic,
-                web,
-            }) => {
-                if web.is_empty() {
-                    result.push(fmt.underline(&self.query));
-                    result.push(fmt.cyan("  Translation:"));
-                    result.push(format!("    {}", translation.join("；")));
-                    return result.join("\n");
+    /// Explain the result in text format using a formatter and options
+    pub fn explain_with(&self, fmt: &dyn Formatter, options: &ExplainOptions) -> String {
+        let mut result = String::new();
+        self.explain_to(&mut result, fmt, options)
+            .expect("writing to a String can't fail");
+        result
+    }
+
+    /// Like `explain_with`, but write the explanation to `out` as it is
+    /// built, without collecting its lines
+    pub fn explain_to(
+        &self,
+        out: &mut impl Write,
+        fmt: &dyn Formatter,
+        options: &ExplainOptions,
+    ) -> fmt::Result {
+        self.write_segments(
+            &mut Render {
+                out,
+                fmt,
+                empty: true,
+            },
+            options,
+        )
+    }
+
+    /// The explanation as lines of styled segments, which `explain_with`
+    /// renders with a formatter
+    pub fn segments(&self, options: &ExplainOptions) -> Vec<Line<'_>> {
+        let mut lines = Collect(vec![]);
+        self.write_segments(&mut lines, options)
+            .expect("collecting segments can't fail");
+        lines.0
+    }
+
+    /// Write the segments of the explanation to `out`, line by line
+    fn write_segments<'a>(
+        &'a self,
+        out: &mut impl Sink<'a>,
+        options: &ExplainOptions,
+    ) -> fmt::Result {
+        let Some(YdResponseInner {
+            translation,
+            basic,
+            web,
+            exams,
+            examples,
+            collins,
+            ce_senses,
+            phrases,
+            forms,
+            synonyms,
+            antonyms,
+            lemma,
+            translated,
+        }) = &self.inner
+        else {
+            out.line([Style::Red.of(" -- No result for this query.")])?;
+            if !self.suggestions.is_empty() {
+                out.line([
+                    Style::Cyan.of(options.label("did_you_mean", "  Did you mean:")),
+                    Style::Plain.of(" "),
+                ])?;
+                push_joined(
+                    out,
+                    ", ",
+                    self.suggestions.iter().map(|x| Style::Yellow.of(x)),
+                )?;
+            }
+            return Ok(());
+        };
+
+        if *translated {
+            out.line([Style::Underline.of(&self.query)])?;
+            out.line([Style::Cyan.of(options.label("translation", "  Translation:"))])?;
+            let translation = translation.join("；");
+            out.line([Style::Plain.of(format!("    {translation}"))])?;
+            push_romanized(out, options, &translation)?;
+            return Ok(());
+        }
+
+        out.line([Style::Underline.of(&self.query)])?;
+        if !exams.is_empty() {
+            out.push(Style::Plain.of(" "))?;
+            out.push(Style::Purple.of(format!("[{}]", exams.join(" "))))?;
+        }
+        out.push(Style::Plain.of(" "))?;
+        let normalize = |x: &str| phonetic::normalize(x, options.phonetics);
+        if let (Some(us_phonetic), Some(uk_phonetic)) = (&basic.us_phonetic, &basic.uk_phonetic) {
+            let (us_phonetic, uk_phonetic) = (normalize(us_phonetic), normalize(uk_phonetic));
+            let (first, second) = match options.accent {
+                None => (
+                    (" UK: [", Style::Yellow.of(uk_phonetic)),
+                    ("], US: [", Style::Yellow.of(us_phonetic)),
+                ),
+                Some(Accent::Uk) => (
+                    (" UK: [", Style::Yellow.of(uk_phonetic)),
+                    ("], US: [", Style::Default.of(us_phonetic)),
+                ),
+                Some(Accent::Us) => (
+                    (" US: [", Style::Yellow.of(us_phonetic)),
+                    ("], UK: [", Style::Default.of(uk_phonetic)),
+                ),
+            };
+            out.extend([
+                Style::Plain.of(first.0),
+                first.1,
+                Style::Plain.of(second.0),
+                second.1,
+                Style::Plain.of("]"),
+            ])?;
+        } else if let Some(phonetic) = &basic.phonetic {
+            out.extend([
+                Style::Plain.of("["),
+                Style::Yellow.of(normalize(phonetic)),
+                Style::Plain.of("]"),
+            ])?;
+        }
+        out.push(Style::Plain.of(" "))?;
+        out.push(Style::Default.of(if options.en_en {
+            String::new()
+        } else {
+            translation.join("; ")
+        }))?;
+
+        if let Some(style) = options.pinyin
+            && is_chinese(&self.query)
+            && !is_japanese(&self.query)
+        {
+            out.line([
+                Style::Plain.of("  "),
+                Style::Cyan.of(options.label("pinyin", "Pinyin:")),
+                Style::Plain.of(" "),
+                Style::Yellow.of(pinyin(&self.query, style)),
+            ])?;
+        }
+
+        if options.romanize {
+            let style = options.pinyin.unwrap_or(PinyinStyle::Marks);
+            // Chinese queries already have their pinyin line
+            let roman = basic
+                .phonetic
+                .as_deref()
+                .and_then(|x| romanize(x, style))
+                .or_else(|| {
+                    (!is_chinese(&self.query) || is_japanese(&self.query))
+                        .then(|| romanize(&self.query, style))
+                        .flatten()
+                });
+            if let Some(roman) = roman {
+                out.line([
+                    Style::Plain.of("  "),
+                    Style::Cyan.of(options.label("romanization", "Romanization:")),
+                    Style::Plain.of(" "),
+                    Style::Yellow.of(roman),
+                ])?;
+            }
+        }
+
+        if let Some(lemma) = lemma.as_ref().filter(|_| options.forms) {
+            out.line([
+                Style::Plain.of("  "),
+                Style::Cyan.of(options.label("lemma", "Lemma:")),
+                Style::Plain.of(" "),
+                Style::Yellow.of(lemma),
+            ])?;
+        }
+
+        if options.forms && !forms.is_empty() {
+            out.line([
+                Style::Plain.of("  "),
+                Style::Cyan.of(options.label("forms", "Forms:")),
+                Style::Plain.of(" "),
+            ])?;
+            for (i, form) in forms.iter().enumerate() {
+                if i > 0 {
+                    out.push(Style::Plain.of("; "))?;
                 }
+                out.extend([
+                    Style::Default.of(&form.name),
+                    Style::Plain.of(" "),
+                    Style::Yellow.of(&form.value),
+                ])?;
+            }
+        }
 
-                let phonetic = if let (Some(us_phonetic), Some(uk_phonetic)) =
-                    (&basic.us_phonetic, &basic.uk_phonetic)
-                {
-                    format!(
-                        " UK: [{}], US: [{}]",
-                        fmt.yellow(uk_phonetic),
-                        fmt.yellow(us_phonetic)
-                    )
-                    .into()
-                } else if let Some(phonetic) = &basic.phonetic {
-                    format!("[{}]", fmt.yellow(phonetic)).into()
+        if options.en_en {
+            if collins.is_empty() {
+                out.line([Style::Red.of("  No English definitions for this word.")])?;
+            }
+            push_dict_senses(
+                out,
+                options.label("definitions", "  Definitions:"),
+                collins,
+                true,
+            )?;
+        } else if !basic.senses.is_empty() {
+            let senses = basic
+                .senses
+                .iter()
+                .filter(|x| {
+                    options.sense_labels.is_empty()
+                        || options.sense_labels.iter().any(|y| x.has_label(y))
+                })
+                .collect::<Vec<_>>();
+            if !senses.is_empty() {
+                out.line([
+                    Style::Cyan.of(options.label("word_explanation", "  Word Explanation:"))
+                ])?;
+            }
+            for sense in senses {
+                let pos = sense
+                    .pos
+                    .as_ref()
+                    .map(|x| format!("{x} "))
+                    .unwrap_or_default();
+                if sense.labels.is_empty() {
+                    out.line([Style::Default.of(format!("     * {pos}{}", sense.meaning))])?;
                 } else {
-                    Cow::Borrowed("")
-                };
+                    out.line([
+                        Style::Default.of(format!("     * {pos}")),
+                        Style::Purple.of(sense.labels.join("")),
+                        Style::Plain.of(" "),
+                        Style::Default.of(&sense.meaning),
+                    ])?;
+                }
+                push_romanized(out, options, &sense.meaning)?;
+            }
+        } else if !basic.explains.is_empty() {
+            out.line([Style::Cyan.of(options.label("word_explanation", "  Word Explanation:"))])?;
+            for exp in &basic.explains {
+                out.line([Style::Default.of(format!("     * {exp}"))])?;
+                push_romanized(out, options, exp)?;
+            }
+        }
 
-                result.push(format!(
-                    "{} {} {}",
-                    fmt.underline(&self.query),
-                    phonetic,
-                    fmt.default(&translation.join("; "))
-                ));
-
-                if !basic.explains.is_empty() {
-                    result.push(fmt.cyan("  Word Explanation:"));
-                    for exp in &basic.explains {
-                        result.push(fmt.default(&format!("     * {exp}")));
-                    }
+        if !web.is_empty() && !options.en_en {
+            out.line([Style::Cyan.of(options.label("web_reference", "  Web Reference:"))])?;
+            for item in web {
+                out.line([Style::Plain.of("     * "), Style::Yellow.of(&item.key)])?;
+                out.line([Style::Plain.of("       ")])?;
+                push_joined(out, "；", item.value.iter().map(|x| Style::Purple.of(x)))?;
+            }
+        }
+
+        for (title, groups) in [
+            (options.label("synonyms", "  Synonyms:"), synonyms),
+            (options.label("antonyms", "  Antonyms:"), antonyms),
+        ] {
+            if !options.synonyms || groups.is_empty() {
+                continue;
+            }
+            out.line([Style::Cyan.of(title)])?;
+            for group in groups {
+                out.line([Style::Plain.of("     * ")])?;
+                if let Some(meaning) = group.meaning.as_ref().filter(|_| !options.en_en) {
+                    out.extend([Style::Default.of(meaning), Style::Plain.of(" ")])?;
                 }
+                push_joined(out, ", ", group.words.iter().map(|x| Style::Yellow.of(x)))?;
+            }
+        }
 
-                if !web.is_empty() {
-                    result.push(fmt.cyan("  Web Reference:"));
-                    for item in web {
-                        result.push(format!("     * {}", &fmt.yellow(&item.key)));
-                        result.push(format!(
-                            "       {}",
-                            &item
-                                .value
-                                .iter()
-                                .map(|x| fmt.purple(x))
-                                .collect::<Vec<_>>()
-                                .join("；")
-                        ));
-                    }
+        if options.phrases > 0 && !phrases.is_empty() {
+            out.line([Style::Cyan.of(options.label("phrases", "  Phrases:"))])?;
+            for phrase in phrases.iter().take(options.phrases) {
+                out.line([Style::Plain.of("     * "), Style::Yellow.of(&phrase.phrase)])?;
+                if !options.en_en {
+                    out.extend([Style::Plain.of(" "), Style::Purple.of(&phrase.translation)])?;
                 }
             }
-            None => {
-                result.push(fmt.red(" -- No result for this query."));
-                return result.join("\n");
+        }
+
+        if !options.en_en {
+            if options.collins {
+                push_dict_senses(out, options.label("collins", "  Collins:"), collins, false)?;
+            }
+            if options.ce_dict {
+                push_dict_senses(
+                    out,
+                    options.label("ce_dict", "  新汉英大辞典:"),
+                    ce_senses,
+                    false,
+                )?;
             }
         }
 
-        result.join("\n")
+        if options.examples > 0 && !examples.is_empty() {
+            out.line([Style::Cyan.of(options.label("examples", "  Examples:"))])?;
+            for example in examples.iter().take(options.examples) {
+                out.line([
+                    Style::Plain.of("     * "),
+                    Style::Yellow.of(&example.sentence),
+                ])?;
+                if !options.en_en {
+                    out.line([
+                        Style::Plain.of("       "),
+                        Style::Purple.of(&example.translation),
+                    ])?;
+                }
+            }
+        }
+
+        Ok(())
     }
 
-    /// Lookup words by Chinese meaning.
-    fn zh2en(html: &Html) -> Result<YdResponseInner, SelectorErrorKind<'_>> {
-        let trans = Selector::parse(".basic .col2 .word-exp .point")?;
-        let mut translations = vec![];
-        html.select(&trans).for_each(|x| {
-            x.text().for_each(|x| {
-                translations.push(x.to_string());
+    /// Words of the typo-correction block
+    fn parse_suggestions(sections: &[ElementRef]) -> Vec<String> {
+        let word = selector!(".typo-rel .title");
+
+        let mut words = vec![];
+        for x in select_in(sections, word) {
+            let x = x.text().collect::<String>().trim().to_string();
+            if !x.is_empty() && !words.contains(&x) {
+                words.push(x);
+            }
+        }
+
+        words
+    }
+
+    /// Exam scopes listed under the headword
+    fn parse_exams(sections: &[ElementRef]) -> Vec<String> {
+        let exam = selector!(".exam_type .exam_type-value");
+
+        select_in(sections, exam)
+            .map(|x| x.text().collect::<String>().trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect()
+    }
+
+    /// Bilingual example sentences, the same for both directions
+    fn parse_examples(sections: &[ElementRef]) -> Vec<YdExample> {
+        let item = selector!(".blng_sents_part .trans-container li .col2");
+        let sentence = selector!(".sen-eng");
+        let translation = selector!(".sen-ch");
+
+        select_in(sections, item)
+            .filter_map(|x| {
+                Some(YdExample {
+                    sentence: first_text(x, sentence)?,
+                    translation: first_text(x, translation)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Inflected forms of the word
+    fn parse_forms(sections: &[ElementRef]) -> Vec<YdWordForm> {
+        let item = selector!(".word-wfs-less .word-wfs-cell-less");
+        let name = selector!(".wfs-name");
+        let value = selector!(".transformation");
+
+        select_in(sections, item)
+            .filter_map(|x| {
+                Some(YdWordForm {
+                    name: first_text(x, name)?,
+                    value: first_text(x, value)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Synonym or antonym groups in the `sections` blocks, each with its
+    /// meaning in the first column and the words linked in the second
+    fn parse_thesaurus(sections: &[ElementRef]) -> Vec<YdThesaurus> {
+        let item = selector!(".trans-container li");
+        let meaning = selector!(".col1");
+        let word = selector!(".col2 a");
+
+        select_in(sections, item)
+            .map(|x| YdThesaurus {
+                meaning: first_text(x, meaning),
+                words: x
+                    .select(word)
+                    .map(|x| x.text().collect::<String>().trim().to_string())
+                    .filter(|x| !x.is_empty())
+                    .collect(),
+            })
+            .filter(|x| !x.words.is_empty())
+            .collect()
+    }
+
+    /// Common phrases and idioms containing the word
+    fn parse_phrases(sections: &[ElementRef]) -> Vec<YdPhrase> {
+        let item = selector!(".phrs .trans-container li");
+        let phrase = selector!(".col1");
+        let translation = selector!(".col2");
+
+        select_in(sections, item)
+            .filter_map(|x| {
+                Some(YdPhrase {
+                    phrase: first_text(x, phrase)?,
+                    translation: first_text(x, translation)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Numbered senses with their examples in the `sections` blocks of a
+    /// dictionary such as Collins
+    fn parse_dict_senses(sections: &[ElementRef]) -> Vec<YdDictSense> {
+        let item = selector!(".trans-container > ul > li");
+        let pos = selector!(".title .pos");
+        let title = selector!(".title");
+        let example = selector!(".exampleLists .examples");
+        let sentence = selector!(".sen-eng");
+        let translation = selector!(".sen-ch");
+
+        select_in(sections, item)
+            .filter_map(|x| {
+                let pos = first_text(x, pos);
+                let definition = first_text(x, title)?;
+                let definition = match &pos {
+                    Some(pos) => definition
+                        .trim_start_matches(pos.as_str())
+                        .trim()
+                        .to_string(),
+                    None => definition,
+                };
+                let examples = x
+                    .select(example)
+                    .filter_map(|x| {
+                        Some(YdExample {
+                            sentence: first_text(x, sentence)?,
+                            translation: first_text(x, translation)?,
+                        })
+                    })
+                    .collect();
+
+                Some(YdDictSense {
+                    pos,
+                    definition,
+                    examples,
+                })
+            })
+            .collect()
+    }
+
+    /// Lookup Japanese words, with the kana reading as phonetic
+    fn ja2zh(sections: &Sections) -> YdResponseInner {
+        let reading = selector!(".phone_con .per-phone .phonetic");
+        let sense = selector!(".basic .word-exp");
+        let pos = selector!(".pos");
+        let trans = selector!(".trans");
+
+        let phonetic = select_in(&sections.phone, reading)
+            .map(|x| {
+                x.text()
+                    .collect::<String>()
+                    .replace('/', "")
+                    .trim()
+                    .to_string()
+            })
+            .find(|x| !x.is_empty());
+
+        let mut explains = vec![];
+        let mut senses = vec![];
+        for x in select_in(&sections.basic, sense) {
+            let pos = first_text(x, pos);
+            let Some(trans) = first_text(x, trans) else {
+                continue;
+            };
+            explains.push(match &pos {
+                Some(pos) => format!("{pos} {trans}"),
+                None => trans.clone(),
             });
-        });
+            senses.push(YdSense::new(pos.as_deref(), &trans));
+        }
+
+        YdResponseInner {
+            translation: senses
+                .first()
+                .and_then(|x| x.meaning.split(['；', '，']).next())
+                .map(|x| vec![x.trim().to_string()])
+                .unwrap_or_default(),
+            basic: YdBasic {
+                explains,
+                phonetic,
+                senses,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
 
+    /// Lookup words by Chinese meaning.
+    fn zh2en(sections: &Sections) -> YdResponseInner {
+        // every sense is an English word followed by its Chinese gloss
+        let sense = selector!(".basic .col2 .word-exp");
+        let point = selector!(".point");
+        let gloss = selector!(".word-exp_tran");
+        let mut translations: Vec<String> = vec![];
         let mut explains = vec![];
-        let explains_query = Selector::parse(".basic .col2 .word-exp .point")?;
-        html.select(&explains_query).for_each(|x| {
-            x.text().for_each(|x| {
-                explains.push(x.to_string());
+        for x in select_in(&sections.basic, sense) {
+            let Some(word) = first_text(x, point) else {
+                continue;
+            };
+            explains.push(match first_text(x, gloss) {
+                Some(gloss) => format!("{word} {gloss}"),
+                None => word.clone(),
             });
-        });
+            if !translations.contains(&word) {
+                translations.push(word);
+            }
+        }
 
         let mut phonetic = String::new();
-        let per_phone = Selector::parse(".phone_con .per-phone .phonetic")?;
-        html.select(&per_phone).for_each(|x| {
+        let per_phone = selector!(".phone_con .per-phone .phonetic");
+        select_in(&sections.phone, per_phone).for_each(|x| {
             x.text().for_each(|x| {
                 phonetic.push_str(x.replace('/', "").trim());
             });
         });
 
-        let mut keys = vec![];
-        let mut values = vec![];
-        let key = Selector::parse(".web_trans .col2 .point")?;
-        let value = Selector::parse(".web_trans .col2 .sen-phrase")?;
-        html.select(&key).for_each(|x| {
-            x.text().for_each(|x| {
-                keys.push(x);
-            });
-        });
-        html.select(&value).for_each(|x| {
-            let v = x
-                .text()
-                .collect::<String>()
-                .split(" ; ")
-                .map(|x| x.trim().to_string())
-                .collect::<Vec<_>>();
-            values.push(v);
-        });
-
-        let mut webs = vec![];
-
-        for (i, c) in keys.iter().enumerate() {
-            webs.push(YdWeb {
-                key: c.to_string(),
-                value: values[i].clone(),
-            });
-        }
-
-        let resp = YdResponseInner {
-            translation: translations
-                .first()
-                .map(|x| vec![x.to_string()])
-                .unwrap_or_default(),
+        YdResponseInner {
+            translation: translations,
             basic: YdBasic {
                 explains,
                 phonetic: Some(phonetic),
                 us_phonetic: None,
                 uk_phonetic: None,
+                us_speech: None,
+                uk_speech: None,
+                senses: vec![],
             },
-            web: webs,
-        };
+            web: Self::parse_web(&sections.web),
+            exams: vec![],
+            examples: vec![],
+            collins: vec![],
+            ce_senses: vec![],
+            phrases: vec![],
+            forms: vec![],
+            synonyms: vec![],
+            antonyms: vec![],
+            lemma: None,
+            translated: false,
+        }
+    }
 
-        Ok(resp)
+    /// Web translations, each a phrase with its translations
+    fn parse_web(sections: &[ElementRef]) -> Vec<YdWeb> {
+        let key = selector!(".web_trans .col2 .point");
+        let value = selector!(".web_trans .col2 .sen-phrase");
+
+        select_in(sections, key)
+            .zip(select_in(sections, value))
+            .map(|(key, value)| YdWeb {
+                key: key.text().collect(),
+                value: value
+                    .text()
+                    .collect::<String>()
+                    .split(" ; ")
+                    .map(|x| x.trim().to_string())
+                    .collect(),
+            })
+            .collect()
     }
 
     /// Lookup words by English word.
-    fn en2zh(html: &Html) -> Result<YdResponseInner, SelectorErrorKind<'_>> {
+    fn en2zh(sections: &Sections) -> YdResponseInner {
         let mut per_phone = vec![];
-        let phonetic = Selector::parse(".phone_con .per-phone")?;
-        html.select(&phonetic).for_each(|x| {
+        let phonetic = selector!(".phone_con .per-phone");
+        select_in(&sections.phone, phonetic).for_each(|x| {
             x.text().for_each(|x| {
                 per_phone.push(x.replace('/', "").trim().to_string());
             });
@@ -235,8 +1384,8 @@ impl YdResponse {
         }
 
         if us_phonetic.is_none() && uk_phonetic.is_none() {
-            let phonetic = Selector::parse(".phone_con .per-phone .phonetic")?;
-            html.select(&phonetic).for_each(|x| {
+            let phonetic = selector!(".phone_con .per-phone .phonetic");
+            select_in(&sections.phone, phonetic).for_each(|x| {
                 x.text().for_each(|x| {
                     per_phone.push(x.replace('/', "").trim().to_string());
                 });
@@ -244,16 +1393,16 @@ impl YdResponse {
         }
 
         let mut poss = vec![];
-        let pos = Selector::parse(".basic .word-exp .pos")?;
-        html.select(&pos).for_each(|x| {
+        let pos = selector!(".basic .word-exp .pos");
+        select_in(&sections.basic, pos).for_each(|x| {
             x.text().for_each(|x| {
                 poss.push(x.to_string());
             });
         });
 
         let mut translations = vec![];
-        let trans = Selector::parse(".basic .word-exp .trans")?;
-        html.select(&trans).for_each(|x| {
+        let trans = selector!(".basic .word-exp .trans");
+        select_in(&sections.basic, trans).for_each(|x| {
             x.text().for_each(|x| {
                 translations.push(x.to_string());
             });
@@ -270,36 +1419,13 @@ impl YdResponse {
                 }
             })
             .collect::<Vec<_>>();
+        let senses = translations
+            .iter()
+            .enumerate()
+            .map(|(i, c)| YdSense::new(poss.get(i).map(|x| x.as_str()), c))
+            .collect();
 
-        let mut keys = vec![];
-        let mut values = vec![];
-        let key = Selector::parse(".web_trans .col2 .point")?;
-        let value = Selector::parse(".web_trans .col2 .sen-phrase")?;
-        html.select(&key).for_each(|x| {
-            x.text().for_each(|x| {
-                keys.push(x);
-            });
-        });
-        html.select(&value).for_each(|x| {
-            let v = x
-                .text()
-                .collect::<String>()
-                .split(" ; ")
-                .map(|x| x.trim().to_string())
-                .collect::<Vec<_>>();
-            values.push(v);
-        });
-
-        let mut webs = vec![];
-
-        for (i, c) in keys.iter().enumerate() {
-            webs.push(YdWeb {
-                key: c.to_string(),
-                value: values[i].clone(),
-            });
-        }
-
-        let resp = YdResponseInner {
+        YdResponseInner {
             translation: translations
                 .first()
                 .and_then(|x| x.split('，').next())
@@ -314,18 +1440,38 @@ impl YdResponse {
                     .or_else(|| per_phone.first().map(|x| x.to_string())),
                 us_phonetic,
                 uk_phonetic,
+                us_speech: None,
+                uk_speech: None,
+                senses,
             },
-            web: webs,
-        };
-
-        Ok(resp)
+            web: Self::parse_web(&sections.web),
+            exams: vec![],
+            examples: vec![],
+            collins: vec![],
+            ce_senses: vec![],
+            phrases: vec![],
+            forms: vec![],
+            synonyms: vec![],
+            antonyms: vec![],
+            lemma: None,
+            translated: false,
+        }
     }
 }
 
 // For testing
 
 #[cfg(test)]
-use std::fmt;
+impl YdResponse {
+    /// A minimal response with a single translation
+    pub(crate) fn fake(query: &str, translation: &str) -> Self {
+        Self::from_translation(
+            query,
+            vec![translation.to_string()],
+            Direction::detect(query),
+        )
+    }
+}
 
 #[cfg(test)]
 impl fmt::Display for YdResponse {
@@ -333,3 +1479,560 @@ impl fmt::Display for YdResponse {
         write!(f, "YdResponse('{}')", self.query)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatters::{HtmlFormatter, PlainFormatter};
+
+    fn comment() -> YdResponse {
+        YdResponse {
+            query: "comment".to_string(),
+            suggestions: vec![],
+            lookup: None,
+            inner: Some(YdResponseInner {
+                translation: vec!["评论".to_string()],
+                basic: YdBasic {
+                    explains: vec![],
+                    phonetic: Some("ˈkɑːment".to_string()),
+                    us_phonetic: Some("ˈkɑːment".to_string()),
+                    uk_phonetic: Some("ˈkɒment".to_string()),
+                    us_speech: None,
+                    uk_speech: None,
+                    senses: vec![],
+                },
+                web: vec![YdWeb {
+                    key: "No Comment".to_string(),
+                    value: vec!["不予置评".to_string()],
+                }],
+                exams: vec![],
+                examples: vec![
+                    YdExample {
+                        sentence: "He declined to comment.".to_string(),
+                        translation: "他拒绝评论。".to_string(),
+                    },
+                    YdExample {
+                        sentence: "No comment.".to_string(),
+                        translation: "无可奉告。".to_string(),
+                    },
+                ],
+                collins: vec![],
+                ce_senses: vec![],
+                phrases: vec![],
+                forms: vec![],
+                synonyms: vec![],
+                antonyms: vec![],
+                lemma: None,
+                translated: false,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_speech_url() {
+        assert_eq!(
+            "https://dict.youdao.com/dictvoice?audio=give+up&type=1",
+            Accent::Uk.speech_url("give up")
+        );
+    }
+
+    #[test]
+    fn test_explain_accent() {
+        let fmt = HtmlFormatter::new(false);
+        let first_line = |accent| {
+            comment()
+                .explain_with(
+                    &fmt,
+                    &ExplainOptions {
+                        accent,
+                        ..Default::default()
+                    },
+                )
+                .lines()
+                .next()
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(
+            r#"<u>comment</u>  UK: [<span color="goldenrod">ˈkɒment</span>], US: [<span color="goldenrod">ˈkɑːment</span>] 评论"#,
+            first_line(None)
+        );
+        assert_eq!(
+            r#"<u>comment</u>  US: [<span color="goldenrod">ˈkɑːment</span>], UK: [ˈkɒment] 评论"#,
+            first_line(Some(Accent::Us))
+        );
+
+        let explanation = comment().explain_with(
+            &PlainFormatter::new(false),
+            &ExplainOptions {
+                accent: Some(Accent::Uk),
+                phonetics: PhoneticStyle::Respell,
+                ..Default::default()
+            },
+        );
+        assert!(explanation.starts_with("comment  UK: [KOMENT], US: [KAHMENT]"));
+    }
+
+    #[test]
+    fn test_parse_examples() {
+        let html = Html::parse_document(
+            r#"<div class="blng_sents_part dict-module"><div class="trans-container"><ul>
+            <li class="mcols-layout"><div class="col1">1.</div><div class="col2">
+              <div class="word-exp"><div class="sen-eng">He declined to <b>comment</b>.</div></div>
+              <div class="word-exp"><div class="sen-ch">他拒绝评论。</div></div>
+              <div class="secondary">《柯林斯英汉双解大词典》</div>
+            </div></li>
+            <li class="mcols-layout"><div class="col2"><div class="sen-eng">Untranslated.</div></div></li>
+            </ul></div></div>"#,
+        );
+
+        let examples = YdResponse::parse_examples(&Sections::find(&html).examples);
+        assert_eq!(1, examples.len());
+        assert_eq!("He declined to comment.", examples[0].sentence);
+        assert_eq!("他拒绝评论。", examples[0].translation);
+    }
+
+    #[test]
+    fn test_explain_examples() {
+        let fmt = HtmlFormatter::new(false);
+        let options = ExplainOptions {
+            examples: 1,
+            ..Default::default()
+        };
+
+        let explanation = comment().explain_with(&fmt, &options);
+        assert!(explanation.contains("He declined to comment."));
+        assert!(!explanation.contains("No comment."));
+        assert!(!comment().explain(&fmt).contains("Examples:"));
+    }
+
+    #[test]
+    fn test_explain_sections() {
+        let fmt = PlainFormatter::new(false);
+        let mut resp = comment();
+        let inner = resp.inner.as_mut().unwrap();
+        inner.forms = vec![YdWordForm {
+            name: "复数".to_string(),
+            value: "comments".to_string(),
+        }];
+        inner.synonyms = vec![YdThesaurus {
+            meaning: None,
+            words: vec!["remark".to_string()],
+        }];
+
+        let explanation = resp.explain(&fmt);
+        assert!(!explanation.contains("Forms:") && !explanation.contains("Synonyms:"));
+
+        let options = ExplainOptions {
+            forms: true,
+            synonyms: true,
+            ..Default::default()
+        };
+        let explanation = resp.explain_with(&fmt, &options);
+        assert!(explanation.contains("Forms: 复数 comments"));
+        assert!(explanation.contains("remark"));
+    }
+
+    #[test]
+    fn test_accessors() {
+        let resp = comment();
+        assert_eq!("comment", resp.query());
+        assert_eq!(vec!["评论"], resp.translation());
+        assert_eq!(Some("ˈkɒment"), resp.accent_phonetic(Accent::Uk));
+        assert_eq!(Some("ˈkɑːment"), resp.accent_phonetic(Accent::Us));
+        assert_eq!("No Comment", resp.web()[0].key);
+        assert!(resp.lemma().is_none() && resp.lookup().is_none());
+        let mut resp = comment();
+        resp.inner.as_mut().unwrap().basic.phonetic = None;
+        assert_eq!(Some("ˈkɑːment"), resp.phonetic());
+        resp.inner.as_mut().unwrap().basic.us_phonetic = None;
+        assert_eq!(Some("ˈkɒment"), resp.phonetic());
+        // entries without web references are still dictionary entries
+        let mut resp = comment();
+        resp.inner.as_mut().unwrap().web.clear();
+        assert!(!resp.is_translation());
+
+        let resp = YdResponse::fake("hello", "你好");
+        assert!(resp.accent_phonetic(Accent::Us).is_none());
+        assert!(resp.senses().is_empty() && resp.phrases().is_empty());
+        assert_eq!(Lang::Zh, resp.lookup().unwrap().to);
+    }
+
+    #[test]
+    fn test_segments() {
+        let resp = comment();
+        let lines = resp.segments(&ExplainOptions::default());
+        assert_eq!(Style::Underline.of("comment"), lines[0][0]);
+        assert_eq!(
+            vec![Style::Plain.of("     * "), Style::Yellow.of("No Comment")],
+            lines[2]
+        );
+        assert!(lines.iter().flatten().all(|x| !x.text.contains('\n')));
+    }
+
+    #[test]
+    fn test_explain_to() {
+        let fmt = PlainFormatter::new(false);
+        let options = ExplainOptions::default();
+
+        let mut out = String::from("> ");
+        comment().explain_to(&mut out, &fmt, &options).unwrap();
+        assert_eq!(format!("> {}", comment().explain(&fmt)), out);
+        assert!(!out.ends_with('\n'));
+
+        let mut out = String::new();
+        YdResponse::fake("xyzzy", "咒语")
+            .explain_to(&mut out, &fmt, &options)
+            .unwrap();
+        assert_eq!("xyzzy\n  Translation:\n    咒语", out);
+    }
+
+    #[test]
+    fn test_labels() {
+        let options = ExplainOptions {
+            labels: BTreeMap::from([("translation".to_string(), "译:".to_string())]),
+            ..Default::default()
+        };
+        assert_eq!(
+            "xyzzy\n  译:\n    咒语",
+            YdResponse::fake("xyzzy", "咒语").explain_with(&PlainFormatter::new(false), &options)
+        );
+    }
+
+    #[test]
+    fn test_parse_collins() {
+        let html = Html::parse_document(
+            r#"<div class="collins"><div class="trans-container"><ul>
+            <li><div class="col2">
+              <div class="title"><span class="pos">N-VAR</span> A <b>comment</b> is something that you say which expresses your opinion. 评论</div>
+              <div class="exampleLists"><div class="examples">
+                <p class="sen-eng">He made his comments at a news conference.</p>
+                <p class="sen-ch">他在新闻发布会上发表了评论。</p>
+              </div></div>
+            </div></li>
+            </ul></div></div>"#,
+        );
+
+        let senses = YdResponse::parse_dict_senses(&Sections::find(&html).collins);
+        assert_eq!(1, senses.len());
+        assert_eq!(Some("N-VAR"), senses[0].pos.as_deref());
+        assert_eq!(
+            "A comment is something that you say which expresses your opinion. 评论",
+            senses[0].definition
+        );
+        assert_eq!(
+            "他在新闻发布会上发表了评论。",
+            senses[0].examples[0].translation
+        );
+    }
+
+    #[test]
+    fn test_parse_phrases() {
+        let html = Html::parse_document(
+            r#"<div class="phrs dict-module"><div class="trans-container"><ul>
+            <li class="mcols-layout"><div class="col1"><a class="point">no comment</a></div>
+              <div class="col2"><p class="secondary">无可奉告；不予置评</p></div></li>
+            <li class="mcols-layout"><div class="col1"><a class="point">comment on</a></div>
+              <div class="col2"><p class="secondary">评论</p></div></li>
+            </ul></div></div>"#,
+        );
+
+        let phrases = YdResponse::parse_phrases(&Sections::find(&html).phrases);
+        assert_eq!(2, phrases.len());
+        assert_eq!("no comment", phrases[0].phrase);
+        assert_eq!("无可奉告；不予置评", phrases[0].translation);
+    }
+
+    #[test]
+    fn test_lemma_of() {
+        assert_eq!(Some("go".to_string()), lemma_of("v. 去（go 的过去式）"));
+        assert_eq!(
+            Some("child".to_string()),
+            lemma_of("n. 孩子（child的复数）")
+        );
+        assert_eq!(None, lemma_of("n. 评论；意见"));
+    }
+
+    #[test]
+    fn test_parse_forms() {
+        let html = Html::parse_document(
+            r#"<div class="word-wfs-less"><ul>
+            <li class="word-wfs-cell-less"><span class="wfs-name">复数</span><span class="transformation">comments</span></li>
+            <li class="word-wfs-cell-less"><span class="wfs-name">过去式</span><span class="transformation">commented</span></li>
+            </ul></div>"#,
+        );
+
+        let forms = YdResponse::parse_forms(&Sections::find(&html).forms);
+        assert_eq!(2, forms.len());
+        assert_eq!(
+            ("过去式", "commented"),
+            (forms[1].name.as_str(), forms[1].value.as_str())
+        );
+    }
+
+    #[test]
+    fn test_parse_thesaurus() {
+        let html = Html::parse_document(
+            r#"<div class="syno dict-module"><div class="trans-container"><ul>
+            <li class="mcols-layout"><div class="col1">n. 评论</div>
+              <div class="col2"><a>remark</a>, <a>observation</a></div></li>
+            </ul></div></div>
+            <div class="antonym dict-module"><div class="trans-container"><ul>
+            <li class="mcols-layout"><div class="col2"><a>silence</a></div></li>
+            </ul></div></div>"#,
+        );
+
+        let synonyms = YdResponse::parse_thesaurus(&Sections::find(&html).synonyms);
+        assert_eq!(Some("n. 评论"), synonyms[0].meaning.as_deref());
+        assert_eq!(vec!["remark", "observation"], synonyms[0].words);
+
+        let antonyms = YdResponse::parse_thesaurus(&Sections::find(&html).antonyms);
+        assert_eq!(
+            (None, vec!["silence".to_string()]),
+            (antonyms[0].meaning.clone(), antonyms[0].words.clone())
+        );
+    }
+
+    #[test]
+    fn test_parse_exams() {
+        let html = Html::parse_document(
+            r#"<div class="exam_type"><span class="exam_type-value">CET4</span>
+            <span class="exam_type-value">考研</span></div>"#,
+        );
+
+        assert_eq!(
+            vec!["CET4", "考研"],
+            YdResponse::parse_exams(&Sections::find(&html).exams)
+        );
+    }
+
+    #[test]
+    fn test_sense_labels() {
+        assert_eq!(
+            YdSense {
+                pos: Some("n.".to_string()),
+                labels: vec!["〔计〕".to_string(), "〈口〉".to_string()],
+                meaning: "程序；节目".to_string(),
+            },
+            YdSense::new(Some("n."), "〔计〕〈口〉 程序；节目")
+        );
+        assert!(YdSense::new(None, "评论").labels.is_empty());
+
+        let sense = YdSense::new(Some("n."), "〔计〕〈口〉 程序；节目");
+        assert!(sense.has_label("计") && sense.has_label("〈口〉"));
+        assert!(!sense.has_label("医"));
+    }
+
+    #[test]
+    fn test_selectors() {
+        // compiles every selector, which panics on invalid ones
+        let body = [
+            "typo-rel",
+            "phone_con",
+            "basic",
+            "web_trans",
+            "exam_type",
+            "blng_sents_part",
+            "collins",
+            "ce_new",
+            "phrs",
+            "word-wfs-less",
+            "syno",
+            "antonym",
+        ]
+        .map(|x| format!(r#"<div class="{x}"></div>"#))
+        .concat();
+        for from in [Lang::En, Lang::Zh, Lang::Ja] {
+            let dir = Direction::resolve("x", Some(from), None).unwrap();
+            assert!(YdResponse::parses_pages(dir));
+            assert!(YdResponse::from_html(&body, "x", dir).is_ok());
+        }
+        for from in [Lang::Ko, Lang::Fr, Lang::De, Lang::Es, Lang::Ru] {
+            let dir = Direction::resolve("x", Some(from), None).unwrap();
+            assert!(!YdResponse::parses_pages(dir));
+        }
+    }
+
+    #[test]
+    fn test_sections() {
+        let html = Html::parse_document(
+            r#"<div class="basic"><div class="basic"><ul>
+            <li class="word-exp"><span class="pos">n.</span><span class="trans">评论</span></li>
+            </ul></div></div>
+            <div class="web_trans"><ul>
+            <li><div class="col2"><a class="point">no comment</a><p class="sen-phrase">无可奉告 ; 不予置评</p></div></li>
+            </ul></div>"#,
+        );
+        let sections = Sections::find(&html);
+        assert_eq!(1, sections.basic.len());
+        assert!(!sections.no_data);
+
+        let resp = YdResponse::en2zh(&sections);
+        assert_eq!(vec!["n. 评论"], resp.basic.explains);
+        assert_eq!("no comment", resp.web[0].key);
+        assert_eq!(vec!["无可奉告", "不予置评"], resp.web[0].value);
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let resp = YdResponse::from_html(
+            r#"<div class="error-wrapper"><div class="no-data-prompt">没有找到</div>
+            <ul><li class="typo-rel"><a class="title">comment</a><span>评论</span></li>
+            <li class="typo-rel"><a class="title">commend</a><span>称赞</span></li></ul></div>"#,
+            "comemnt",
+            Direction::detect("comemnt"),
+        )
+        .unwrap();
+
+        assert!(!resp.has_result());
+        assert_eq!(vec!["comment", "commend"], resp.suggestions());
+        assert!(
+            resp.explain(&HtmlFormatter::new(false))
+                .contains("Did you mean:")
+        );
+    }
+
+    #[test]
+    fn test_empty_page() {
+        let dir = Direction::detect("comment");
+        let resp = YdResponse::from_html("<p>维护中</p>", "comment", dir).unwrap();
+        assert!(!resp.has_result());
+        assert!(matches!(
+            YdResponse::from_html("<p>请输入验证码</p>", "comment", dir),
+            Err(YdcvError::Captcha)
+        ));
+    }
+
+    #[test]
+    fn test_from_html_file() {
+        let path = std::env::temp_dir().join(format!("ydcv-test-{}.html", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"<div class="phone_con"><div class="per-phone"><span>英</span><span class="phonetic">/ˈkɒment/</span></div></div>
+            <div class="basic"><ul>
+            <li class="word-exp"><span class="pos">n.</span><span class="trans">评论</span></li>
+            </ul></div>"#,
+        )
+        .unwrap();
+        let resp = YdResponse::from_html_file(&path, "comment").unwrap();
+        assert_eq!(vec!["评论"], resp.translation());
+        assert_eq!(Some("ˈkɒment"), resp.accent_phonetic(Accent::Uk));
+
+        std::fs::write(&path, r#"<div class="no-data-prompt">没有找到</div>"#).unwrap();
+        let resp = YdResponse::from_html_file(&path, "hakunamatata").unwrap();
+        assert!(!resp.has_result());
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            YdResponse::from_html_file(&path, "x"),
+            Err(YdcvError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_lookup_json() {
+        let dir = Direction::resolve("bonjour", Some(Lang::Fr), None).unwrap();
+        let resp = YdResponse::from_translation("bonjour", vec!["你好".to_string()], dir);
+        let json = serde_json::to_value(&resp).unwrap();
+        assert_eq!("en", json["lookup"]["detected"]);
+        assert_eq!("fr", json["lookup"]["from"]);
+        assert_eq!("zh", json["lookup"]["to"]);
+        assert!(resp.is_translation());
+
+        // responses cached before the field existed
+        let resp: YdResponse = serde_json::from_str(r#"{"query":"hi","translation":["嗨"],"basic":{"explains":[],"phonetic":null,"us_phonetic":null,"uk_phonetic":null},"web":[]}"#).unwrap();
+        assert!(resp.lookup.is_none() && !resp.is_translation());
+    }
+
+    #[test]
+    fn test_zh2en() {
+        let html = Html::parse_document(
+            r#"<div class="basic"><div class="col2"><ul>
+            <li class="word-exp"><a class="point">comment</a><span class="word-exp_tran">n. 评论；意见</span></li>
+            <li class="word-exp"><a class="point">remark</a><span class="word-exp_tran">n. 评论；谈论</span></li>
+            </ul></div></div>
+            <div class="ce_new"><div class="trans-container"><ul>
+            <li><div class="title">comment; remark</div>
+              <div class="exampleLists"><div class="examples">
+                <p class="sen-eng">Make comments on a film</p><p class="sen-ch">评论一部电影</p>
+              </div></div></li>
+            </ul></div></div>"#,
+        );
+
+        let resp = YdResponse::zh2en(&Sections::find(&html));
+        assert_eq!(vec!["comment", "remark"], resp.translation);
+        assert_eq!("remark n. 评论；谈论", resp.basic.explains[1]);
+
+        let senses = YdResponse::parse_dict_senses(&Sections::find(&html).ce_senses);
+        assert_eq!("comment; remark", senses[0].definition);
+        assert_eq!("评论一部电影", senses[0].examples[0].translation);
+    }
+
+    #[test]
+    fn test_ja2zh() {
+        let html = Html::parse_document(
+            r#"<div class="phone_con"><div class="per-phone"><span class="phonetic">べんきょう</span></div></div>
+            <div class="basic"><ul>
+            <li class="word-exp"><span class="pos">名·自他サ</span><span class="trans">学习，用功；经验</span></li>
+            </ul></div>"#,
+        );
+
+        let resp = YdResponse::ja2zh(&Sections::find(&html));
+        assert_eq!(Some("べんきょう"), resp.basic.phonetic.as_deref());
+        assert_eq!(vec!["学习"], resp.translation);
+        assert_eq!("名·自他サ 学习，用功；经验", resp.basic.explains[0]);
+    }
+
+    #[test]
+    fn test_explain_romanize() {
+        let html = Html::parse_document(
+            r#"<div class="phone_con"><div class="per-phone"><span class="phonetic">べんきょう</span></div></div>
+            <div class="basic"><ul>
+            <li class="word-exp"><span class="pos">名</span><span class="trans">学习</span></li>
+            </ul></div>"#,
+        );
+        let resp = YdResponse {
+            query: "勉強".to_string(),
+            suggestions: vec![],
+            lookup: None,
+            inner: Some(YdResponseInner {
+                web: vec![YdWeb {
+                    key: "勉強会".to_string(),
+                    value: vec!["学习会".to_string()],
+                }],
+                ..YdResponse::ja2zh(&Sections::find(&html))
+            }),
+        };
+        let options = ExplainOptions {
+            romanize: true,
+            ..Default::default()
+        };
+
+        let explanation = resp.explain_with(&PlainFormatter::new(false), &options);
+        assert!(explanation.contains("Romanization: benkyou"));
+        assert!(explanation.contains("     * 名 学习\n       xué xí"));
+        let explanation = resp.explain_with(&PlainFormatter::new(false), &Default::default());
+        assert!(!explanation.contains("benkyou"));
+    }
+
+    #[test]
+    fn test_explain_en_en() {
+        let mut resp = comment();
+        resp.inner.as_mut().unwrap().collins = vec![YdDictSense {
+            pos: Some("N-VAR".to_string()),
+            definition: "A comment is something that you say. 评论".to_string(),
+            examples: vec![],
+        }];
+        let options = ExplainOptions {
+            en_en: true,
+            examples: 3,
+            ..Default::default()
+        };
+
+        let explanation = resp.explain_with(&PlainFormatter::new(false), &options);
+        assert!(explanation.contains("1. [N-VAR] A comment is something that you say."));
+        assert!(explanation.contains("He declined to comment."));
+        assert!(!explanation.contains('评'));
+        assert!(!explanation.contains('他'));
+    }
+}